@@ -1,5 +1,16 @@
 fn main() {
     println!("cargo:rerun-if-changed=ffi/minimp3.c");
+    println!("cargo:rerun-if-env-changed=RMP3_MINIMP3_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=RMP3_MINIMP3_LIB");
+
+    // a prebuilt minimp3, which has to be built with the same defines as below
+    if let Some(dir) = std::env::var_os("RMP3_MINIMP3_LIB_DIR") {
+        let lib = std::env::var("RMP3_MINIMP3_LIB").unwrap_or_else(|_| String::from("static=minimp3"));
+        println!("cargo:rustc-link-search=native={}", std::path::Path::new(&dir).display());
+        println!("cargo:rustc-link-lib={}", lib);
+        return;
+    }
+
     let mut build = cc::Build::new();
 
     build.include("ffi/minimp3");
@@ -9,13 +20,30 @@ fn main() {
     }
     if cfg!(not(feature = "simd")) {
         build.define("MINIMP3_NO_SIMD", None);
+    } else if target_has("wasm32", "simd128") {
+        // minimp3 has no handwritten SIMD for WebAssembly, but this lets clang vectorize it
+        build.flag("-msimd128");
+    }
+    if cfg!(feature = "minimp3-ex") {
+        // file IO is bridged from Rust instead
+        build.define("RMP3_EX", None).define("MINIMP3_NO_STDIO", None);
     }
     if cfg!(not(feature = "mp1-mp2")) {
         build.define("MINIMP3_ONLY_MP3", None);
     }
+    if cfg!(feature = "nonstandard-but-logical") {
+        build.define("MINIMP3_NONSTANDARD_BUT_LOGICAL", None);
+    }
 
     build
         .define("MINIMP3_IMPLEMENTATION", None)
         .file("ffi/minimp3.c")
         .compile("minimp3");
 }
+
+/// Checks whether the target is of `arch` and has `feature` enabled.
+fn target_has(arch: &str, feature: &str) -> bool {
+    let target_arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target_features = std::env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    target_arch == arch && target_features.split(',').any(|x| x == feature)
+}
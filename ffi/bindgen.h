@@ -1,2 +1,2 @@
 /* include file for rust-bindgen */
-#include <minimp3.h>
+#include <minimp3_ex.h>
@@ -1,2 +1,6 @@
 /* minimp3 library for cc */
+#ifdef RMP3_EX
+#include <minimp3_ex.h>
+#else
 #include <minimp3.h>
+#endif
@@ -0,0 +1,170 @@
+//! Hashing of decoded audio.
+
+use crate::{Audio, PcmSink, Sample};
+
+/// Per-round shift amounts of MD5.
+const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// Per-step constants of MD5, as in, the integer part of `abs(sin(i + 1)) * 2^32`.
+#[rustfmt::skip]
+const CONSTANTS: [u32; 64] = [
+    0xD76AA478, 0xE8C7B756, 0x242070DB, 0xC1BDCEEE,
+    0xF57C0FAF, 0x4787C62A, 0xA8304613, 0xFD469501,
+    0x698098D8, 0x8B44F7AF, 0xFFFF5BB1, 0x895CD7BE,
+    0x6B901122, 0xFD987193, 0xA679438E, 0x49B40821,
+    0xF61E2562, 0xC040B340, 0x265E5A51, 0xE9B6C7AA,
+    0xD62F105D, 0x02441453, 0xD8A1E681, 0xE7D3FBC8,
+    0x21E1CDE6, 0xC33707D6, 0xF4D50D87, 0x455A14ED,
+    0xA9E3E905, 0xFCEFA3F8, 0x676F02D9, 0x8D2A4C8A,
+    0xFFFA3942, 0x8771F681, 0x6D9D6122, 0xFDE5380C,
+    0xA4BEEA44, 0x4BDECFA9, 0xF6BB4B60, 0xBEBFBC70,
+    0x289B7EC6, 0xEAA127FA, 0xD4EF3085, 0x04881D05,
+    0xD9D4D039, 0xE6DB99E5, 0x1FA27CF8, 0xC4AC5665,
+    0xF4292244, 0x432AFF97, 0xAB9423A7, 0xFC93A039,
+    0x655B59C3, 0x8F0CCC92, 0xFFEFF47D, 0x85845DD1,
+    0x6FA87E4F, 0xFE2CE6E0, 0xA3014314, 0x4E0811A1,
+    0xF7537E82, 0xBD3AF235, 0x2AD7D2BB, 0xEB86D391,
+];
+
+/// A running MD5 hash of decoded samples, for checking that two files decode to identical audio,
+/// like the MD5 signature that FLAC stores.
+///
+/// Samples are hashed interleaved and little-endian as they're decoded, so for 16-bit samples,
+/// this is the same as FLAC's signature of the same audio. With the `float` feature,
+/// the bytes of the floats are hashed instead, which depend on minimp3's exact arithmetic.
+///
+/// MD5 isn't secure against deliberate collisions, which doesn't matter for verifying decodes.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{analysis::PcmHash, Decoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut hash = PcmHash::new();
+/// Decoder::new(&mp3).decode_into(&mut hash);
+/// println!("{:02x?}", hash.finish());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcmHash {
+    state: [u32; 4],
+
+    // the current partial block, and the length hashed in total, in bytes
+    block: [u8; 64],
+    len: u64,
+}
+
+impl PcmHash {
+    /// Constructs a new `PcmHash` of nothing yet.
+    pub fn new() -> Self {
+        Self { state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476], block: [0; 64], len: 0 }
+    }
+
+    /// Hashes interleaved samples.
+    pub fn process(&mut self, samples: &[Sample]) {
+        for &x in samples {
+            self.update(&x.to_le_bytes());
+        }
+    }
+
+    /// Finishes the hash, returning the 16-byte MD5 digest.
+    pub fn finish(mut self) -> [u8; 16] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.len % 64 != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_le_bytes());
+
+        let mut digest = [0; 16];
+        for (dest, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            dest.copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let start = (self.len % 64) as usize;
+            let len = data.len().min(64 - start);
+            self.block[start..start + len].copy_from_slice(&data[..len]);
+            self.len += len as u64;
+            data = &data[len..];
+            if start + len == 64 {
+                self.compress();
+            }
+        }
+    }
+
+    /// Mixes the full current block into the state.
+    fn compress(&mut self) {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(self.block.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(CONSTANTS[i]).wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i / 16 * 4 + i % 4]));
+        }
+        for (state, x) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(x);
+        }
+    }
+}
+
+impl Default for PcmHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PcmSink for PcmHash {
+    #[inline]
+    fn write_samples(&mut self, _audio: &Audio<'_, '_>, samples: &[Sample]) {
+        self.process(samples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PcmHash;
+
+    fn md5(data: &[u8]) -> [u8; 16] {
+        let mut hash = PcmHash::new();
+        hash.update(data);
+        hash.finish()
+    }
+
+    fn hex(digest: [u8; 16]) -> String {
+        digest.iter().map(|x| format!("{:02x}", x)).collect()
+    }
+
+    #[test]
+    fn md5_vectors() {
+        assert_eq!(hex(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex(md5(&[b'a'; 1000])), "cabe45dcc9ae5b66ba86600cca6b8ba8");
+    }
+
+    #[test]
+    #[cfg(not(feature = "float"))]
+    fn samples() {
+        let mut hash = PcmHash::new();
+        hash.process(&[0, 16384]);
+        hash.process(&[-32768]);
+        assert_eq!(hash.finish(), md5(&[0x00, 0x00, 0x00, 0x40, 0x00, 0x80]));
+    }
+}
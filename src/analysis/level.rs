@@ -0,0 +1,165 @@
+//! Peak and RMS level measurement.
+
+use super::to_f64;
+use crate::Sample;
+
+/// Gets the peak amplitude of samples, where 1.0 is full scale.
+///
+/// With the `simd` feature, this uses SSE2 on x86 targets which have it.
+pub fn peak(samples: &[Sample]) -> f64 {
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+    {
+        // SAFETY: SSE2 is known to be available at compile-time.
+        unsafe { sse2::peak(samples) }
+    }
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2")))]
+    {
+        samples.iter().fold(0.0, |peak: f64, &x| peak.max(to_f64(x).abs()))
+    }
+}
+
+/// Gets the RMS (root mean square) level of samples, where 1.0 is a full scale square wave.
+pub fn rms(samples: &[Sample]) -> f64 {
+    match samples.len() {
+        0 => 0.0,
+        len => (sum_of_squares(samples) / len as f64).sqrt(),
+    }
+}
+
+/// Sums up squares eight lanes at a time, which the compiler vectorizes.
+fn sum_of_squares(samples: &[Sample]) -> f64 {
+    let chunks = samples.chunks_exact(8);
+    let rest = chunks.remainder().iter().map(|&x| to_f64(x) * to_f64(x)).sum::<f64>();
+    let mut lanes = [0.0; 8];
+    for chunk in chunks {
+        for (lane, &x) in lanes.iter_mut().zip(chunk) {
+            *lane += to_f64(x) * to_f64(x);
+        }
+    }
+    lanes.iter().sum::<f64>() + rest
+}
+
+/// Running peak and RMS level over everything given to it, such as for a meter.
+///
+/// For the level of a single frame, use [`peak`] and [`rms`] on its samples instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Level {
+    peak: f64,
+    samples: u64,
+    sum: f64,
+}
+
+impl Level {
+    /// Constructs a new `Level` with nothing measured yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures more samples, in any channel layout.
+    pub fn process(&mut self, samples: &[Sample]) {
+        self.peak = self.peak.max(peak(samples));
+        self.sum += sum_of_squares(samples);
+        self.samples += samples.len() as u64;
+    }
+
+    /// Gets the peak amplitude of everything measured so far, where 1.0 is full scale.
+    #[inline]
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+
+    /// Gets the RMS level of everything measured so far, where 1.0 is a full scale square wave.
+    #[inline]
+    pub fn rms(&self) -> f64 {
+        match self.samples {
+            0 => 0.0,
+            samples => (self.sum / samples as f64).sqrt(),
+        }
+    }
+
+    /// Forgets everything measured so far, such as for the next meter period.
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+mod sse2 {
+    #[cfg(feature = "float")]
+    use super::to_f64;
+    use crate::Sample;
+
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    #[cfg(not(feature = "float"))]
+    pub(super) unsafe fn peak(samples: &[Sample]) -> f64 {
+        let chunks = samples.chunks_exact(8);
+        let rest = chunks.remainder();
+        let (mut max, mut min) = (_mm_setzero_si128(), _mm_setzero_si128());
+        for chunk in chunks {
+            let x = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            max = _mm_max_epi16(max, x);
+            min = _mm_min_epi16(min, x);
+        }
+        let (mut maxs, mut mins) = ([0i16; 8], [0i16; 8]);
+        _mm_storeu_si128(maxs.as_mut_ptr() as *mut __m128i, max);
+        _mm_storeu_si128(mins.as_mut_ptr() as *mut __m128i, min);
+
+        // widened, since -32768 has no positive counterpart
+        let peak = maxs
+            .iter()
+            .chain(&mins)
+            .chain(rest)
+            .map(|&x| i32::from(x).abs())
+            .max()
+            .unwrap_or(0);
+        f64::from(peak) / 32768.0
+    }
+
+    #[cfg(feature = "float")]
+    pub(super) unsafe fn peak(samples: &[Sample]) -> f64 {
+        let chunks = samples.chunks_exact(4);
+        let rest = chunks.remainder();
+        let abs = _mm_castsi128_ps(_mm_set1_epi32(0x7FFF_FFFF));
+        let mut max = _mm_setzero_ps();
+        for chunk in chunks {
+            max = _mm_max_ps(max, _mm_and_ps(abs, _mm_loadu_ps(chunk.as_ptr())));
+        }
+        let mut lanes = [0.0f32; 4];
+        _mm_storeu_ps(lanes.as_mut_ptr(), max);
+        lanes.iter().chain(rest).fold(0.0, |peak: f64, &x| peak.max(to_f64(x).abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{peak, rms, Level};
+    use crate::Sample;
+
+    #[test]
+    fn level() {
+        #[cfg(not(feature = "float"))]
+        let (low, high): (Sample, Sample) = (-16384, i16::MIN);
+        #[cfg(feature = "float")]
+        let (low, high): (Sample, Sample) = (-0.5, -1.0);
+
+        let mut samples = [low; 19];
+        assert_eq!(peak(&samples), 0.5);
+        assert_eq!(rms(&samples), 0.5);
+        samples[17] = high;
+        assert_eq!(peak(&samples), 1.0);
+        assert_eq!(peak(&samples[..17]), 0.5);
+
+        let mut level = Level::new();
+        level.process(&[low; 3]);
+        level.process(&[]);
+        assert_eq!((level.peak(), level.rms()), (0.5, 0.5));
+        level.reset();
+        assert_eq!((level.peak(), level.rms()), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,290 @@
+//! EBU R128 (ITU-R BS.1770) loudness measurement.
+
+use super::to_f64;
+use crate::{Decoder, Frame, PcmStorage, Sample};
+use std::f64::consts::PI;
+
+/// Gating blocks are 400ms long, overlapping by 75%, so they're made of 100ms sub-blocks.
+const SUB_BLOCKS_PER_SECOND: u64 = 10;
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Gating thresholds for the integrated loudness.
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Oversampling used for finding the true peak, and the interpolation filter's length.
+const OVERSAMPLING: usize = 4;
+const INTERPOLATION_TAPS: usize = 49;
+const INTERPOLATION_HISTORY: usize = INTERPOLATION_TAPS / OVERSAMPLING + 1;
+
+/// Converts a channel-summed mean square to LUFS.
+#[inline]
+fn lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// A second-order IIR filter section.
+#[derive(Clone, Copy)]
+struct Biquad {
+    a: [f64; 2],
+    b: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { a, b, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Builds the two stages of the K-weighting filter for a sample rate,
+/// a high shelf modelling the head followed by a high-pass (the "RLB" curve).
+///
+/// These are derived from the analog prototype rather than the 48 kHz coefficients in the
+/// standard, so that every sample rate is handled (as in libebur128).
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(sample_rate);
+
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+    [shelf, high_pass]
+}
+
+/// Per-channel filter and interpolation state.
+#[derive(Clone)]
+struct Channel {
+    filters: [Biquad; 2],
+    history: [f64; INTERPOLATION_HISTORY],
+}
+
+/// Incremental EBU R128 loudness measurement, giving the momentary, short-term
+/// and integrated loudness in LUFS, and the true peak.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{analysis::Loudness, Decoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// if let Some(loudness) = Loudness::scan(&mut Decoder::new(&mp3)) {
+///     if let Some(integrated) = loudness.integrated() {
+///         println!("{:.1} LUFS, {:.1} dBTP", integrated, loudness.true_peak_db());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Loudness {
+    blocks: Vec<f64>,
+    channels: Vec<Channel>,
+    interpolation: [f64; INTERPOLATION_TAPS],
+    sample_rate: u32,
+    samples: u64,
+    sub_block: (f64, usize),
+    sub_blocks: [(f64, usize); SHORT_TERM_SUB_BLOCKS],
+    sub_block_count: usize,
+    true_peak: f64,
+}
+
+impl Loudness {
+    /// Constructs a new `Loudness` for measuring audio of the given format.
+    ///
+    /// Returns `None` if the sample rate is zero, or if there's more than two channels.
+    pub fn new(sample_rate: u32, channels: u16) -> Option<Self> {
+        if sample_rate == 0 || !(1..=2).contains(&channels) {
+            return None;
+        }
+
+        // windowed sinc low-pass at the original Nyquist frequency, as in libebur128
+        let mut interpolation = [0.0; INTERPOLATION_TAPS];
+        for (i, tap) in interpolation.iter_mut().enumerate() {
+            let m = i as f64 - (INTERPOLATION_TAPS - 1) as f64 / 2.0;
+            let x = m * PI / OVERSAMPLING as f64;
+            let sinc = if m.abs() > 1e-6 { x.sin() / x } else { 1.0 };
+            let window = 0.5 * (1.0 - (2.0 * PI * i as f64 / (INTERPOLATION_TAPS - 1) as f64).cos());
+            *tap = sinc * window;
+        }
+
+        let channel = Channel {
+            filters: k_weighting(sample_rate),
+            history: [0.0; INTERPOLATION_HISTORY],
+        };
+        Some(Self {
+            blocks: Vec::new(),
+            channels: vec![channel; channels as usize],
+            interpolation,
+            sample_rate,
+            samples: 0,
+            sub_block: (0.0, 0),
+            sub_blocks: [(0.0, 0); SHORT_TERM_SUB_BLOCKS],
+            sub_block_count: 0,
+            true_peak: 0.0,
+        })
+    }
+
+    /// Decodes every remaining frame of `decoder`, returning the measurement of all of it.
+    ///
+    /// The format of the first audio frame is used, and any frames in other formats are ignored.
+    /// Returns `None` if there's no audio at all.
+    pub fn scan<P: PcmStorage>(decoder: &mut Decoder<'_, P>) -> Option<Self> {
+        let mut loudness: Option<Self> = None;
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                if loudness.is_none() {
+                    loudness = Some(Self::new(audio.sample_rate(), audio.channels())?);
+                }
+                if let Some(loudness) = &mut loudness {
+                    if (audio.sample_rate(), audio.channels() as usize) == (loudness.sample_rate, loudness.channels.len()) {
+                        loudness.process(audio.samples());
+                    }
+                }
+            }
+        }
+        loudness
+    }
+
+    /// Measures interleaved samples, in the format given in [`new`](Self::new).
+    pub fn process(&mut self, samples: &[Sample]) {
+        for frame in samples.chunks_exact(self.channels.len()) {
+            let mut energy = 0.0;
+            for (channel, &sample) in self.channels.iter_mut().zip(frame) {
+                let x = to_f64(sample);
+                let shelved = channel.filters[0].process(x);
+                let y = channel.filters[1].process(shelved);
+                energy += y * y;
+
+                channel.history.copy_within(..INTERPOLATION_HISTORY - 1, 1);
+                channel.history[0] = x;
+                for phase in 0..OVERSAMPLING {
+                    let taps = self.interpolation.iter().skip(phase).step_by(OVERSAMPLING);
+                    let y: f64 = taps.zip(&channel.history).map(|(tap, x)| tap * x).sum();
+                    self.true_peak = self.true_peak.max(y.abs());
+                }
+            }
+            self.sub_block.0 += energy;
+            self.sub_block.1 += 1;
+
+            // sub-blocks are rounded to whole samples, which only matters at 11.025 kHz
+            self.samples += 1;
+            let next = (self.sub_block_count as u64 + 1) * u64::from(self.sample_rate) / SUB_BLOCKS_PER_SECOND;
+            if self.samples >= next {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    /// Gets the integrated (gated) loudness of everything measured so far in LUFS,
+    /// or `None` if nothing passes the gate, such as if it's all silence.
+    pub fn integrated(&self) -> Option<f64> {
+        let mean = |threshold: f64| {
+            let (sum, count) = self
+                .blocks
+                .iter()
+                .filter(|&&x| lufs(x) > threshold)
+                .fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+            if count == 0 { None } else { Some(sum / f64::from(count)) }
+        };
+        let relative = lufs(mean(ABSOLUTE_GATE)?) + RELATIVE_GATE;
+        mean(relative.max(ABSOLUTE_GATE)).map(lufs)
+    }
+
+    /// Gets the momentary loudness (of the last 400ms) in LUFS, or `None` if less than that was measured.
+    pub fn momentary(&self) -> Option<f64> {
+        self.window(MOMENTARY_SUB_BLOCKS).map(lufs)
+    }
+
+    /// Gets the short-term loudness (of the last 3 seconds) in LUFS, or `None` if less than that was measured.
+    pub fn short_term(&self) -> Option<f64> {
+        self.window(SHORT_TERM_SUB_BLOCKS).map(lufs)
+    }
+
+    /// Gets the true peak amplitude of everything measured so far, where 1.0 is full scale,
+    /// which is found by oversampling 4 times to catch peaks between samples.
+    #[inline]
+    pub fn true_peak(&self) -> f64 {
+        self.true_peak
+    }
+
+    /// Gets the true peak of everything measured so far in dBTP.
+    #[inline]
+    pub fn true_peak_db(&self) -> f64 {
+        20.0 * self.true_peak.log10()
+    }
+
+    fn finish_sub_block(&mut self) {
+        self.sub_blocks[self.sub_block_count % SHORT_TERM_SUB_BLOCKS] = self.sub_block;
+        self.sub_block_count += 1;
+        self.sub_block = (0.0, 0);
+        if let Some(energy) = self.window(MOMENTARY_SUB_BLOCKS) {
+            self.blocks.push(energy);
+        }
+    }
+
+    /// Gets the mean energy of the last `count` sub-blocks, if there's that many.
+    fn window(&self, count: usize) -> Option<f64> {
+        if self.sub_block_count < count {
+            return None;
+        }
+        let (energy, samples) = (1..=count)
+            .map(|i| self.sub_blocks[(self.sub_block_count - i) % SHORT_TERM_SUB_BLOCKS])
+            .fold((0.0, 0), |(energy, samples), x| (energy + x.0, samples + x.1));
+        Some(energy / samples as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Loudness;
+    use crate::Sample;
+
+    #[test]
+    fn sine() {
+        // EBU Tech 3341: a 1 kHz sine at -23 dBFS in both channels measures -23 LUFS
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let samples: Vec<Sample> = (0..48000 * 5)
+            .flat_map(|i| {
+                let x = amplitude * (i as f64 * 1000.0 / 48000.0 * 2.0 * std::f64::consts::PI).sin();
+                #[cfg(not(feature = "float"))]
+                let x = (x * 32768.0).round() as Sample;
+                #[cfg(feature = "float")]
+                let x = x as Sample;
+                vec![x, x]
+            })
+            .collect();
+
+        let mut loudness = Loudness::new(48000, 2).unwrap();
+        assert_eq!(loudness.integrated(), None);
+        loudness.process(&samples);
+        for measured in &[loudness.integrated(), loudness.momentary(), loudness.short_term()] {
+            assert!((measured.unwrap() + 23.0).abs() < 0.1, "{:?}", measured);
+        }
+        assert!((loudness.true_peak_db() + 23.0).abs() < 0.1);
+    }
+}
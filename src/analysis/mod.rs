@@ -0,0 +1,33 @@
+//! Analysis of decoded audio, such as loudness measurement.
+//!
+//! Everything here works on interleaved [`Sample`]s as given by [`Audio::samples`](crate::Audio::samples),
+//! so it can be fed in the same pass as decoding.
+
+mod hash;
+mod level;
+mod loudness;
+mod replaygain;
+mod silence;
+mod waveform;
+
+pub use hash::PcmHash;
+pub use level::{peak, rms, Level};
+pub use loudness::Loudness;
+pub use replaygain::{ReplayGain, TrackGain};
+pub use silence::{SilenceBounds, SilenceDetector};
+pub use waveform::{Peak, Waveform};
+
+use crate::Sample;
+
+/// Converts a sample to a float, where 1.0 is full scale.
+#[inline(always)]
+fn to_f64(sample: Sample) -> f64 {
+    #[cfg(not(feature = "float"))]
+    {
+        f64::from(sample) / 32768.0
+    }
+    #[cfg(feature = "float")]
+    {
+        f64::from(sample)
+    }
+}
@@ -0,0 +1,320 @@
+//! ReplayGain 1.0 track analysis, matching the reference `gain_analysis.c`.
+
+use super::to_f64;
+use crate::{Decoder, Frame, PcmStorage, Sample};
+
+/// Loudness of pink noise at the reference level, which the gain is relative to.
+const PINK_REF: f64 = 64.82;
+
+/// Resolution and range of the loudness histogram.
+const STEPS_PER_DB: f64 = 100.0;
+const MAX_DB: usize = 120;
+
+/// Every block is 50ms long, and the loudest 5% of them determine the loudness.
+const BLOCK_MS: usize = 50;
+const PERCENTILE: f64 = 0.95;
+
+/// The sample rates there are filters for, in the same order as the coefficients.
+const SAMPLE_RATES: [u32; 9] = [48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000];
+
+// Equal loudness filter (10th-order Yule-Walker followed by a 2nd-order Butterworth high-pass)
+static A_YULE: [[f64; 11]; 9] = [
+    [
+        1.00000000000000, -3.84664617118067, 7.81501653005538, -11.34170355132042, 13.05504219327545, -12.28759895145294,
+        9.48293806319790, -5.87257861775999, 2.75465861874613, -0.86984376593551, 0.13919314567432,
+    ],
+    [
+        1.00000000000000, -3.47845948550071, 6.36317777566148, -8.54751527471874, 9.47693607801280, -8.81498681370155,
+        6.85401540936998, -4.39470996079559, 2.19611684890774, -0.75104302451432, 0.13149317958808,
+    ],
+    [
+        1.00000000000000, -2.37898834973084, 2.84868151156327, -2.64577170229825, 2.23697657451713, -1.67148153367602,
+        1.00595954808547, -0.45953458054983, 0.16378164858596, -0.05032077717131, 0.02347897407020,
+    ],
+    [
+        1.00000000000000, -1.61273165137247, 1.07977492259970, -0.25656257754070, -0.16276719120440, -0.22638893773906,
+        0.39120800788284, -0.22138138954925, 0.04500235387352, 0.02005851806501, 0.00302439095741,
+    ],
+    [
+        1.00000000000000, -1.49858979367799, 0.87350271418188, 0.12205022308084, -0.80774944671438, 0.47854794562326,
+        -0.12453458140019, -0.04067510197014, 0.08333755284107, -0.04237348025746, 0.02977207319925,
+    ],
+    [
+        1.00000000000000, -0.62820619233671, 0.29661783706366, -0.37256372942400, 0.00213767857124, -0.42029820170918,
+        0.22199650564824, 0.00613424350682, 0.06747620744683, 0.05784820375801, 0.03222754072173,
+    ],
+    [
+        1.00000000000000, -1.04800335126349, 0.29156311971249, -0.26806001042947, 0.00819999645858, 0.45054734505008,
+        -0.33032403314006, 0.06739368333110, -0.04784254229033, 0.01639907836189, 0.01807364323573,
+    ],
+    [
+        1.00000000000000, -0.51035327095184, -0.31863563325245, -0.20256413484477, 0.14728154134330, 0.38952639978999,
+        -0.23313271880868, -0.05246019024463, -0.02505961724053, 0.02442357316099, 0.01818801111503,
+    ],
+    [
+        1.00000000000000, -0.25049871956020, -0.43193942311114, -0.03424681017675, -0.04678328784242, 0.26408300200955,
+        0.15113130533216, -0.17556493366449, -0.18823009262115, 0.05477720428674, 0.04704409688120,
+    ],
+];
+
+static B_YULE: [[f64; 11]; 9] = [
+    [
+        0.03857599435200, -0.02160367184185, -0.00123395316851, -0.00009291677959, -0.01655260341619, 0.02161526843274,
+        -0.02074045215285, 0.00594298065125, 0.00306428023191, 0.00012025322027, 0.00288463683916,
+    ],
+    [
+        0.05418656406430, -0.02911007808948, -0.00848709379851, -0.00851165645469, -0.00834990904936, 0.02245293253339,
+        -0.02596338512915, 0.01624864962975, -0.00240879051584, 0.00674613682247, -0.00187763777362,
+    ],
+    [
+        0.15457299681924, -0.09331049056315, -0.06247880153653, 0.02163541888798, -0.05588393329856, 0.04781476674921,
+        0.00222312597743, 0.03174092540049, -0.01390589421898, 0.00651420667831, -0.00881362733839,
+    ],
+    [
+        0.30296907319327, -0.22613988682123, -0.08587323730772, 0.03282930172664, -0.00915702933434, -0.02364141202522,
+        -0.00584456039913, 0.06276101321749, -0.00000828086748, 0.00205861885564, -0.02950134983287,
+    ],
+    [
+        0.33642304856132, -0.25572241425570, -0.11828570177555, 0.11921148675203, -0.07834489609479, -0.00469977914380,
+        -0.00589500224440, 0.05724228140351, 0.00832043980773, -0.01635381384540, -0.01760176568150,
+    ],
+    [
+        0.44915256608450, -0.14351757464547, -0.22784394429749, -0.01419140100551, 0.04078262797139, -0.12398163381748,
+        0.04097565135648, 0.10478503600251, -0.01863887810927, -0.03193428438915, 0.00541907748707,
+    ],
+    [
+        0.56619470757641, -0.75464456939302, 0.16242137742230, 0.16744243493672, -0.18901604199609, 0.30931782841830,
+        -0.27562961986224, 0.00647310677246, 0.08647503780351, -0.03788984554840, -0.00588215443421,
+    ],
+    [
+        0.58100494960553, -0.53174909058578, -0.14289799034253, 0.17520704835522, 0.02377945217615, 0.15558449135573,
+        -0.25344790059353, 0.01628462406333, 0.06920467763959, -0.03721611395801, -0.00749618797172,
+    ],
+    [
+        0.53648789255105, -0.42163034350696, -0.00275953611929, 0.04267842219415, -0.10214864179676, 0.14590772289388,
+        -0.02459864859345, -0.11202315195388, -0.04060034127000, 0.04788665548180, -0.02217936801134,
+    ],
+];
+
+static A_BUTTER: [[f64; 3]; 9] = [
+    [1.00000000000000, -1.97223372919527, 0.97261396931306],
+    [1.00000000000000, -1.96977855582618, 0.97022847566350],
+    [1.00000000000000, -1.95835380975398, 0.95920349965459],
+    [1.00000000000000, -1.95002759149878, 0.95124613669835],
+    [1.00000000000000, -1.94561023566527, 0.94705070426118],
+    [1.00000000000000, -1.92783286977036, 0.93034775234268],
+    [1.00000000000000, -1.91858953033784, 0.92177618768381],
+    [1.00000000000000, -1.91542108074780, 0.91885558323625],
+    [1.00000000000000, -1.88903307939452, 0.89487434461664],
+];
+
+static B_BUTTER: [[f64; 3]; 9] = [
+    [0.98621192462708, -1.97242384925416, 0.98621192462708],
+    [0.98500175787242, -1.97000351574484, 0.98500175787242],
+    [0.97938932735214, -1.95877865470428, 0.97938932735214],
+    [0.97531843204928, -1.95063686409857, 0.97531843204928],
+    [0.97316523498161, -1.94633046996323, 0.97316523498161],
+    [0.96454515552826, -1.92909031105652, 0.96454515552826],
+    [0.96009142950541, -1.92018285901082, 0.96009142950541],
+    [0.95856916599601, -1.91713833199203, 0.95856916599601],
+    [0.94597685600279, -1.89195371200558, 0.94597685600279],
+];
+
+/// Filter state and block sum of one channel.
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    butter: [f64; 2],
+    input: [f64; 10],
+    sum: f64,
+    yule: [f64; 10],
+}
+
+impl Channel {
+    /// Filters a sample and adds it to the block sum.
+    #[inline]
+    fn push(&mut self, x: f64, filter: usize) {
+        let (a, b) = (&A_YULE[filter], &B_YULE[filter]);
+        // the tiny offset keeps the filter out of the denormal range, as in the reference
+        let mut y = 1e-10 + x * b[0];
+        for k in 0..10 {
+            y += self.input[k] * b[k + 1] - self.yule[k] * a[k + 1];
+        }
+
+        let (a, b) = (&A_BUTTER[filter], &B_BUTTER[filter]);
+        let z = y * b[0] + self.yule[0] * b[1] + self.yule[1] * b[2] - self.butter[0] * a[1] - self.butter[1] * a[2];
+
+        self.input.copy_within(..9, 1);
+        self.input[0] = x;
+        self.yule.copy_within(..9, 1);
+        self.yule[0] = y;
+        self.butter = [z, self.butter[0]];
+        self.sum += z * z;
+    }
+}
+
+/// Incremental ReplayGain 1.0 analysis of a single track.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{analysis::ReplayGain, Decoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// if let Some(track) = ReplayGain::scan(&mut Decoder::new(&mp3)) {
+///     println!("REPLAYGAIN_TRACK_GAIN={:.2} dB", track.gain());
+///     println!("REPLAYGAIN_TRACK_PEAK={:.6}", track.peak());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReplayGain {
+    block_len: usize,
+    block_pos: usize,
+    channels: [Channel; 2],
+    filter: usize,
+    histogram: Vec<u32>,
+    peak: f64,
+    stereo: bool,
+}
+
+impl ReplayGain {
+    /// Constructs a new `ReplayGain` for analysing audio of the given format.
+    ///
+    /// Returns `None` if the sample rate isn't one of those MPEG Audio supports,
+    /// or if there's more than two channels.
+    pub fn new(sample_rate: u32, channels: u16) -> Option<Self> {
+        let filter = SAMPLE_RATES.iter().position(|&x| x == sample_rate)?;
+        let stereo = match channels {
+            1 => false,
+            2 => true,
+            _ => return None,
+        };
+        Some(Self {
+            block_len: (sample_rate as usize * BLOCK_MS).div_ceil(1000),
+            block_pos: 0,
+            channels: [Channel::default(); 2],
+            filter,
+            histogram: vec![0; MAX_DB * STEPS_PER_DB as usize],
+            peak: 0.0,
+            stereo,
+        })
+    }
+
+    /// Decodes every remaining frame of `decoder`, returning the gain and peak of all of it.
+    ///
+    /// The format of the first audio frame is used, and any frames in other formats are ignored.
+    /// Returns `None` if there's no audio at all, or if the format isn't supported.
+    pub fn scan<P: PcmStorage>(decoder: &mut Decoder<'_, P>) -> Option<TrackGain> {
+        let mut analysis: Option<(Self, u32, u16)> = None;
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                let format = (audio.sample_rate(), audio.channels());
+                if analysis.is_none() {
+                    analysis = Some((Self::new(format.0, format.1)?, format.0, format.1));
+                }
+                if let Some((analysis, sample_rate, channels)) = &mut analysis {
+                    if format == (*sample_rate, *channels) {
+                        analysis.process(audio.samples());
+                    }
+                }
+            }
+        }
+        analysis?.0.result()
+    }
+
+    /// Analyses interleaved samples, in the format given in [`new`](Self::new).
+    pub fn process(&mut self, samples: &[Sample]) {
+        let channels = if self.stereo { 2 } else { 1 };
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in self.channels.iter_mut().zip(frame) {
+                let x = to_f64(sample);
+                self.peak = self.peak.max(x.abs());
+                // the filters are designed for 16-bit sample values
+                channel.push(x * 32768.0, self.filter);
+            }
+            self.block_pos += 1;
+            if self.block_pos == self.block_len {
+                self.finish_block();
+            }
+        }
+    }
+
+    /// Gets the gain and peak of everything analysed so far,
+    /// or `None` if that's not even a single block (50ms) long.
+    pub fn result(&self) -> Option<TrackGain> {
+        let blocks: u32 = self.histogram.iter().sum();
+        if blocks == 0 {
+            return None;
+        }
+        let mut upper = (f64::from(blocks) * (1.0 - PERCENTILE)).ceil() as i64;
+        let mut index = self.histogram.len();
+        while index > 0 {
+            index -= 1;
+            upper -= i64::from(self.histogram[index]);
+            if upper <= 0 {
+                break;
+            }
+        }
+        Some(TrackGain { gain: PINK_REF - index as f64 / STEPS_PER_DB, peak: self.peak })
+    }
+
+    fn finish_block(&mut self) {
+        let sum = match self.stereo {
+            true => self.channels[0].sum + self.channels[1].sum,
+            false => self.channels[0].sum * 2.0,
+        };
+        let loudness = STEPS_PER_DB * 10.0 * (sum / self.block_len as f64 * 0.5 + 1e-37).log10();
+        let index = (loudness as isize).clamp(0, self.histogram.len() as isize - 1);
+        self.histogram[index as usize] += 1;
+
+        self.block_pos = 0;
+        for channel in &mut self.channels {
+            channel.sum = 0.0;
+        }
+    }
+}
+
+/// The result of a [`ReplayGain`] analysis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct TrackGain {
+    gain: f64,
+    peak: f64,
+}
+
+impl TrackGain {
+    /// Gets the gain to apply in dB, to be written as `REPLAYGAIN_TRACK_GAIN`,
+    /// which is conventionally formatted with two decimals (`{:.2} dB`).
+    #[inline]
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Gets the peak amplitude, where 1.0 is full scale, to be written as `REPLAYGAIN_TRACK_PEAK`,
+    /// which is conventionally formatted with six decimals (`{:.6}`).
+    #[inline]
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReplayGain, PINK_REF};
+
+    #[test]
+    fn silence() {
+        assert!(ReplayGain::new(44100, 3).is_none());
+        assert!(ReplayGain::new(96000, 2).is_none());
+
+        let mut analysis = ReplayGain::new(44100, 2).unwrap();
+        analysis.process(&[Default::default(); 2000]);
+        assert!(analysis.result().is_none());
+        analysis.process(&[Default::default(); 4000]);
+        let track = analysis.result().unwrap();
+        assert_eq!(track.gain(), PINK_REF);
+        assert_eq!(track.peak(), 0.0);
+    }
+}
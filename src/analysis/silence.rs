@@ -0,0 +1,200 @@
+//! Detection of leading and trailing silence.
+
+use super::to_f64;
+use crate::{Decoder, Frame, PcmStorage, Sample};
+
+/// Finds where the leading and trailing silence of a stream are.
+///
+/// A sample (across all channels) is silent if every channel is at or below the threshold,
+/// and silence is only counted if it lasts for at least the minimum duration.
+///
+/// # Example
+///
+/// Example that decodes a file twice, first to find the silence, and then to trim it off:
+///
+/// ```no_run
+/// use rmp3::{analysis::SilenceDetector, Decoder, Frame};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// if let Some(bounds) = SilenceDetector::scan(&mut decoder, -60.0, 0.1) {
+///     decoder.set_position(0);
+///     let mut offset = 0;
+///     while let Some(frame) = decoder.next() {
+///         if let Frame::Audio(audio) = frame {
+///             let samples = bounds.trim(audio.samples(), offset, audio.channels());
+///             // process trimmed samples here!
+///             offset += audio.sample_count() as u64;
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SilenceDetector {
+    channels: usize,
+    first: Option<u64>,
+    last: u64,
+    min_samples: u64,
+    samples: u64,
+    threshold: f64,
+}
+
+/// Where the audible part of a stream starts and ends, as found by a [`SilenceDetector`].
+///
+/// Positions are in samples per channel.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct SilenceBounds {
+    end: u64,
+    start: u64,
+    total: u64,
+}
+
+impl SilenceDetector {
+    /// Constructs a new `SilenceDetector` for audio of the given format,
+    /// with the threshold in dBFS, and the minimum duration in seconds.
+    ///
+    /// Returns `None` if there's no channels.
+    pub fn new(sample_rate: u32, channels: u16, threshold_db: f64, min_duration: f64) -> Option<Self> {
+        if channels == 0 {
+            return None;
+        }
+        Some(Self {
+            channels: channels as usize,
+            first: None,
+            last: 0,
+            min_samples: (min_duration.max(0.0) * f64::from(sample_rate)) as u64,
+            samples: 0,
+            threshold: 10f64.powf(threshold_db / 20.0),
+        })
+    }
+
+    /// Decodes every remaining frame of `decoder`, returning where the silence in all of it is.
+    ///
+    /// The format of the first audio frame is used, and any frames in other formats are
+    /// still counted, but not looked at.
+    /// Returns `None` if there's no audio at all.
+    pub fn scan<P: PcmStorage>(decoder: &mut Decoder<'_, P>, threshold_db: f64, min_duration: f64) -> Option<SilenceBounds> {
+        let mut detector: Option<(Self, u32)> = None;
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                if detector.is_none() {
+                    let sample_rate = audio.sample_rate();
+                    detector = Some((Self::new(sample_rate, audio.channels(), threshold_db, min_duration)?, sample_rate));
+                }
+                if let Some((detector, sample_rate)) = &mut detector {
+                    if (audio.sample_rate(), audio.channels() as usize) == (*sample_rate, detector.channels) {
+                        detector.process(audio.samples());
+                    } else {
+                        detector.samples += audio.sample_count() as u64;
+                    }
+                }
+            }
+        }
+        detector.map(|(detector, _)| detector.bounds())
+    }
+
+    /// Looks at interleaved samples, in the channel count given in [`new`](Self::new).
+    pub fn process(&mut self, samples: &[Sample]) {
+        for frame in samples.chunks_exact(self.channels) {
+            if frame.iter().any(|&x| to_f64(x).abs() > self.threshold) {
+                self.first.get_or_insert(self.samples);
+                self.last = self.samples + 1;
+            }
+            self.samples += 1;
+        }
+    }
+
+    /// Gets where the silence is in everything looked at so far.
+    ///
+    /// If it's all silence, the audible part is empty and at the end.
+    pub fn bounds(&self) -> SilenceBounds {
+        let total = self.samples;
+        let mut start = self.first.unwrap_or(total);
+        let mut end = self.last.max(start);
+        if start < self.min_samples {
+            start = 0;
+        }
+        if total - end < self.min_samples {
+            end = total;
+        }
+        SilenceBounds { end, start, total }
+    }
+}
+
+impl SilenceBounds {
+    /// Gets where the audible part ends, as in, where the trailing silence starts.
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Gets how many samples of leading silence there are.
+    #[inline]
+    pub fn leading(&self) -> u64 {
+        self.start
+    }
+
+    /// Gets where the audible part starts, as in, where the leading silence ends.
+    #[inline]
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Gets the total sample count, including silence.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Gets how many samples of trailing silence there are.
+    #[inline]
+    pub fn trailing(&self) -> u64 {
+        self.total - self.end
+    }
+
+    /// Trims the silence off of interleaved samples which start at sample `offset`,
+    /// such as those of a single frame.
+    pub fn trim<'a>(&self, samples: &'a [Sample], offset: u64, channels: u16) -> &'a [Sample] {
+        let channels = channels as usize;
+        let count = (samples.len() / channels.max(1)) as u64;
+        let start = self.start.saturating_sub(offset).min(count) as usize;
+        let end = self.end.saturating_sub(offset).min(count).max(start as u64) as usize;
+        &samples[start * channels..end * channels]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SilenceBounds, SilenceDetector};
+    use crate::Sample;
+
+    #[test]
+    fn silence() {
+        #[cfg(not(feature = "float"))]
+        let loud: Sample = 16384;
+        #[cfg(feature = "float")]
+        let loud: Sample = 0.5;
+
+        // mono at 100 Hz, so 0.1 seconds is 10 samples
+        let mut detector = SilenceDetector::new(100, 1, -60.0, 0.1).unwrap();
+        let mut samples = [Sample::default(); 50];
+        samples[2] = loud;
+        samples[20] = loud;
+        samples[29] = loud;
+        detector.process(&samples);
+        let bounds = detector.bounds();
+        assert_eq!(bounds, SilenceBounds { start: 0, end: 30, total: 50 });
+        assert_eq!((bounds.leading(), bounds.trailing()), (0, 20));
+
+        assert_eq!(bounds.trim(&samples[..10], 25, 1).len(), 5);
+        assert_eq!(bounds.trim(&samples[..10], 40, 1).len(), 0);
+
+        let mut detector = SilenceDetector::new(100, 2, -60.0, 0.1).unwrap();
+        detector.process(&[Sample::default(); 40]);
+        assert_eq!(detector.bounds(), SilenceBounds { start: 20, end: 20, total: 20 });
+    }
+}
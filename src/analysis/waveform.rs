@@ -0,0 +1,176 @@
+//! Waveform overviews for drawing.
+
+use super::to_f64;
+use crate::{Decoder, Frame, PcmStorage, Sample};
+
+/// One point of a waveform overview, summarizing a fixed amount of samples across all channels,
+/// where 1.0 is full scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Peak {
+    max: f32,
+    min: f32,
+    rms: f32,
+}
+
+impl Peak {
+    /// Gets the highest sample.
+    #[inline]
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Gets the lowest sample.
+    #[inline]
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    /// Gets the RMS (root mean square) level.
+    #[inline]
+    pub fn rms(&self) -> f32 {
+        self.rms
+    }
+}
+
+/// Builds a waveform overview, as in, the min/max peaks and RMS level of every so many samples,
+/// such as for the "peaks files" that editors and players draw waveforms from.
+///
+/// Only the peaks are kept, never the samples, so a whole stream can be summarized in one pass.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{analysis::Waveform, Decoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// if let Some(peaks) = Waveform::scan(&mut decoder, 256) {
+///     for peak in peaks {
+///         // draw a line from peak.min() to peak.max() here!
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waveform {
+    channels: usize,
+    peaks: Vec<Peak>,
+    samples_per_peak: u32,
+
+    // the peak being built
+    count: u32,
+    max: f32,
+    min: f32,
+    sum: f64,
+}
+
+impl Waveform {
+    /// Constructs a new `Waveform` for audio with the given channel count,
+    /// with a peak for every `samples_per_peak` samples per channel.
+    ///
+    /// Returns `None` if there's no channels, or `samples_per_peak` is zero.
+    pub fn new(channels: u16, samples_per_peak: u32) -> Option<Self> {
+        if channels == 0 || samples_per_peak == 0 {
+            return None;
+        }
+        Some(Self {
+            channels: channels as usize,
+            peaks: Vec::new(),
+            samples_per_peak,
+            count: 0,
+            max: 0.0,
+            min: 0.0,
+            sum: 0.0,
+        })
+    }
+
+    /// Decodes every remaining frame of `decoder`, returning the peaks of all of it.
+    ///
+    /// The channel count of the first audio frame is used, and any frames with another
+    /// channel count are skipped. Returns `None` if there's no audio at all.
+    pub fn scan<P: PcmStorage>(decoder: &mut Decoder<'_, P>, samples_per_peak: u32) -> Option<Vec<Peak>> {
+        let mut waveform: Option<Self> = None;
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                if waveform.is_none() {
+                    waveform = Some(Self::new(audio.channels(), samples_per_peak)?);
+                }
+                if let Some(waveform) = &mut waveform {
+                    if audio.channels() as usize == waveform.channels {
+                        waveform.process(audio.samples());
+                    }
+                }
+            }
+        }
+        waveform.map(Self::finish)
+    }
+
+    /// Looks at interleaved samples, in the channel count given in [`new`](Self::new).
+    pub fn process(&mut self, samples: &[Sample]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for &x in frame {
+                let x = to_f64(x);
+                self.max = self.max.max(x as f32);
+                self.min = self.min.min(x as f32);
+                self.sum += x * x;
+            }
+            self.count += 1;
+            if self.count == self.samples_per_peak {
+                self.push();
+            }
+        }
+    }
+
+    /// Gets the peaks finished so far.
+    #[inline]
+    pub fn peaks(&self) -> &[Peak] {
+        &self.peaks
+    }
+
+    /// Takes out the peaks finished so far, such as for drawing a stream as it's decoded.
+    pub fn take_peaks(&mut self) -> Vec<Peak> {
+        core::mem::take(&mut self.peaks)
+    }
+
+    /// Finishes the last peak, even if it's short, and returns all of them.
+    pub fn finish(mut self) -> Vec<Peak> {
+        if self.count != 0 {
+            self.push();
+        }
+        self.peaks
+    }
+
+    fn push(&mut self) {
+        let rms = (self.sum / (u64::from(self.count) * self.channels as u64) as f64).sqrt() as f32;
+        self.peaks.push(Peak { max: self.max, min: self.min, rms });
+        self.count = 0;
+        self.max = 0.0;
+        self.min = 0.0;
+        self.sum = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Peak, Waveform};
+    use crate::Sample;
+
+    #[test]
+    fn waveform() {
+        #[cfg(not(feature = "float"))]
+        let (low, high): (Sample, Sample) = (-16384, 16384);
+        #[cfg(feature = "float")]
+        let (low, high): (Sample, Sample) = (-0.5, 0.5);
+
+        assert!(Waveform::new(2, 0).is_none());
+        let mut waveform = Waveform::new(2, 2).unwrap();
+        waveform.process(&[low, high, high, low]);
+        assert_eq!(waveform.peaks(), [Peak { max: 0.5, min: -0.5, rms: 0.5 }]);
+        waveform.process(&[low, low]);
+        assert_eq!(waveform.take_peaks().len(), 1);
+        assert_eq!(waveform.finish(), [Peak { max: 0.0, min: -0.5, rms: 0.5 }]);
+    }
+}
@@ -0,0 +1,145 @@
+//! Frame decoding engines, which [`RawDecoder`](crate::RawDecoder) is built on.
+//!
+//! Finding frames is done in Rust (see the `header` module), so a backend is only ever given
+//! one whole frame at a time, and has to keep the state which carries over between frames.
+
+use crate::{ffi, FrameHeader, Sample};
+use core::mem::MaybeUninit;
+use libc::c_int;
+
+/// A frame decoding engine.
+///
+/// Backends must behave exactly like minimp3 on the same input, which the decoder relies on
+/// for knowing when a stream was resynchronized. minimp3 is the only one there is, so this only
+/// keeps the decoder from depending on its state directly, and there's no way to pick another.
+pub(crate) trait Backend {
+    /// Constructs the backend, as if nothing was decoded yet.
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut this = MaybeUninit::uninit();
+        unsafe {
+            Self::init(this.as_mut_ptr());
+            this.assume_init()
+        }
+    }
+
+    /// Constructs the backend in place, for when it should never be on the stack.
+    ///
+    /// # Safety
+    ///
+    /// `this` has to be valid for writes, and is overwritten without being dropped.
+    unsafe fn init(this: *mut Self);
+
+    /// Forgets the whole stream, as when resynchronizing.
+    fn reset(&mut self);
+
+    /// Gets the header of the previous frame, unless it was reset since.
+    ///
+    /// Backends reset themselves on corrupt data, which makes this `None` after decoding.
+    fn header(&self) -> Option<FrameHeader>;
+
+    /// Remembers the header of the frame which is about to be decoded.
+    ///
+    /// This must only be called after a [`reset`](Self::reset) if there's no header yet,
+    /// as minimp3 only initializes the rest of its state then.
+    fn set_header(&mut self, header: FrameHeader);
+
+    /// Gets the frame size of a free-format stream, or 0 if it's not free-format.
+    fn free_format_bytes(&self) -> usize;
+
+    /// Sets the frame size of a free-format stream, or 0 if it's not free-format.
+    fn set_free_format_bytes(&mut self, bytes: usize);
+
+    /// Decodes a single whole frame into `pcm`, returning the sample count per channel,
+    /// and filling in `info` for it.
+    ///
+    /// # Safety
+    ///
+    /// `frame` has to start with a valid header (the one given to [`set_header`](Self::set_header)),
+    /// and `pcm` has to be valid for as many writes as the frame has samples, which is its
+    /// [`sample_count`](FrameHeader::sample_count) times its [`channels`](FrameHeader::channels).
+    unsafe fn decode(&mut self, frame: &[u8], pcm: *mut Sample, info: &mut ffi::mp3dec_frame_info_t) -> usize;
+}
+
+/// The backend in use, which is minimp3 as it's the only one right now.
+pub(crate) type DefaultBackend = Minimp3;
+
+/// The minimp3 C library.
+#[derive(Clone)]
+pub(crate) struct Minimp3(MaybeUninit<ffi::mp3dec_t>);
+
+// The state doesn't depend on which features minimp3 is built with,
+// which the memory usage documented in the crate docs relies on.
+const _: () = assert!(core::mem::size_of::<ffi::mp3dec_t>() == 6668);
+
+// The sizes of the decoders on 64-bit targets, as documented in the crate docs.
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    use crate::{Decoder, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+    use core::mem::size_of;
+
+    assert!(size_of::<RawDecoder>() == 6784);
+    assert!(size_of::<Decoder<&mut [Sample; MAX_SAMPLES_PER_FRAME]>>() == 6848);
+    #[cfg(not(feature = "float"))]
+    assert!(size_of::<Decoder>() == 11448);
+    #[cfg(feature = "float")]
+    assert!(size_of::<Decoder>() == 16056);
+};
+
+impl Backend for Minimp3 {
+    #[inline]
+    unsafe fn init(this: *mut Self) {
+        ffi::mp3dec_init(core::ptr::addr_of_mut!((*this).0).cast());
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        // this is what `mp3dec_decode_frame` does when it can't find the previous frame's successor
+        unsafe {
+            core::ptr::write_bytes(self.0.as_mut_ptr(), 0, 1);
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> Option<FrameHeader> {
+        // SAFETY: The header is always initialized, as `mp3dec_init` clears the first byte,
+        // and the rest is only read when the first byte was written by `set_header` (or a reset).
+        unsafe {
+            let header = &(*self.0.as_ptr()).header;
+            match header[0] {
+                0xFF => Some(FrameHeader::from_frame_unchecked(header)),
+                _ => None,
+            }
+        }
+    }
+
+    #[inline]
+    fn set_header(&mut self, header: FrameHeader) {
+        unsafe {
+            (*self.0.as_mut_ptr()).header = header.bytes();
+        }
+    }
+
+    #[inline]
+    fn free_format_bytes(&self) -> usize {
+        // SAFETY: This is only read while a header is remembered, and the first header
+        // is only ever set after a reset (see `set_header`), which initializes it.
+        unsafe { (*self.0.as_ptr()).free_format_bytes as usize }
+    }
+
+    #[inline]
+    fn set_free_format_bytes(&mut self, bytes: usize) {
+        unsafe {
+            (*self.0.as_mut_ptr()).free_format_bytes = bytes as c_int;
+        }
+    }
+
+    #[inline]
+    unsafe fn decode(&mut self, frame: &[u8], pcm: *mut Sample, info: &mut ffi::mp3dec_frame_info_t) -> usize {
+        // minimp3 accepts a lone frame which fills up the entire input as-is
+        // (it's also the same as the remembered header), so this only decodes it.
+        ffi::mp3dec_decode_frame(self.0.as_mut_ptr(), frame.as_ptr(), frame.len() as c_int, pcm, info) as usize
+    }
+}
@@ -0,0 +1,248 @@
+//! Integration with the [bytes](https://docs.rs/bytes) crate, for network services.
+
+#[cfg(feature = "float")]
+use crate::dsp::to_i16;
+use crate::{dsp::to_f32, DecoderOptions, Frame, RawDecoder, Sample, LOOKAHEAD, MAX_SAMPLES_PER_FRAME};
+use alloc::vec::Vec;
+use bytes::{Buf, BufMut};
+
+/// Describes how samples are packed into bytes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum PcmEncoding {
+    /// Signed 16-bit little-endian integers, as in WAV files and most raw PCM formats.
+    S16Le,
+
+    /// 32-bit little-endian floats in the range \[-1.0, 1.0\].
+    F32Le,
+}
+
+impl PcmEncoding {
+    /// Gets how many bytes one sample takes up.
+    #[inline]
+    pub const fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::S16Le => 2,
+            Self::F32Le => 4,
+        }
+    }
+}
+
+/// Appends `samples` to `dest`, converted to the given encoding,
+/// such as for assembling the payloads of network audio in a `BytesMut`.
+///
+/// Float samples are clipped when converted to 16-bit integers.
+///
+/// # Panics
+///
+/// Panics if `dest` doesn't have enough capacity left, which a `BytesMut` always has.
+///
+/// # Example
+///
+/// ```no_run
+/// use bytes::BytesMut;
+/// use rmp3::{buf::{put_pcm, PcmEncoding}, Decoder, Frame};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut payload = BytesMut::new();
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         put_pcm(&mut payload, audio.samples(), PcmEncoding::S16Le);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn put_pcm<B: BufMut + ?Sized>(dest: &mut B, samples: &[Sample], encoding: PcmEncoding) {
+    match encoding {
+        #[cfg(not(feature = "float"))]
+        PcmEncoding::S16Le => samples.iter().for_each(|&x| dest.put_i16_le(x)),
+        #[cfg(feature = "float")]
+        PcmEncoding::S16Le => samples.iter().for_each(|&x| dest.put_i16_le(to_i16(x))),
+        PcmEncoding::F32Le => samples.iter().for_each(|&x| dest.put_f32_le(to_f32(x))),
+    }
+}
+
+/// Decoder for a stream arriving in [`Buf`]s, such as the chunked bodies of hyper or tonic,
+/// including chained buffers.
+///
+/// Frames are decoded straight out of the buffers wherever there's more than 16 KiB of the chunk
+/// after them, and the rest of the chunk is copied to be put together with the next one,
+/// so the stream doesn't need to be put together into one slice first. Chunks smaller than that
+/// are copied whole, so this saves copying with large chunks only.
+///
+/// # Example
+///
+/// ```no_run
+/// use bytes::Bytes;
+/// use rmp3::{buf::BufDecoder, Frame};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let chunks: Vec<Bytes> = Vec::new();
+/// let mut decoder = BufDecoder::new();
+/// let mut play = |frame: Frame<'_, '_>| {
+///     if let Frame::Audio(audio) = frame {
+///         // play audio.samples() here!
+///     }
+/// };
+/// for mut chunk in chunks {
+///     decoder.push(&mut chunk, &mut play);
+/// }
+/// decoder.flush(&mut play);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BufDecoder {
+    // data around a chunk boundary, which had to be put together
+    carry: Vec<u8>,
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    raw: RawDecoder,
+}
+
+impl BufDecoder {
+    /// Constructs a new `BufDecoder`.
+    pub fn new() -> Self {
+        Self::with_options(DecoderOptions::new())
+    }
+
+    /// Constructs a new `BufDecoder` with the given options.
+    pub fn with_options(options: DecoderOptions) -> Self {
+        Self {
+            carry: Vec::new(),
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            raw: RawDecoder::with_options(options),
+        }
+    }
+
+    /// Consumes all of `buf`, calling `f` with every frame which is certainly complete.
+    ///
+    /// The last few frames are held back until there's enough data after them,
+    /// or until [`flush`](Self::flush) is called at the end of the stream.
+    pub fn push<B, F>(&mut self, buf: &mut B, mut f: F)
+    where
+        B: Buf + ?Sized,
+        F: FnMut(Frame<'_, '_>),
+    {
+        while buf.has_remaining() {
+            if self.carry.is_empty() {
+                let consumed = decode(&mut self.raw, &mut self.pcm, buf.chunk(), usize::MAX, LOOKAHEAD, &mut f);
+                buf.advance(consumed);
+                // whatever is left of this chunk is too short, so it's put together with the next
+                let rest = buf.chunk();
+                let len = rest.len();
+                self.carry.extend_from_slice(rest);
+                buf.advance(len);
+            } else {
+                // look at the next chunk without consuming it, to decode the frames in the carried data
+                let tail = self.carry.len();
+                let chunk = buf.chunk();
+                let peeked = chunk.len().min(2 * LOOKAHEAD);
+                self.carry.extend_from_slice(&chunk[..peeked]);
+                let consumed = decode(&mut self.raw, &mut self.pcm, &self.carry, tail, LOOKAHEAD, &mut f);
+                if consumed >= tail {
+                    // made it into the chunk, so it can be decoded from directly again
+                    buf.advance(consumed - tail);
+                    self.carry.clear();
+                } else {
+                    buf.advance(peeked);
+                    self.carry.drain(..consumed);
+                }
+            }
+        }
+    }
+
+    /// Decodes everything that's still held back, for when the stream has ended.
+    pub fn flush<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Frame<'_, '_>),
+    {
+        decode(&mut self.raw, &mut self.pcm, &self.carry, usize::MAX, 0, &mut f);
+        self.carry.clear();
+    }
+
+    /// Forgets the stream, ready to decode a new one.
+    pub fn reset(&mut self) {
+        self.carry.clear();
+        self.raw = RawDecoder::with_options(self.raw.options());
+    }
+}
+
+impl Default for BufDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes frames starting before `limit` while more than `lookahead` bytes are left after them,
+/// returning how many bytes were consumed.
+fn decode<F>(
+    raw: &mut RawDecoder,
+    pcm: &mut [Sample; MAX_SAMPLES_PER_FRAME],
+    data: &[u8],
+    limit: usize,
+    lookahead: usize,
+    f: &mut F,
+) -> usize
+where
+    F: FnMut(Frame<'_, '_>),
+{
+    let mut position = 0;
+    while position < limit && data.len() - position > lookahead {
+        match raw.next(&data[position..], pcm) {
+            Some((frame, consumed)) => {
+                f(frame);
+                position += consumed;
+            },
+            None => break,
+        }
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{put_pcm, PcmEncoding};
+    use crate::Sample;
+
+    #[test]
+    #[cfg(not(feature = "float"))]
+    fn pcm() {
+        let samples = [0i16, 16384, -32768].map(Sample::from);
+        let mut dest = alloc::vec::Vec::new();
+        put_pcm(&mut dest, &samples, PcmEncoding::S16Le);
+        assert_eq!(dest.len(), samples.len() * PcmEncoding::S16Le.bytes_per_sample());
+        assert_eq!(dest, [0x00, 0x00, 0x00, 0x40, 0x00, 0x80]);
+
+        dest.clear();
+        put_pcm(&mut dest, &samples, PcmEncoding::F32Le);
+        assert_eq!(dest[4..8], 0.5f32.to_le_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn frames_across_pushes() {
+        use super::BufDecoder;
+        use crate::{test_util::FrameBuilder, Frame};
+
+        let mp3 = FrameBuilder::new().stream(200).unwrap();
+        // small chunks are always carried, and large ones are partly decoded from directly
+        for size in [100, 417, 20_000, 40_000, mp3.len()] {
+            let mut decoder = BufDecoder::new();
+            let mut frames = 0;
+            let mut count = |frame: Frame<'_, '_>| {
+                if let Frame::Audio(audio) = frame {
+                    assert_eq!(audio.sample_count(), 1152);
+                    frames += 1;
+                }
+            };
+            for mut chunk in mp3.chunks(size) {
+                decoder.push(&mut chunk, &mut count);
+            }
+            decoder.flush(&mut count);
+            assert_eq!(frames, 200, "in chunks of {}", size);
+        }
+    }
+}
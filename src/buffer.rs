@@ -0,0 +1,145 @@
+//! An output buffer which knows which of its samples are valid.
+
+use crate::{Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+
+/// A buffer of one frame of samples, which keeps track of how many of them the last decoded frame filled,
+/// for use with [`RawDecoder::next_buffer`].
+///
+/// With a plain array, what's left over from previous frames is easily read by mistake,
+/// since only the [`Audio`](crate::Audio) knows how much of it is valid.
+/// Here, [`samples`](Self::samples) is only ever the last frame's samples, and empty when it wasn't audio.
+/// The same buffer should be reused for every frame, which [`Concealment::Repeat`](crate::Concealment::Repeat)
+/// relies on.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{PcmBuffer, RawDecoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = RawDecoder::new();
+/// let mut buffer = PcmBuffer::new();
+/// let mut data = &mp3[..];
+/// while let Some((_, consumed)) = decoder.next_buffer(data, &mut buffer) {
+///     data = &data[consumed..];
+///     for sample in buffer.channel(0) {
+///         // process the first channel here!
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PcmBuffer {
+    samples: [Sample; MAX_SAMPLES_PER_FRAME],
+    len: usize,
+    channels: u16,
+}
+
+impl PcmBuffer {
+    /// Constructs a new, empty `PcmBuffer`.
+    pub const fn new() -> Self {
+        Self { samples: [0 as Sample; MAX_SAMPLES_PER_FRAME], len: 0, channels: 0 }
+    }
+
+    /// Gets the interleaved samples of the last frame, if it was audio.
+    #[inline]
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples[..self.len]
+    }
+
+    /// Gets the interleaved samples of the last frame mutably, such as for processing them in place.
+    #[inline]
+    pub fn samples_mut(&mut self) -> &mut [Sample] {
+        &mut self.samples[..self.len]
+    }
+
+    /// Gets an iterator over the samples of one channel of the last frame, counting from zero,
+    /// without deinterleaving them into another buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer isn't empty and `index` isn't less than [`channels`](Self::channels).
+    #[inline]
+    pub fn channel(&self, index: usize) -> impl ExactSizeIterator<Item = Sample> + '_ {
+        let channels = usize::from(self.channels.max(1));
+        assert!(self.len == 0 || index < channels, "channel {} out of range for {} channels", index, channels);
+        self.samples().get(index..).unwrap_or(&[]).iter().copied().step_by(channels)
+    }
+
+    /// Gets the channel count of the last frame, or 0 if it wasn't audio.
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Gets the sample count per [`channel`](Self::channels) of the last frame.
+    #[inline]
+    pub fn sample_count(&self) -> usize {
+        self.len / usize::from(self.channels.max(1))
+    }
+
+    /// Checks whether there's no samples, as in, the last frame wasn't audio.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Marks the samples as invalid, without touching them.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.channels = 0;
+    }
+}
+
+impl Default for PcmBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RawDecoder {
+    /// Reads the next frame into `buffer`, which is otherwise the same as [`next`](Self::next).
+    ///
+    /// Once the frame is dropped, the buffer holds its samples if it was audio, or is empty otherwise.
+    pub fn next_buffer<'src, 'pcm>(
+        &mut self,
+        src: &'src [u8],
+        buffer: &'pcm mut PcmBuffer,
+    ) -> Option<(Frame<'src, 'pcm>, usize)> {
+        buffer.clear();
+        let dest = buffer.samples.as_mut_ptr();
+        let (frame, len) = unsafe { self.next_into(src, dest, MAX_SAMPLES_PER_FRAME, usize::MAX)? };
+        if let Frame::Audio(audio) = &frame {
+            // the frame only points into `samples`, so these can still be written
+            buffer.len = audio.samples().len();
+            buffer.channels = audio.channels();
+        }
+        Some((frame, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PcmBuffer;
+    use crate::Sample;
+
+    #[test]
+    fn buffer() {
+        let mut buffer = PcmBuffer::new();
+        assert!(buffer.samples().is_empty());
+        assert_eq!(buffer.channel(1).len(), 0);
+
+        buffer.samples[..6].copy_from_slice(&[1i16, 2, 3, 4, 5, 6].map(Sample::from));
+        buffer.len = 6;
+        buffer.channels = 2;
+        assert_eq!(buffer.sample_count(), 3);
+        assert!(buffer.channel(1).eq([2i16, 4, 6].map(Sample::from)));
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.sample_count(), 0);
+    }
+}
@@ -0,0 +1,124 @@
+//! Decoding data which arrives in chunks.
+
+use crate::{DecoderOptions, Frame, RawDecoder, Sample, LOOKAHEAD, MAX_SAMPLES_PER_FRAME};
+use alloc::vec::Vec;
+
+/// High-level streaming iterator over data which is already split into chunks,
+/// such as from a ring buffer or a channel receiver.
+///
+/// Chunks can be anything that's bytes, such as `&[u8]` or `Vec<u8>`, and of any size.
+/// Chunks are copied into a buffer as they're pulled, which stitches together frames split
+/// between them, and what was decoded is dropped from it as it goes, so it holds a little over
+/// 16 KiB and the last chunk at most. Chunks are pulled until 16 KiB is buffered, so an iterator which blocks,
+/// such as a channel receiver's, holds up decoding until that's arrived or it ends.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{ChunksDecoder, Frame};
+/// use std::sync::mpsc;
+///
+/// let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+/// // send chunks of the stream from another thread here!
+/// # drop(sender);
+/// let mut decoder = ChunksDecoder::new(receiver.iter());
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         // play `audio.samples()` here!
+///     }
+/// }
+/// ```
+pub struct ChunksDecoder<I> {
+    chunks: I,
+    ended: bool,
+
+    // the chunks received so far, of which everything before `start` was decoded
+    buffer: Vec<u8>,
+    start: usize,
+
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    raw: RawDecoder,
+}
+
+impl<I> ChunksDecoder<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// Constructs a new `ChunksDecoder` over `chunks`.
+    pub fn new(chunks: I) -> Self {
+        Self::with_options(chunks, DecoderOptions::new())
+    }
+
+    /// Constructs a new `ChunksDecoder` over `chunks` with the given options.
+    pub fn with_options(chunks: I, options: DecoderOptions) -> Self {
+        Self {
+            chunks,
+            ended: false,
+            buffer: Vec::new(),
+            start: 0,
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            raw: RawDecoder::with_options(options),
+        }
+    }
+
+    /// Reads the next frame, skipping over potential garbage data,
+    /// and pulling more chunks first if needed.
+    #[allow(clippy::should_implement_trait)] // it lends out the frame, so it can't be an `Iterator`
+    pub fn next(&mut self) -> Option<Frame<'_, '_>> {
+        self.refill();
+        let (frame, len) = self.raw.next(&self.buffer[self.start..], &mut self.pcm)?;
+        self.start += len;
+        Some(frame)
+    }
+
+    /// Gets a reference to the chunk iterator.
+    #[inline]
+    pub fn chunks(&self) -> &I {
+        &self.chunks
+    }
+
+    /// Consumes the `ChunksDecoder`, returning the chunk iterator.
+    ///
+    /// Whatever was pulled from it but not decoded yet is lost.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.chunks
+    }
+
+    /// Drops what was decoded, and pulls chunks until there's enough after it or they end.
+    fn refill(&mut self) {
+        if self.buffer.len() - self.start > LOOKAHEAD {
+            return;
+        }
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        while !self.ended && self.buffer.len() <= LOOKAHEAD {
+            match self.chunks.next() {
+                Some(chunk) => self.buffer.extend_from_slice(chunk.as_ref()),
+                None => self.ended = true,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::ChunksDecoder;
+    use crate::{test_util::FrameBuilder, Frame};
+
+    #[test]
+    fn frames_across_chunks() {
+        let mp3 = FrameBuilder::new().stream(100).unwrap();
+        // 100 bytes doesn't divide the frame size, so every frame is split up differently
+        let mut decoder = ChunksDecoder::new(mp3.chunks(100));
+        let mut frames = 0;
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                assert_eq!(audio.sample_count(), 1152);
+                frames += 1;
+            }
+        }
+        assert_eq!(frames, 100);
+    }
+}
@@ -0,0 +1,176 @@
+//! Decoding whole files at once.
+
+use crate::{
+    dsp::{to_f32, Resampler},
+    DecoderOptions, FormatPolicy, Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME,
+};
+use alloc::vec::Vec;
+
+/// How many samples minimp3 delays the output by, on top of the encoder delay.
+const DECODER_DELAY: usize = 529;
+
+/// A whole stream decoded by [`decode_all`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedAudio {
+    channels: u16,
+    resampled: bool,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl DecodedAudio {
+    /// Gets the channel count, which is that of the first frame.
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Gets whether part of the stream was at another sample rate, and was resampled.
+    #[inline]
+    pub fn resampled(&self) -> bool {
+        self.resampled
+    }
+
+    /// Gets the sample rate in Hz, which is that of the first frame.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Gets the amount of samples per channel.
+    #[inline]
+    pub fn sample_count(&self) -> usize {
+        self.samples.len() / usize::from(self.channels)
+    }
+
+    /// Gets the interleaved samples, in the range \[-1.0, 1.0\).
+    #[inline]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Consumes the `DecodedAudio`, returning the interleaved samples.
+    #[inline]
+    pub fn into_samples(self) -> Vec<f32> {
+        self.samples
+    }
+}
+
+/// Decodes all of `data` into one buffer, like minimp3's `mp3dec_load_buf`,
+/// returning `None` if there's no audio in it.
+///
+/// The whole stream is converted to the format of its first frame, so it can be played as one:
+/// frames with another channel count are mixed to it (see [`FormatPolicy::Conform`]),
+/// and frames at another sample rate are resampled to it with a [`Resampler`], which
+/// [`resampled`](DecodedAudio::resampled) tells about. If the stream has a LAME tag,
+/// the encoder delay and padding are trimmed off, for gapless playback.
+/// Samples are converted to floats whether or not the `float` feature is enabled.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let audio = rmp3::decode_all(&mp3).ok_or("no audio")?;
+/// println!("{} samples at {} Hz", audio.sample_count(), audio.sample_rate());
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_all(data: &[u8]) -> Option<DecodedAudio> {
+    let mut raw = RawDecoder::with_options(DecoderOptions::new().format_policy(FormatPolicy::Conform));
+    let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+    let mut decoded: Option<DecodedAudio> = None;
+    let mut gapless = None;
+    let mut resampler: Option<(u32, Resampler)> = None;
+    let mut resampled = Vec::new();
+
+    let mut offset = 0;
+    while let Some((frame, len)) = raw.next(&data[offset..], &mut pcm) {
+        offset += len;
+        let audio = match frame {
+            Frame::Audio(audio) => audio,
+            Frame::Xing { header, .. } if decoded.is_none() => {
+                gapless = header.lame().map(|lame| trim_lengths(lame.encoder_delay(), lame.padding()));
+                continue;
+            },
+            _ => continue,
+        };
+        let decoded = decoded.get_or_insert_with(|| DecodedAudio {
+            channels: audio.channels(),
+            resampled: false,
+            sample_rate: audio.sample_rate(),
+            samples: Vec::new(),
+        });
+
+        if audio.sample_rate() == decoded.sample_rate {
+            resampler = None;
+            decoded.samples.extend(audio.samples().iter().copied().map(to_f32));
+            continue;
+        }
+        let resampler = match &mut resampler {
+            Some((rate, resampler)) if *rate == audio.sample_rate() => resampler,
+            _ => {
+                // the rates are from valid headers, and the channels are conformed to 1 or 2
+                let new = Resampler::new(audio.sample_rate(), decoded.sample_rate, decoded.channels).unwrap();
+                &mut resampler.insert((audio.sample_rate(), new)).1
+            },
+        };
+        resampled.resize(resampler.max_output_len(audio.samples().len()), Sample::default());
+        let (_, written) = resampler.process(audio.samples(), &mut resampled);
+        decoded.samples.extend(resampled[..written].iter().copied().map(to_f32));
+        decoded.resampled = true;
+    }
+
+    let mut decoded = decoded?;
+    if let Some((delay, padding)) = gapless {
+        trim(&mut decoded.samples, decoded.channels, delay, padding);
+    }
+    Some(decoded)
+}
+
+/// Gets how many samples per channel to trim off the start and end of the decoded stream,
+/// given the encoder delay and padding from a LAME tag.
+///
+/// The decoder delay shifts the whole stream, so it's part of the padding at the end too,
+/// which is how minimp3's `mp3dec_load` trims it.
+fn trim_lengths(encoder_delay: u16, padding: u16) -> (usize, usize) {
+    let delay = usize::from(encoder_delay) + DECODER_DELAY;
+    (delay, usize::from(padding).saturating_sub(DECODER_DELAY))
+}
+
+/// Trims `delay` samples per channel off the start of `samples`, and `padding` off the end.
+fn trim(samples: &mut Vec<f32>, channels: u16, delay: usize, padding: usize) {
+    let channels = usize::from(channels);
+    let padding = (padding * channels).min(samples.len());
+    samples.truncate(samples.len() - padding);
+    samples.drain(..(delay * channels).min(samples.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{trim, trim_lengths};
+    use alloc::{vec, vec::Vec};
+
+    #[test]
+    fn gapless_trim() {
+        let mut samples: Vec<f32> = (0..10).map(|x| x as f32).collect();
+        trim(&mut samples, 2, 1, 2);
+        assert_eq!(samples, [2.0, 3.0, 4.0, 5.0]);
+
+        let mut samples = vec![0.0; 4];
+        trim(&mut samples, 1, 3, 3);
+        assert!(samples.is_empty());
+
+        assert_eq!(trim_lengths(576, 1152), (1105, 623));
+        assert_eq!(trim_lengths(576, 100), (1105, 0));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn float_samples() {
+        let mp3 = crate::test_util::FrameBuilder::new().stream(10).unwrap();
+        let audio = super::decode_all(&mp3).unwrap();
+        assert_eq!((audio.channels(), audio.sample_rate(), audio.sample_count()), (2, 44100, 10 * 1152));
+        assert!(audio.samples().iter().all(|x| (-1.0..1.0).contains(x)));
+    }
+}
@@ -0,0 +1,96 @@
+//! DC offset removal.
+
+use crate::Sample;
+
+/// Cutoff frequency used by [`DcBlocker::new`], low enough to leave audible bass alone.
+const DEFAULT_CUTOFF: f32 = 10.0;
+
+/// A first-order high-pass (DC blocking) filter, which removes any constant offset
+/// in decoded audio that would otherwise click when joining streams together.
+///
+/// The filter keeps its state between calls, so it should be given every frame of a stream in order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DcBlocker {
+    channels: usize,
+    pole: f32,
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl DcBlocker {
+    /// Constructs a new `DcBlocker` for audio of the given format, with a 10 Hz cutoff.
+    ///
+    /// Returns `None` if there's no channels or more than two.
+    pub fn new(sample_rate: u32, channels: u16) -> Option<Self> {
+        Self::with_cutoff(sample_rate, channels, DEFAULT_CUTOFF)
+    }
+
+    /// Constructs a new `DcBlocker` for audio of the given format, with the given cutoff in Hz.
+    ///
+    /// Returns `None` if there's no channels or more than two, or if the sample rate is zero.
+    pub fn with_cutoff(sample_rate: u32, channels: u16, cutoff: f32) -> Option<Self> {
+        if sample_rate == 0 || !(1..=2).contains(&channels) {
+            return None;
+        }
+        // the usual approximation of the pole position, which is accurate for low cutoffs
+        let pole = (1.0 - 2.0 * core::f32::consts::PI * cutoff / sample_rate as f32).max(0.0);
+        Some(Self { channels: channels as usize, pole, x: [0.0; 2], y: [0.0; 2] })
+    }
+
+    /// Filters interleaved samples in place, in the format given when constructing it.
+    pub fn process(&mut self, samples: &mut [Sample]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            for (i, sample) in frame.iter_mut().enumerate() {
+                #[cfg(not(feature = "float"))]
+                let x = f32::from(*sample) / 32768.0;
+                #[cfg(feature = "float")]
+                let x = *sample;
+
+                let y = x - self.x[i] + self.pole * self.y[i];
+                self.x[i] = x;
+                self.y[i] = y;
+
+                #[cfg(not(feature = "float"))]
+                {
+                    *sample = super::gain::to_i16(y);
+                }
+                #[cfg(feature = "float")]
+                {
+                    *sample = y;
+                }
+            }
+        }
+    }
+
+    /// Forgets the filter state, such as after seeking.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.x = [0.0; 2];
+        self.y = [0.0; 2];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DcBlocker;
+    use crate::Sample;
+
+    #[test]
+    fn dc() {
+        assert!(DcBlocker::new(44100, 0).is_none());
+
+        #[cfg(not(feature = "float"))]
+        let offset: Sample = 8192;
+        #[cfg(feature = "float")]
+        let offset: Sample = 0.25;
+
+        let mut blocker = DcBlocker::new(44100, 2).unwrap();
+        let mut samples = [offset; 44100];
+        blocker.process(&mut samples);
+        assert_eq!(samples[0], offset);
+        #[cfg(not(feature = "float"))]
+        assert_eq!(samples[44099], 0);
+        #[cfg(feature = "float")]
+        assert!(samples[44099].abs() < 1e-6);
+    }
+}
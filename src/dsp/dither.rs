@@ -0,0 +1,161 @@
+//! Dithered conversion to 16-bit PCM.
+
+/// Describes how the noise added by [`Dither`] is distributed across frequencies.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum NoiseShaping {
+    /// Plain TPDF (triangular) dither, which is white noise, and the default.
+    #[default]
+    Flat,
+
+    /// First-order shaping, which moves the noise towards high frequencies, where it's less audible.
+    FirstOrder,
+
+    /// Second-order shaping, which moves the noise further towards high frequencies still.
+    ///
+    /// This has the least audible noise for sample rates of 44.1 kHz and up, such as for archiving,
+    /// but more noise in total, which can be heard at lower sample rates.
+    SecondOrder,
+}
+
+/// Converts floats to 16-bit PCM with TPDF dither and optional [noise shaping](NoiseShaping),
+/// which turns the distortion of rounding quiet passages into a constant, inaudible noise floor.
+///
+/// The noise comes from a small built-in generator, so output is the same every time for the same input.
+/// The shaping filter keeps its state between calls, so it should be given every frame of a stream in order.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "float")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use rmp3::{dsp::{Dither, NoiseShaping}, Decoder, Frame};
+///
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut dither = Dither::new(2, NoiseShaping::SecondOrder).unwrap();
+/// let mut pcm = [0i16; rmp3::MAX_SAMPLES_PER_FRAME];
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         let len = dither.process(audio.samples(), &mut pcm);
+///         // write `pcm[..len]` to a file here!
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "float"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dither {
+    channels: usize,
+    shaping: NoiseShaping,
+
+    // the last two quantization errors per channel, in LSBs, newest first
+    errors: [[f32; 2]; 2],
+    random: u32,
+}
+
+/// Seed of the noise generator, which can be anything other than zero.
+const SEED: u32 = 0x9E37_79B9;
+
+impl Dither {
+    /// Constructs a new `Dither` for audio with the given channel count.
+    ///
+    /// Returns `None` if there's no channels or more than two.
+    pub fn new(channels: u16, shaping: NoiseShaping) -> Option<Self> {
+        if !(1..=2).contains(&channels) {
+            return None;
+        }
+        Some(Self { channels: channels as usize, shaping, errors: [[0.0; 2]; 2], random: SEED })
+    }
+
+    /// Converts interleaved samples in the range \[-1.0, 1.0\] into `dest`, in the channel count
+    /// given when constructing it, clipping anything out of range, and returns how many samples were written.
+    ///
+    /// Only as many whole sample frames as fit in the shorter slice are converted.
+    pub fn process(&mut self, samples: &[f32], dest: &mut [i16]) -> usize {
+        let mut written = 0;
+        for (frame, dest) in samples.chunks_exact(self.channels).zip(dest.chunks_exact_mut(self.channels)) {
+            for (i, (&x, y)) in frame.iter().zip(dest).enumerate() {
+                let noise = self.uniform() - self.uniform();
+                let errors = &mut self.errors[i];
+                let feedback = match self.shaping {
+                    NoiseShaping::Flat => 0.0,
+                    NoiseShaping::FirstOrder => errors[0],
+                    NoiseShaping::SecondOrder => 2.0 * errors[0] - errors[1],
+                };
+                let wanted = x * 32768.0 - feedback;
+                let z = wanted + noise;
+                let rounded = if z >= 32767.0 {
+                    32767.0
+                } else if z <= -32768.0 {
+                    -32768.0
+                } else {
+                    // CAST: This is in range, and the cast truncates towards zero after rounding.
+                    (if z < 0.0 { z - 0.5 } else { z + 0.5 }) as i16 as f32
+                };
+                // CAST: This was rounded and clipped above.
+                *y = rounded as i16;
+                // clipping makes the error huge, which would throw the filter off for a while
+                let error = (rounded - wanted).clamp(-2.0, 2.0);
+                *errors = [error, errors[0]];
+            }
+            written += self.channels;
+        }
+        written
+    }
+
+    /// Forgets the shaping filter state and restarts the noise, such as after seeking.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.errors = [[0.0; 2]; 2];
+        self.random = SEED;
+    }
+
+    /// Gets a uniformly distributed random number in \[0.0, 1.0\), with a xorshift generator.
+    #[inline(always)]
+    fn uniform(&mut self) -> f32 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random = x;
+        // the top 24 bits, which is all a float can hold exactly
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dither, NoiseShaping};
+
+    #[test]
+    fn dither() {
+        assert!(Dither::new(3, NoiseShaping::Flat).is_none());
+
+        // silence turns into noise of at most one LSB, or a bit more when shaped
+        let mut dest = [0i16; 1000];
+        for (shaping, max) in [(NoiseShaping::Flat, 1), (NoiseShaping::FirstOrder, 2), (NoiseShaping::SecondOrder, 4)] {
+            let mut dither = Dither::new(2, shaping).unwrap();
+            assert_eq!(dither.process(&[0.0; 1001], &mut dest), 1000);
+            assert!(dest.iter().all(|x| x.abs() <= max));
+            assert!(dest.iter().any(|&x| x != 0));
+        }
+
+        // while the average stays where it was, even between two LSBs
+        let mut dither = Dither::new(1, NoiseShaping::SecondOrder).unwrap();
+        dither.process(&[0.25 + 0.5 / 32768.0; 1000], &mut dest);
+        let average = dest.iter().map(|&x| f64::from(x)).sum::<f64>() / 1000.0;
+        assert!((average - 8192.5).abs() < 0.01);
+
+        dither.reset();
+        let mut again = [0i16; 1000];
+        dither.process(&[0.25 + 0.5 / 32768.0; 1000], &mut again);
+        assert_eq!(dest, again);
+
+        assert_eq!(dither.process(&[2.0, -2.0], &mut dest), 2);
+        assert_eq!(dest[..2], [i16::MAX, i16::MIN]);
+    }
+}
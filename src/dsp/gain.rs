@@ -0,0 +1,272 @@
+//! Gain application and conversion to 16-bit PCM.
+
+/// Multiplies every sample by `gain`, as in, a linear volume factor.
+///
+/// With the `simd` feature, this uses SSE2 on x86 targets, NEON on AArch64 targets,
+/// or SIMD128 on WebAssembly targets, which have it.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+    // SAFETY: SSE2 is known to be available at compile-time.
+    let samples = unsafe { sse2::apply_gain(samples, gain) };
+    #[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+    // SAFETY: NEON is known to be available at compile-time.
+    let samples = unsafe { neon::apply_gain(samples, gain) };
+    #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+    // SAFETY: SIMD128 is known to be available at compile-time.
+    let samples = unsafe { simd128::apply_gain(samples, gain) };
+    for x in samples {
+        *x *= gain;
+    }
+}
+
+/// Multiplies every sample by `gain` and converts them to 16-bit PCM in `dest`,
+/// clipping anything out of range, and returns how many samples were written.
+///
+/// Only as many samples as fit in the shorter slice are converted.
+/// With the `simd` feature, this uses SSE2 on x86 targets, NEON on AArch64 targets,
+/// or SIMD128 on WebAssembly targets, which have it.
+pub fn apply_gain_to_i16(samples: &[f32], gain: f32, dest: &mut [i16]) -> usize {
+    let len = samples.len().min(dest.len());
+    let (samples, dest) = (&samples[..len], &mut dest[..len]);
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+    // SAFETY: SSE2 is known to be available at compile-time.
+    let (samples, dest) = unsafe { sse2::apply_gain_to_i16(samples, gain, dest) };
+    #[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+    // SAFETY: NEON is known to be available at compile-time.
+    let (samples, dest) = unsafe { neon::apply_gain_to_i16(samples, gain, dest) };
+    #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+    // SAFETY: SIMD128 is known to be available at compile-time.
+    let (samples, dest) = unsafe { simd128::apply_gain_to_i16(samples, gain, dest) };
+    for (x, y) in samples.iter().zip(dest) {
+        *y = to_i16(*x * gain);
+    }
+    len
+}
+
+/// Describes what happens to samples beyond full scale when converting floats to 16-bit PCM,
+/// which hot masters often have after decoding.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Clipping {
+    /// Samples are clipped at full scale, which is the default.
+    ///
+    /// Everything in range is left untouched, but clipped peaks distort harshly.
+    #[default]
+    Hard,
+
+    /// Samples beyond half of full scale (-6 dBFS) are smoothly compressed towards full scale,
+    /// so they saturate instead of being cut off, at the cost of slightly colouring loud passages.
+    Soft,
+}
+
+/// Like [`apply_gain_to_i16`], but handling samples beyond full scale according to `clipping`.
+///
+/// Only [`Clipping::Hard`] uses SIMD.
+pub fn apply_gain_to_i16_with(samples: &[f32], gain: f32, clipping: Clipping, dest: &mut [i16]) -> usize {
+    match clipping {
+        Clipping::Hard => apply_gain_to_i16(samples, gain, dest),
+        Clipping::Soft => {
+            let len = samples.len().min(dest.len());
+            for (x, y) in samples.iter().zip(dest) {
+                *y = to_i16(soft_clip(*x * gain));
+            }
+            len
+        },
+    }
+}
+
+/// Compresses a sample above the knee at 0.5 as `u / (1 + u)` does, which meets it at the same slope,
+/// and only approaches full scale without reaching it.
+#[inline(always)]
+fn soft_clip(x: f32) -> f32 {
+    const KNEE: f32 = 0.5;
+    let magnitude = if x < 0.0 { -x } else { x };
+    if magnitude <= KNEE {
+        return x;
+    }
+    let over = (magnitude - KNEE) / (1.0 - KNEE);
+    let magnitude = KNEE + (1.0 - KNEE) * over / (1.0 + over);
+    if x < 0.0 { -magnitude } else { magnitude }
+}
+
+/// Converts a decoded sample to a float in the range \[-1.0, 1.0\).
+#[cfg(feature = "alloc")]
+#[inline(always)]
+pub(crate) fn to_f32(x: crate::Sample) -> f32 {
+    #[cfg(not(feature = "float"))]
+    {
+        f32::from(x) / 32768.0
+    }
+    #[cfg(feature = "float")]
+    {
+        x
+    }
+}
+
+/// Converts a float sample to 16-bit PCM, rounding to the nearest value and clipping.
+#[inline(always)]
+pub(crate) fn to_i16(x: f32) -> i16 {
+    let x = x * 32768.0;
+    if x >= 32767.0 {
+        i16::MAX
+    } else if x <= -32768.0 {
+        i16::MIN
+    } else {
+        // CAST: This is in range, and the cast truncates towards zero after rounding.
+        (if x < 0.0 { x - 0.5 } else { x + 0.5 }) as i16
+    }
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    /// Applies gain to as much as possible 4 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain(samples: &mut [f32], gain: f32) -> &mut [f32] {
+        let split = samples.len() - samples.len() % 4;
+        let (chunks, rest) = samples.split_at_mut(split);
+        let gain = _mm_set1_ps(gain);
+        for chunk in chunks.chunks_exact_mut(4) {
+            let x = _mm_loadu_ps(chunk.as_ptr());
+            _mm_storeu_ps(chunk.as_mut_ptr(), _mm_mul_ps(x, gain));
+        }
+        rest
+    }
+
+    /// Applies gain and converts as much as possible 8 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain_to_i16<'a, 'b>(
+        samples: &'a [f32],
+        gain: f32,
+        dest: &'b mut [i16],
+    ) -> (&'a [f32], &'b mut [i16]) {
+        let split = samples.len() - samples.len() % 8;
+        let (chunks, rest) = samples.split_at(split);
+        let (dest_chunks, dest_rest) = dest.split_at_mut(split);
+
+        // the scale is folded into the gain, and `cvtps` rounds to the nearest (even) value
+        let gain = _mm_set1_ps(gain * 32768.0);
+        let (min, max) = (_mm_set1_ps(-32768.0), _mm_set1_ps(32767.0));
+        for (chunk, dest) in chunks.chunks_exact(8).zip(dest_chunks.chunks_exact_mut(8)) {
+            let low = _mm_mul_ps(_mm_loadu_ps(chunk.as_ptr()), gain);
+            let high = _mm_mul_ps(_mm_loadu_ps(chunk.as_ptr().add(4)), gain);
+            let low = _mm_cvtps_epi32(_mm_min_ps(_mm_max_ps(low, min), max));
+            let high = _mm_cvtps_epi32(_mm_min_ps(_mm_max_ps(high, min), max));
+            _mm_storeu_si128(dest.as_mut_ptr() as *mut __m128i, _mm_packs_epi32(low, high));
+        }
+        (rest, dest_rest)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use core::arch::aarch64::*;
+
+    /// Applies gain to as much as possible 4 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain(samples: &mut [f32], gain: f32) -> &mut [f32] {
+        let split = samples.len() - samples.len() % 4;
+        let (chunks, rest) = samples.split_at_mut(split);
+        let gain = vdupq_n_f32(gain);
+        for chunk in chunks.chunks_exact_mut(4) {
+            let x = vld1q_f32(chunk.as_ptr());
+            vst1q_f32(chunk.as_mut_ptr(), vmulq_f32(x, gain));
+        }
+        rest
+    }
+
+    /// Applies gain and converts as much as possible 8 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain_to_i16<'a, 'b>(
+        samples: &'a [f32],
+        gain: f32,
+        dest: &'b mut [i16],
+    ) -> (&'a [f32], &'b mut [i16]) {
+        let split = samples.len() - samples.len() % 8;
+        let (chunks, rest) = samples.split_at(split);
+        let (dest_chunks, dest_rest) = dest.split_at_mut(split);
+
+        // `cvtn` rounds to the nearest (even) value like SSE2's `cvtps`, and `qmovn` does the clipping
+        let gain = vdupq_n_f32(gain * 32768.0);
+        let convert = |x: float32x4_t| vqmovn_s32(vcvtnq_s32_f32(x));
+        for (chunk, dest) in chunks.chunks_exact(8).zip(dest_chunks.chunks_exact_mut(8)) {
+            let low = vmulq_f32(vld1q_f32(chunk.as_ptr()), gain);
+            let high = vmulq_f32(vld1q_f32(chunk.as_ptr().add(4)), gain);
+            vst1q_s16(dest.as_mut_ptr(), vcombine_s16(convert(low), convert(high)));
+        }
+        (rest, dest_rest)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128 {
+    use core::arch::wasm32::*;
+
+    /// Applies gain to as much as possible 4 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain(samples: &mut [f32], gain: f32) -> &mut [f32] {
+        let split = samples.len() - samples.len() % 4;
+        let (chunks, rest) = samples.split_at_mut(split);
+        let gain = f32x4_splat(gain);
+        for chunk in chunks.chunks_exact_mut(4) {
+            let x = v128_load(chunk.as_ptr() as *const v128);
+            v128_store(chunk.as_mut_ptr() as *mut v128, f32x4_mul(x, gain));
+        }
+        rest
+    }
+
+    /// Applies gain and converts as much as possible 8 at a time, returning the rest.
+    pub(super) unsafe fn apply_gain_to_i16<'a, 'b>(
+        samples: &'a [f32],
+        gain: f32,
+        dest: &'b mut [i16],
+    ) -> (&'a [f32], &'b mut [i16]) {
+        let split = samples.len() - samples.len() % 8;
+        let (chunks, rest) = samples.split_at(split);
+        let (dest_chunks, dest_rest) = dest.split_at_mut(split);
+
+        // rounding to the nearest (even) value first matches what SSE2's `cvtps` does
+        let gain = f32x4_splat(gain * 32768.0);
+        let (min, max) = (f32x4_splat(-32768.0), f32x4_splat(32767.0));
+        let convert = |x: v128| i32x4_trunc_sat_f32x4(f32x4_nearest(f32x4_min(f32x4_max(x, min), max)));
+        for (chunk, dest) in chunks.chunks_exact(8).zip(dest_chunks.chunks_exact_mut(8)) {
+            let low = f32x4_mul(v128_load(chunk.as_ptr() as *const v128), gain);
+            let high = f32x4_mul(v128_load(chunk.as_ptr().add(4) as *const v128), gain);
+            v128_store(dest.as_mut_ptr() as *mut v128, i16x8_narrow_i32x4(convert(low), convert(high)));
+        }
+        (rest, dest_rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_gain, apply_gain_to_i16, apply_gain_to_i16_with, Clipping};
+
+    #[test]
+    fn gain() {
+        let mut samples = [0.25f32; 11];
+        samples[9] = -1.0;
+        apply_gain(&mut samples, 2.0);
+        assert!(samples[..9].iter().all(|&x| x == 0.5));
+        assert_eq!(samples[9..], [-2.0, 0.5]);
+
+        let mut dest = [0i16; 10];
+        assert_eq!(apply_gain_to_i16(&samples, 0.5, &mut dest), 10);
+        assert!(dest[..9].iter().all(|&x| x == 8192));
+        assert_eq!(dest[9], i16::MIN);
+        assert_eq!(apply_gain_to_i16(&[2.0, -0.5], 1.0, &mut dest), 2);
+        assert_eq!(dest[..2], [i16::MAX, -16384]);
+    }
+    #[test]
+    fn soft_clip() {
+        let samples = [0.25, 0.75, -2.0, 100.0];
+        let mut dest = [0i16; 4];
+        assert_eq!(apply_gain_to_i16_with(&samples, 1.0, Clipping::Soft, &mut dest), 4);
+        assert_eq!(dest[0], 8192);
+        // 0.5 + 0.5 * (0.5 / 1.5)
+        assert_eq!(dest[1], 21845);
+        // 0.5 + 0.5 * (3 / 4)
+        assert_eq!(dest[2], -28672);
+        assert!(dest[3] > 32000 && dest[3] < i16::MAX);
+    }
+}
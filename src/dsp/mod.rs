@@ -0,0 +1,21 @@
+//! Processing of decoded audio, such as applying gain or resampling.
+//!
+//! Unlike the `analysis` module, this doesn't need `std` (other than where noted),
+//! and it all works in place or between caller-provided buffers.
+
+mod dc;
+mod dither;
+mod gain;
+mod normalize;
+mod resample;
+
+#[cfg(feature = "alloc")]
+pub(crate) use gain::to_f32;
+#[cfg(all(feature = "bytes", feature = "float"))]
+pub(crate) use gain::to_i16;
+
+pub use dc::DcBlocker;
+pub use dither::{Dither, NoiseShaping};
+pub use gain::{apply_gain, apply_gain_to_i16, apply_gain_to_i16_with, Clipping};
+pub use normalize::{Normalizer, PeakNormalizer};
+pub use resample::{Interpolation, RateConverter, Resampler};
@@ -0,0 +1,266 @@
+//! Peak and loudness normalization.
+
+use crate::Sample;
+
+/// Scales audio so that its peak hits a target level, in two passes:
+/// one to measure the peak (see [`scan`](Self::scan)), and one to apply the gain.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "analysis")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use rmp3::{dsp::PeakNormalizer, Decoder, Frame};
+///
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let normalizer = PeakNormalizer::scan(&mut decoder, -1.0).unwrap_or_default();
+/// decoder.set_position(0);
+/// let mut pcm = Vec::new();
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         let start = pcm.len();
+///         pcm.extend_from_slice(audio.samples());
+///         normalizer.process(&mut pcm[start..]);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "analysis"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeakNormalizer {
+    gain: f32,
+}
+
+impl PeakNormalizer {
+    /// Constructs a new `PeakNormalizer` scaling audio with the given peak to the target,
+    /// both as linear amplitudes where 1.0 is full scale.
+    ///
+    /// Silent audio (a peak of zero) is left as-is.
+    pub fn new(peak: f64, target: f64) -> Self {
+        let gain = if peak > 0.0 { target / peak } else { 1.0 };
+        Self { gain: gain as f32 }
+    }
+
+    /// Decodes every remaining frame of `decoder` to measure the peak,
+    /// returning a `PeakNormalizer` scaling it to `target_db` in dBFS, such as -1.0.
+    ///
+    /// Returns `None` if there's no audio at all.
+    #[cfg(feature = "analysis")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "analysis")))]
+    pub fn scan<P: crate::PcmStorage>(decoder: &mut crate::Decoder<'_, P>, target_db: f64) -> Option<Self> {
+        let mut level: Option<crate::analysis::Level> = None;
+        while let Some(frame) = decoder.next() {
+            if let crate::Frame::Audio(audio) = frame {
+                level.get_or_insert_with(Default::default).process(audio.samples());
+            }
+        }
+        Some(Self::new(level?.peak(), 10f64.powf(target_db / 20.0)))
+    }
+
+    /// Gets the linear gain this applies.
+    #[inline]
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Applies the gain to samples in place.
+    pub fn process(&self, samples: &mut [Sample]) {
+        #[cfg(feature = "float")]
+        super::apply_gain(samples, self.gain);
+        #[cfg(not(feature = "float"))]
+        for x in samples {
+            *x = super::gain::to_i16(f32::from(*x) / 32768.0 * self.gain);
+        }
+    }
+}
+
+impl Default for PeakNormalizer {
+    /// Gets a `PeakNormalizer` which leaves audio as-is.
+    fn default() -> Self {
+        Self { gain: 1.0 }
+    }
+}
+
+/// Time over which [`Normalizer`] measures the level, which is that of EBU R128's momentary loudness.
+const LEVEL_WINDOW_MS: f32 = 400.0;
+
+/// A streaming automatic gain control, which continuously moves the level of audio towards a target,
+/// such as for a "night mode" that evens out quiet and loud passages as they play.
+///
+/// The level is measured as the average amplitude over the last 400 ms or so.
+/// When the audio gets louder, the gain comes down over the attack time,
+/// and when it gets quieter, the gain goes back up over the release time, up to a maximum.
+/// Unlike [`PeakNormalizer`], this needs only one pass, at the cost of changing the dynamics.
+///
+/// The normalizer keeps its state between calls, so it should be given every frame of a stream in order.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{dsp::Normalizer, Decoder, Frame};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut normalizer = Normalizer::new(44100, 2, 0.1).unwrap().release(2000.0);
+/// let mut pcm = Vec::new();
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         let start = pcm.len();
+///         pcm.extend_from_slice(audio.samples());
+///         normalizer.process(&mut pcm[start..]);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normalizer {
+    channels: usize,
+    sample_rate: u32,
+    target: f32,
+    max_gain: f32,
+
+    // how far the level and gain move towards their new values per sample frame
+    attack: f32,
+    level_speed: f32,
+    release: f32,
+
+    gain: f32,
+    level: Option<f32>,
+}
+
+impl Normalizer {
+    /// Constructs a new `Normalizer` for audio of the given format, moving its level towards `target`
+    /// as a linear amplitude where 1.0 is full scale, with the defaults of a 50 ms attack,
+    /// a 1000 ms release, and a maximum gain of 10.0 (20 dB).
+    ///
+    /// A `target` of around 0.1 suits most music.
+    /// Returns `None` if there's no channels or more than two, or if the sample rate is zero.
+    pub fn new(sample_rate: u32, channels: u16, target: f32) -> Option<Self> {
+        if sample_rate == 0 || !(1..=2).contains(&channels) {
+            return None;
+        }
+        Some(Self {
+            channels: channels as usize,
+            sample_rate,
+            target,
+            max_gain: 10.0,
+            attack: speed(50.0, sample_rate),
+            level_speed: speed(LEVEL_WINDOW_MS, sample_rate),
+            release: speed(1000.0, sample_rate),
+            gain: 1.0,
+            level: None,
+        })
+    }
+
+    /// Sets how many milliseconds the gain takes to come down when the audio gets louder.
+    pub fn attack(mut self, ms: f32) -> Self {
+        self.attack = speed(ms, self.sample_rate);
+        self
+    }
+
+    /// Sets how many milliseconds the gain takes to go back up when the audio gets quieter.
+    pub fn release(mut self, ms: f32) -> Self {
+        self.release = speed(ms, self.sample_rate);
+        self
+    }
+
+    /// Sets the highest linear gain this applies, so that silence and quiet passages
+    /// don't get amplified to the target.
+    pub fn max_gain(mut self, gain: f32) -> Self {
+        self.max_gain = gain;
+        self
+    }
+
+    /// Gets the linear gain this is applying right now.
+    #[inline]
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Applies the gain to interleaved samples in place, in the format given when constructing it.
+    pub fn process(&mut self, samples: &mut [Sample]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let mut amplitude = 0.0;
+            for &x in frame.iter() {
+                #[cfg(not(feature = "float"))]
+                let x = f32::from(x) / 32768.0;
+                amplitude += if x < 0.0 { -x } else { x };
+            }
+            let amplitude = amplitude / self.channels as f32;
+            // the first frame starts the measurement right away, rather than fading in from silence
+            let level = self.level.get_or_insert(amplitude);
+            *level += (amplitude - *level) * self.level_speed;
+
+            let wanted = if *level * self.max_gain > self.target { self.target / *level } else { self.max_gain };
+            let speed = if wanted < self.gain { self.attack } else { self.release };
+            self.gain += (wanted - self.gain) * speed;
+
+            #[cfg(feature = "float")]
+            frame.iter_mut().for_each(|x| *x *= self.gain);
+            #[cfg(not(feature = "float"))]
+            for x in frame {
+                *x = super::gain::to_i16(f32::from(*x) / 32768.0 * self.gain);
+            }
+        }
+    }
+
+    /// Forgets the measured level and goes back to unity gain, such as when starting a new stream.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+        self.level = None;
+    }
+}
+
+/// Gets how far a one-pole smoother moves per sample frame to settle in roughly `ms` milliseconds.
+#[inline]
+fn speed(ms: f32, sample_rate: u32) -> f32 {
+    let frames = ms * sample_rate as f32 / 1000.0;
+    if frames > 1.0 { 1.0 / frames } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Normalizer, PeakNormalizer};
+    use crate::Sample;
+
+    #[test]
+    fn normalize() {
+        let normalizer = PeakNormalizer::new(0.25, 0.5);
+        assert_eq!(normalizer.gain(), 2.0);
+        assert_eq!(PeakNormalizer::new(0.0, 0.5), PeakNormalizer::default());
+
+        #[cfg(not(feature = "float"))]
+        let (mut samples, expected): ([Sample; 2], [Sample; 2]) = ([8192, -8192], [16384, -16384]);
+        #[cfg(feature = "float")]
+        let (mut samples, expected): ([Sample; 2], [Sample; 2]) = ([0.25, -0.25], [0.5, -0.5]);
+        normalizer.process(&mut samples);
+        assert_eq!(samples, expected);
+    }
+    #[test]
+    fn normalizer() {
+        assert!(Normalizer::new(0, 2, 0.1).is_none());
+
+        #[cfg(not(feature = "float"))]
+        let (quiet, loud): (Sample, Sample) = (1638, 16384);
+        #[cfg(feature = "float")]
+        let (quiet, loud): (Sample, Sample) = (0.05, 0.5);
+
+        // quiet audio is brought up, but no further than the maximum gain
+        let mut normalizer = Normalizer::new(1000, 1, 0.1).unwrap().max_gain(1.5);
+        normalizer.process(&mut [quiet; 10000]);
+        assert!((normalizer.gain() - 1.5).abs() < 1e-3);
+
+        // and loud audio is brought down
+        let mut normalizer = Normalizer::new(1000, 1, 0.1).unwrap();
+        normalizer.process(&mut [loud; 10000]);
+        assert!((normalizer.gain() - 0.2).abs() < 1e-3);
+        normalizer.reset();
+        assert_eq!(normalizer.gain(), 1.0);
+    }
+}
@@ -0,0 +1,787 @@
+//! Sample rate conversion.
+
+use crate::Sample;
+
+/// How many input samples the sinc filter spans, half of them on either side of the output.
+const TAPS: usize = 32;
+
+/// How many positions between two input samples the sinc filter is tabulated at.
+const PHASES: usize = 64;
+
+/// Describes how a [`Resampler`] works out the output samples between the input ones.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Interpolation {
+    /// A straight line between the input samples on either side, which is the default.
+    ///
+    /// This is cheap, but there's no anti-aliasing filter, so it's best suited for converting
+    /// between close rates, and upsampling, where the lack of one is the least audible.
+    #[default]
+    Linear,
+
+    /// A polyphase windowed sinc filter over the 32 input samples around each output sample.
+    ///
+    /// It cuts off at 90% of the input's Nyquist frequency, which removes the images of upsampling,
+    /// and the aliasing of downsampling by up to about 10%, such as from 48 kHz to 44.1 kHz,
+    /// for 32 multiplications per output sample. Downsampling further than that still aliases.
+    /// The last 16 input samples are held back until the input after them arrives.
+    Sinc,
+}
+
+/// A streaming resampler, such as for playing a 44.1 kHz stream on a DAC which only runs at 48 kHz.
+///
+/// The position between input samples is kept as an exact fraction, so rates don't drift
+/// over long streams, and without the `float` feature, resampling uses no floating point.
+/// The output is [interpolated](Interpolation) linearly by default.
+///
+/// The resampler keeps its state between calls, so it should be given every frame of a stream in order,
+/// and then [flushed](Self::flush) for the outputs after the last input.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{dsp::{Interpolation, Resampler}, Decoder, Frame, Sample};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut resampler = Resampler::new(44100, 48000, 2).unwrap().interpolation(Interpolation::Sinc);
+/// let mut output = [Sample::default(); 1024];
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         let mut samples = audio.samples();
+///         while !samples.is_empty() {
+///             let (consumed, written) = resampler.process(samples, &mut output);
+///             samples = &samples[consumed..];
+///             // play `output[..written]` here!
+///         }
+///     }
+/// }
+/// let written = resampler.flush(&mut output);
+/// // and play `output[..written]` last!
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resampler {
+    channels: usize,
+    interpolation: Interpolation,
+
+    // input and output rates, reduced to lowest terms
+    from: u32,
+    to: u32,
+
+    // the last input sample frames as a ring buffer starting at `head`, if there's been any,
+    // and how far past the one `lookahead` from the end the next output is, in `1 / to`ths
+    history: Option<[[Sample; 2]; TAPS]>,
+    head: usize,
+    phase: u32,
+
+    // inputs to take before there's any output, so the first input is where the output starts
+    delay: usize,
+    // copies of the last input still to be taken to get out what's held back
+    flushing: usize,
+}
+
+impl Resampler {
+    /// Constructs a new `Resampler` from one sample rate to another, for audio with the given channel count.
+    ///
+    /// Returns `None` if there's no channels or more than two, or if either rate is zero.
+    pub fn new(from: u32, to: u32, channels: u16) -> Option<Self> {
+        if from == 0 || to == 0 || !(1..=2).contains(&channels) {
+            return None;
+        }
+        let divisor = gcd(from, to);
+        Some(Self {
+            channels: channels as usize,
+            interpolation: Interpolation::Linear,
+            from: from / divisor,
+            to: to / divisor,
+            history: None,
+            head: 0,
+            phase: 0,
+            delay: 0,
+            flushing: 0,
+        })
+    }
+
+    /// Sets how the output samples are worked out, which also forgets the resampler state.
+    #[inline]
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self.reset();
+        self
+    }
+
+    /// Gets how many samples at most are written for `len` input samples,
+    /// which is useful for sizing the output buffer.
+    pub fn max_output_len(&self, len: usize) -> usize {
+        let frames = (len / self.channels) as u64;
+        let max = (frames * u64::from(self.to)).div_ceil(u64::from(self.from)) + 1;
+        max as usize * self.channels
+    }
+
+    /// Resamples interleaved samples from `input` into `output`, in the format given when constructing it,
+    /// returning how many samples were consumed and written.
+    ///
+    /// This stops when either all of `input` was consumed, or `output` is full,
+    /// so it should be called again with the rest of the input if it's too short.
+    pub fn process(&mut self, input: &[Sample], output: &mut [Sample]) -> (usize, usize) {
+        let channels = self.channels;
+        let mut inputs = input.chunks_exact(channels);
+        let mut outputs = output.chunks_exact_mut(channels);
+        let mut history = match self.history {
+            Some(history) => history,
+            None => match inputs.next() {
+                // as if it had been there all along, which is what the output starts at
+                Some(first) => {
+                    self.delay = self.lookahead() - 1;
+                    [frame(first); TAPS]
+                },
+                None => return (0, 0),
+            },
+        };
+        let mut consumed = input.len() / channels - inputs.len();
+        let mut written = 0;
+        'resample: for next in inputs {
+            let next = frame(next);
+            if self.delay != 0 {
+                self.delay -= 1;
+            } else {
+                while self.phase < self.to {
+                    let output = match outputs.next() {
+                        Some(output) => output,
+                        None => break 'resample,
+                    };
+                    self.interpolate(&history, &next, output);
+                    written += 1;
+                    self.phase += self.from;
+                }
+                self.phase -= self.to;
+            }
+            history[self.head] = next;
+            self.head = (self.head + 1) % TAPS;
+            consumed += 1;
+        }
+        self.history = Some(history);
+        (consumed * channels, written * channels)
+    }
+
+    /// Writes out the outputs after the last input, which are held back until the input after it arrives,
+    /// returning how many samples were written. This is for the end of the stream, and once it's all out,
+    /// the resampler state is forgotten, so it can be used for another stream.
+    ///
+    /// The last input is held for as long as needed, as if the stream went on with it.
+    /// Like [`process`](Self::process), this stops when `output` is full,
+    /// so it should be called again if it was filled.
+    pub fn flush(&mut self, output: &mut [Sample]) -> usize {
+        let last = match self.history {
+            Some(history) => history[(self.head + TAPS - 1) % TAPS],
+            None => return 0,
+        };
+        if self.flushing == 0 {
+            self.flushing = self.lookahead();
+        }
+        let mut written = 0;
+        while self.flushing != 0 {
+            let (consumed, len) = self.process(&last[..self.channels], &mut output[written..]);
+            written += len;
+            if consumed == 0 {
+                return written;
+            }
+            self.flushing -= 1;
+        }
+        self.reset();
+        written
+    }
+
+    /// Forgets the resampler state, such as after seeking.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.history = None;
+        self.head = 0;
+        self.phase = 0;
+        self.delay = 0;
+        self.flushing = 0;
+    }
+
+    /// Gets how many inputs past the one before it an output needs.
+    fn lookahead(&self) -> usize {
+        match self.interpolation {
+            Interpolation::Linear => 1,
+            Interpolation::Sinc => TAPS / 2,
+        }
+    }
+
+    /// Works out the output at the current phase, from the history and the input after it.
+    fn interpolate(&self, history: &[[Sample; 2]; TAPS], next: &[Sample; 2], output: &mut [Sample]) {
+        // counting from the oldest, with `next` last, as it's where it'll go
+        let tap = |i: usize| match i {
+            i if i == TAPS - 1 => next,
+            i => &history[(self.head + 1 + i) % TAPS],
+        };
+        match self.interpolation {
+            Interpolation::Linear => {
+                let last = tap(TAPS - 2);
+                for (i, sample) in output.iter_mut().enumerate() {
+                    *sample = lerp(last[i], next[i], self.phase, self.to);
+                }
+            },
+            Interpolation::Sinc => {
+                // between two tabulated phases, and how far from the first, in 1 / 2^15ths
+                let position = u64::from(self.phase) * PHASES as u64;
+                let phase = (position / u64::from(self.to)) as usize;
+                let weight = (((position % u64::from(self.to)) << 15) / u64::from(self.to)) as i32;
+                let mut coefficients = [0; TAPS];
+                for (i, x) in coefficients.iter_mut().enumerate() {
+                    let (a, b) = (i32::from(SINC[phase][i]), i32::from(SINC[phase + 1][i]));
+                    *x = a + (((b - a) * weight) >> 15);
+                }
+                for (i, sample) in output.iter_mut().enumerate() {
+                    *sample = convolve((0..TAPS).map(|j| tap(j)[i]), &coefficients);
+                }
+            },
+        }
+    }
+}
+
+/// Converts a stream to one fixed sample rate, such as for a DAC which only runs at 48 kHz,
+/// following the stream through sample rate and channel count changes.
+///
+/// Audio which is already at the output rate is copied as-is, and anything else goes through
+/// a [`Resampler`], which is set up again whenever the format changes, as happens in some
+/// internet radio streams. What it held back of the previous format is written out first,
+/// by a call which doesn't consume anything. The channel count isn't converted, so for a fixed one too,
+/// see [`DecoderOptions::output_layout`](crate::DecoderOptions::output_layout).
+/// For iterating over a decoder's samples at one rate, there's [`Samples::output_rate`](crate::Samples::output_rate).
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{dsp::RateConverter, Decoder, Frame, Sample};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut converter = RateConverter::new(48000).unwrap();
+/// let mut output = [Sample::default(); 1024];
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         let mut samples = audio.samples();
+///         while !samples.is_empty() {
+///             let (consumed, written) =
+///                 converter.process(samples, audio.sample_rate(), audio.channels(), &mut output);
+///             samples = &samples[consumed..];
+///             // play `output[..written]` at 48 kHz here!
+///         }
+///     }
+/// }
+/// let written = converter.flush(&mut output);
+/// // and play `output[..written]` last!
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateConverter {
+    rate: u32,
+    interpolation: Interpolation,
+
+    // the format it was set up for last, and the resampler if it's not at the output rate
+    format: Option<(u32, u16)>,
+    resampler: Option<Resampler>,
+}
+
+impl RateConverter {
+    /// Constructs a new `RateConverter` to the given sample rate.
+    ///
+    /// Returns `None` if the rate is zero.
+    pub fn new(rate: u32) -> Option<Self> {
+        if rate == 0 {
+            return None;
+        }
+        Some(Self { rate, interpolation: Interpolation::Linear, format: None, resampler: None })
+    }
+
+    /// Sets how the resampler works out the output samples, which also forgets the resampler state.
+    #[inline]
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self.resampler = self.resampler.map(|x| x.interpolation(interpolation));
+        self
+    }
+
+    /// Gets the sample rate everything is converted to.
+    #[inline]
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Converts interleaved samples from `input`, which are at `sample_rate` with `channels` channels,
+    /// into `output`, returning how many samples were consumed and written.
+    ///
+    /// Like [`Resampler::process`], this stops when either all of `input` was consumed
+    /// or `output` is full. Nothing is consumed if the format is invalid,
+    /// as in, there's no channels or more than two, or the sample rate is zero.
+    pub fn process(
+        &mut self,
+        input: &[Sample],
+        sample_rate: u32,
+        channels: u16,
+        output: &mut [Sample],
+    ) -> (usize, usize) {
+        if self.format != Some((sample_rate, channels)) {
+            // the rest of the previous format goes first
+            let written = self.flush(output);
+            if written != 0 || matches!(self.resampler, Some(x) if x.history.is_some()) {
+                return (0, written);
+            }
+            self.format = Some((sample_rate, channels));
+            self.resampler = match Resampler::new(sample_rate, self.rate, channels) {
+                Some(_) if sample_rate == self.rate => None,
+                Some(resampler) => Some(resampler.interpolation(self.interpolation)),
+                None => {
+                    self.format = None;
+                    return (0, 0);
+                },
+            };
+        }
+        match &mut self.resampler {
+            Some(resampler) => resampler.process(input, output),
+            None => {
+                let channels = usize::from(channels);
+                let len = input.len().min(output.len()) / channels * channels;
+                output[..len].copy_from_slice(&input[..len]);
+                (len, len)
+            },
+        }
+    }
+
+    /// Writes out what the resampler held back, for the end of the stream,
+    /// returning how many samples were written. See [`Resampler::flush`] for more info.
+    pub fn flush(&mut self, output: &mut [Sample]) -> usize {
+        match &mut self.resampler {
+            Some(resampler) => resampler.flush(output),
+            None => 0,
+        }
+    }
+
+    /// Forgets the resampler state, such as after seeking.
+    #[inline]
+    pub fn reset(&mut self) {
+        if let Some(resampler) = &mut self.resampler {
+            resampler.reset();
+        }
+    }
+}
+
+/// Copies an input sample frame of one or two channels.
+#[inline(always)]
+fn frame(samples: &[Sample]) -> [Sample; 2] {
+    let mut frame = [Sample::default(); 2];
+    frame[..samples.len()].copy_from_slice(samples);
+    frame
+}
+
+/// Sums up `samples` weighted by `coefficients`, which are in 1 / 2^15ths.
+#[inline(always)]
+fn convolve(samples: impl Iterator<Item = Sample>, coefficients: &[i32; TAPS]) -> Sample {
+    #[cfg(not(feature = "float"))]
+    {
+        let sum: i64 = samples.zip(coefficients).map(|(x, &c)| i64::from(x) * i64::from(c)).sum();
+        ((sum + (1 << 14)) >> 15).clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as Sample
+    }
+    #[cfg(feature = "float")]
+    {
+        let sum: f32 = samples.zip(coefficients).map(|(x, &c)| x * c as f32).sum();
+        sum / 32768.0
+    }
+}
+
+/// Interpolates `phase / scale` of the way from `a` to `b`.
+#[inline(always)]
+fn lerp(a: Sample, b: Sample, phase: u32, scale: u32) -> Sample {
+    #[cfg(not(feature = "float"))]
+    {
+        let delta = i64::from(b) - i64::from(a);
+        (i64::from(a) + delta * i64::from(phase) / i64::from(scale)) as Sample
+    }
+    #[cfg(feature = "float")]
+    {
+        a + (b - a) * (phase as f32 / scale as f32)
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+
+/// The sinc filter at each phase, in 1 / 2^15ths, with one more phase after the last
+/// to interpolate towards. It's a Kaiser-windowed (β = 6) sinc cutting off at 90% of the
+/// Nyquist frequency, with each phase adding up to exactly one so it keeps the level as-is.
+#[rustfmt::skip]
+static SINC: [[i16; TAPS]; PHASES + 1] = [
+    [
+        -25, 48, -71, 84, -67, 0, 140, -370, 697, -1111, 1588, -2086, 2554, -2938, 3190, 29502,
+        3190, -2938, 2554, -2086, 1588, -1111, 697, -370, 140, 0, -67, 84, -71, 48, -25, 0,
+    ],
+    [
+        -25, 46, -68, 78, -58, -14, 158, -390, 715, -1120, 1578, -2042, 2454, -2733, 2710, 29484,
+        3679, -3139, 2649, -2124, 1594, -1099, 677, -349, 122, 14, -77, 89, -74, 49, -25, 9,
+    ],
+    [
+        -24, 45, -65, 72, -48, -28, 176, -410, 731, -1128, 1566, -1996, 2350, -2526, 2239, 29460,
+        4176, -3339, 2741, -2160, 1597, -1085, 655, -327, 103, 28, -86, 95, -77, 50, -26, 9,
+    ],
+    [
+        -24, 44, -62, 66, -39, -41, 193, -428, 747, -1132, 1551, -1946, 2244, -2318, 1779, 29406,
+        4682, -3535, 2829, -2191, 1597, -1068, 633, -304, 83, 43, -96, 100, -79, 51, -26, 9,
+    ],
+    [
+        -24, 43, -59, 60, -29, -55, 209, -445, 760, -1135, 1532, -1893, 2134, -2109, 1329, 29344,
+        5196, -3728, 2912, -2218, 1593, -1050, 608, -280, 63, 57, -105, 105, -82, 52, -26, 9,
+    ],
+    [
+        -23, 41, -56, 54, -20, -68, 225, -461, 772, -1136, 1511, -1837, 2021, -1899, 890, 29257,
+        5718, -3917, 2990, -2242, 1587, -1029, 583, -256, 43, 72, -114, 111, -84, 52, -26, 9,
+    ],
+    [
+        -22, 40, -52, 48, -10, -81, 240, -476, 782, -1134, 1488, -1778, 1906, -1689, 462, 29150,
+        6246, -4101, 3064, -2261, 1577, -1006, 555, -231, 23, 86, -124, 116, -86, 53, -26, 9,
+    ],
+    [
+        -22, 38, -49, 42, -1, -93, 255, -490, 790, -1130, 1461, -1716, 1789, -1479, 46, 29026,
+        6780, -4281, 3132, -2276, 1565, -980, 527, -205, 2, 101, -133, 120, -88, 54, -26, 9,
+    ],
+    [
+        -21, 36, -46, 36, 8, -105, 269, -503, 797, -1124, 1432, -1651, 1669, -1270, -359, 28883,
+        7321, -4455, 3195, -2286, 1549, -953, 497, -178, -19, 116, -142, 125, -90, 54, -26, 9,
+    ],
+    [
+        -21, 35, -42, 31, 17, -117, 282, -515, 803, -1115, 1401, -1584, 1548, -1061, -752, 28718,
+        7867, -4623, 3252, -2292, 1529, -923, 466, -151, -40, 130, -150, 130, -92, 54, -26, 9,
+    ],
+    [
+        -20, 33, -39, 25, 26, -129, 295, -525, 806, -1105, 1367, -1514, 1425, -854, -1132, 28540,
+        8417, -4786, 3304, -2294, 1507, -891, 434, -123, -61, 144, -159, 134, -94, 55, -26, 8,
+    ],
+    [
+        -19, 31, -35, 19, 35, -140, 307, -535, 808, -1092, 1331, -1442, 1301, -648, -1500, 28337,
+        8972, -4942, 3350, -2291, 1481, -857, 400, -94, -82, 159, -167, 138, -95, 55, -25, 8,
+    ],
+    [
+        -19, 30, -32, 13, 43, -151, 318, -543, 809, -1078, 1292, -1368, 1175, -444, -1854, 28123,
+        9530, -5091, 3390, -2283, 1452, -821, 365, -66, -104, 173, -175, 142, -96, 55, -25, 8,
+    ],
+    [
+        -18, 28, -28, 7, 52, -161, 328, -550, 808, -1061, 1251, -1292, 1049, -243, -2196, 27886,
+        10091, -5232, 3424, -2271, 1420, -783, 330, -36, -125, 187, -183, 145, -97, 55, -25, 8,
+    ],
+    [
+        -17, 26, -25, 1, 60, -171, 338, -557, 805, -1043, 1208, -1214, 923, -44, -2525, 27639,
+        10655, -5366, 3451, -2255, 1385, -744, 293, -7, -146, 200, -191, 149, -98, 55, -24, 7,
+    ],
+    [
+        -16, 24, -21, -4, 68, -180, 347, -561, 800, -1022, 1164, -1135, 796, 152, -2839, 27366,
+        11221, -5492, 3472, -2233, 1347, -702, 255, 23, -168, 214, -198, 152, -99, 54, -24, 7,
+    ],
+    [
+        -16, 22, -18, -10, 75, -189, 355, -565, 795, -1000, 1117, -1054, 669, 345, -3141, 27080,
+        11788, -5609, 3486, -2207, 1306, -658, 217, 53, -189, 227, -205, 155, -99, 54, -23, 7,
+    ],
+    [
+        -15, 21, -15, -15, 83, -197, 362, -568, 787, -976, 1068, -972, 542, 533, -3428, 26778,
+        12356, -5717, 3494, -2176, 1261, -613, 178, 84, -210, 240, -211, 157, -100, 53, -22, 6,
+    ],
+    [
+        -14, 19, -11, -21, 90, -205, 369, -569, 778, -951, 1018, -889, 415, 718, -3702, 26464,
+        12924, -5817, 3494, -2141, 1214, -566, 138, 114, -231, 252, -218, 159, -100, 53, -22, 6,
+    ],
+    [
+        -13, 17, -8, -26, 97, -213, 374, -569, 768, -923, 967, -805, 290, 899, -3961, 26127,
+        13492, -5906, 3488, -2101, 1164, -518, 97, 145, -251, 264, -224, 161, -100, 52, -21, 5,
+    ],
+    [
+        -12, 15, -4, -31, 103, -220, 379, -568, 756, -894, 914, -720, 165, 1075, -4207, 25777,
+        14058, -5986, 3475, -2057, 1111, -468, 56, 176, -271, 276, -229, 163, -100, 51, -20, 5,
+    ],
+    [
+        -11, 13, -1, -36, 110, -226, 383, -567, 743, -864, 860, -635, 41, 1247, -4438, 25413,
+        14623, -6055, 3455, -2007, 1055, -417, 14, 206, -291, 287, -234, 164, -99, 50, -19, 4,
+    ],
+    [
+        -11, 11, 2, -41, 116, -232, 387, -563, 729, -833, 804, -549, -81, 1414, -4655, 25032,
+        15186, -6113, 3427, -1954, 997, -364, -28, 237, -311, 298, -239, 165, -98, 49, -18, 4,
+    ],
+    [
+        -10, 9, 5, -46, 121, -237, 389, -559, 713, -800, 748, -464, -202, 1575, -4858, 24643,
+        15745, -6161, 3393, -1896, 936, -310, -71, 267, -330, 309, -243, 166, -97, 47, -17, 3,
+    ],
+    [
+        -9, 8, 8, -50, 127, -242, 391, -554, 696, -765, 691, -378, -321, 1731, -5047, 24231,
+        16302, -6197, 3351, -1833, 873, -255, -114, 297, -349, 319, -247, 167, -96, 46, -16, 3,
+    ],
+    [
+        -8, 6, 12, -54, 132, -246, 392, -548, 678, -730, 632, -292, -438, 1881, -5221, 23812,
+        16854, -6222, 3302, -1766, 807, -199, -156, 327, -367, 328, -251, 167, -95, 44, -15, 2,
+    ],
+    [
+        -7, 4, 14, -59, 136, -250, 392, -541, 659, -693, 574, -207, -553, 2025, -5381, 23383,
+        17401, -6235, 3246, -1695, 739, -142, -200, 356, -385, 337, -254, 166, -93, 43, -14, 2,
+    ],
+    [
+        -6, 2, 17, -63, 141, -253, 391, -532, 638, -656, 514, -122, -665, 2164, -5527, 22938,
+        17943, -6235, 3183, -1620, 669, -85, -243, 385, -402, 345, -256, 166, -92, 41, -13, 1,
+    ],
+    [
+        -6, 1, 20, -66, 145, -256, 390, -523, 617, -617, 454, -37, -775, 2296, -5659, 22480,
+        18479, -6224, 3112, -1541, 597, -26, -286, 413, -418, 353, -258, 165, -90, 39, -11, 0,
+    ],
+    [
+        -5, -1, 23, -70, 149, -258, 388, -513, 594, -578, 394, 46, -882, 2421, -5777, 22014,
+        19008, -6199, 3034, -1457, 523, 33, -329, 441, -434, 360, -260, 164, -88, 37, -10, 0,
+    ],
+    [
+        -4, -3, 26, -73, 152, -260, 385, -502, 571, -538, 334, 129, -987, 2541, -5882, 21536,
+        19530, -6162, 2949, -1370, 447, 93, -371, 469, -449, 366, -261, 162, -85, 35, -9, -1,
+    ],
+    [
+        -3, -4, 28, -77, 155, -261, 381, -490, 547, -497, 273, 211, -1088, 2653, -5972, 21049,
+        20045, -6112, 2858, -1280, 370, 153, -414, 495, -464, 372, -261, 160, -83, 33, -7, -2,
+    ],
+    [
+        -3, -6, 30, -80, 158, -261, 377, -477, 521, -455, 213, 291, -1185, 2759, -6049, 20551,
+        20551, -6049, 2759, -1185, 291, 213, -455, 521, -477, 377, -261, 158, -80, 30, -6, -3,
+    ],
+    [
+        -2, -7, 33, -83, 160, -261, 372, -464, 495, -414, 153, 370, -1280, 2858, -6112, 20045,
+        21049, -5972, 2653, -1088, 211, 273, -497, 547, -490, 381, -261, 155, -77, 28, -4, -3,
+    ],
+    [
+        -1, -9, 35, -85, 162, -261, 366, -449, 469, -371, 93, 447, -1370, 2949, -6162, 19530,
+        21536, -5882, 2541, -987, 129, 334, -538, 571, -502, 385, -260, 152, -73, 26, -3, -4,
+    ],
+    [
+        0, -10, 37, -88, 164, -260, 360, -434, 441, -329, 33, 523, -1457, 3034, -6199, 19008,
+        22014, -5777, 2421, -882, 46, 394, -578, 594, -513, 388, -258, 149, -70, 23, -1, -5,
+    ],
+    [
+        0, -11, 39, -90, 165, -258, 353, -418, 413, -286, -26, 597, -1541, 3112, -6224, 18479,
+        22480, -5659, 2296, -775, -37, 454, -617, 617, -523, 390, -256, 145, -66, 20, 1, -6,
+    ],
+    [
+        1, -13, 41, -92, 166, -256, 345, -402, 385, -243, -85, 669, -1620, 3183, -6235, 17943,
+        22938, -5527, 2164, -665, -122, 514, -656, 638, -532, 391, -253, 141, -63, 17, 2, -6,
+    ],
+    [
+        2, -14, 43, -93, 166, -254, 337, -385, 356, -200, -142, 739, -1695, 3246, -6235, 17401,
+        23383, -5381, 2025, -553, -207, 574, -693, 659, -541, 392, -250, 136, -59, 14, 4, -7,
+    ],
+    [
+        2, -15, 44, -95, 167, -251, 328, -367, 327, -156, -199, 807, -1766, 3302, -6222, 16854,
+        23812, -5221, 1881, -438, -292, 632, -730, 678, -548, 392, -246, 132, -54, 12, 6, -8,
+    ],
+    [
+        3, -16, 46, -96, 167, -247, 319, -349, 297, -114, -255, 873, -1833, 3351, -6197, 16302,
+        24231, -5047, 1731, -321, -378, 691, -765, 696, -554, 391, -242, 127, -50, 8, 8, -9,
+    ],
+    [
+        3, -17, 47, -97, 166, -243, 309, -330, 267, -71, -310, 936, -1896, 3393, -6161, 15745,
+        24643, -4858, 1575, -202, -464, 748, -800, 713, -559, 389, -237, 121, -46, 5, 9, -10,
+    ],
+    [
+        4, -18, 49, -98, 165, -239, 298, -311, 237, -28, -364, 997, -1954, 3427, -6113, 15186,
+        25032, -4655, 1414, -81, -549, 804, -833, 729, -563, 387, -232, 116, -41, 2, 11, -11,
+    ],
+    [
+        4, -19, 50, -99, 164, -234, 287, -291, 206, 14, -417, 1055, -2007, 3455, -6055, 14623,
+        25413, -4438, 1247, 41, -635, 860, -864, 743, -567, 383, -226, 110, -36, -1, 13, -11,
+    ],
+    [
+        5, -20, 51, -100, 163, -229, 276, -271, 176, 56, -468, 1111, -2057, 3475, -5986, 14058,
+        25777, -4207, 1075, 165, -720, 914, -894, 756, -568, 379, -220, 103, -31, -4, 15, -12,
+    ],
+    [
+        5, -21, 52, -100, 161, -224, 264, -251, 145, 97, -518, 1164, -2101, 3488, -5906, 13492,
+        26127, -3961, 899, 290, -805, 967, -923, 768, -569, 374, -213, 97, -26, -8, 17, -13,
+    ],
+    [
+        6, -22, 53, -100, 159, -218, 252, -231, 114, 138, -566, 1214, -2141, 3494, -5817, 12924,
+        26464, -3702, 718, 415, -889, 1018, -951, 778, -569, 369, -205, 90, -21, -11, 19, -14,
+    ],
+    [
+        6, -22, 53, -100, 157, -211, 240, -210, 84, 178, -613, 1261, -2176, 3494, -5717, 12356,
+        26778, -3428, 533, 542, -972, 1068, -976, 787, -568, 362, -197, 83, -15, -15, 21, -15,
+    ],
+    [
+        7, -23, 54, -99, 155, -205, 227, -189, 53, 217, -658, 1306, -2207, 3486, -5609, 11788,
+        27080, -3141, 345, 669, -1054, 1117, -1000, 795, -565, 355, -189, 75, -10, -18, 22, -16,
+    ],
+    [
+        7, -24, 54, -99, 152, -198, 214, -168, 23, 255, -702, 1347, -2233, 3472, -5492, 11221,
+        27366, -2839, 152, 796, -1135, 1164, -1022, 800, -561, 347, -180, 68, -4, -21, 24, -16,
+    ],
+    [
+        7, -24, 55, -98, 149, -191, 200, -146, -7, 293, -744, 1385, -2255, 3451, -5366, 10655,
+        27639, -2525, -44, 923, -1214, 1208, -1043, 805, -557, 338, -171, 60, 1, -25, 26, -17,
+    ],
+    [
+        8, -25, 55, -97, 145, -183, 187, -125, -36, 330, -783, 1420, -2271, 3424, -5232, 10091,
+        27886, -2196, -243, 1049, -1292, 1251, -1061, 808, -550, 328, -161, 52, 7, -28, 28, -18,
+    ],
+    [
+        8, -25, 55, -96, 142, -175, 173, -104, -66, 365, -821, 1452, -2283, 3390, -5091, 9530,
+        28123, -1854, -444, 1175, -1368, 1292, -1078, 809, -543, 318, -151, 43, 13, -32, 30, -19,
+    ],
+    [
+        8, -25, 55, -95, 138, -167, 159, -82, -94, 400, -857, 1481, -2291, 3350, -4942, 8972,
+        28337, -1500, -648, 1301, -1442, 1331, -1092, 808, -535, 307, -140, 35, 19, -35, 31, -19,
+    ],
+    [
+        8, -26, 55, -94, 134, -159, 144, -61, -123, 434, -891, 1507, -2294, 3304, -4786, 8417,
+        28540, -1132, -854, 1425, -1514, 1367, -1105, 806, -525, 295, -129, 26, 25, -39, 33, -20,
+    ],
+    [
+        9, -26, 54, -92, 130, -150, 130, -40, -151, 466, -923, 1529, -2292, 3252, -4623, 7867,
+        28718, -752, -1061, 1548, -1584, 1401, -1115, 803, -515, 282, -117, 17, 31, -42, 35, -21,
+    ],
+    [
+        9, -26, 54, -90, 125, -142, 116, -19, -178, 497, -953, 1549, -2286, 3195, -4455, 7321,
+        28883, -359, -1270, 1669, -1651, 1432, -1124, 797, -503, 269, -105, 8, 36, -46, 36, -21,
+    ],
+    [
+        9, -26, 54, -88, 120, -133, 101, 2, -205, 527, -980, 1565, -2276, 3132, -4281, 6780,
+        29026, 46, -1479, 1789, -1716, 1461, -1130, 790, -490, 255, -93, -1, 42, -49, 38, -22,
+    ],
+    [
+        9, -26, 53, -86, 116, -124, 86, 23, -231, 555, -1006, 1577, -2261, 3064, -4101, 6246,
+        29150, 462, -1689, 1906, -1778, 1488, -1134, 782, -476, 240, -81, -10, 48, -52, 40, -22,
+    ],
+    [
+        9, -26, 52, -84, 111, -114, 72, 43, -256, 583, -1029, 1587, -2242, 2990, -3917, 5718,
+        29257, 890, -1899, 2021, -1837, 1511, -1136, 772, -461, 225, -68, -20, 54, -56, 41, -23,
+    ],
+    [
+        9, -26, 52, -82, 105, -105, 57, 63, -280, 608, -1050, 1593, -2218, 2912, -3728, 5196,
+        29344, 1329, -2109, 2134, -1893, 1532, -1135, 760, -445, 209, -55, -29, 60, -59, 43, -24,
+    ],
+    [
+        9, -26, 51, -79, 100, -96, 43, 83, -304, 633, -1068, 1597, -2191, 2829, -3535, 4682,
+        29406, 1779, -2318, 2244, -1946, 1551, -1132, 747, -428, 193, -41, -39, 66, -62, 44, -24,
+    ],
+    [
+        9, -26, 50, -77, 95, -86, 28, 103, -327, 655, -1085, 1597, -2160, 2741, -3339, 4176,
+        29460, 2239, -2526, 2350, -1996, 1566, -1128, 731, -410, 176, -28, -48, 72, -65, 45, -24,
+    ],
+    [
+        9, -25, 49, -74, 89, -77, 14, 122, -349, 677, -1099, 1594, -2124, 2649, -3139, 3679,
+        29484, 2710, -2733, 2454, -2042, 1578, -1120, 715, -390, 158, -14, -58, 78, -68, 46, -25,
+    ],
+    [
+        0, -25, 48, -71, 84, -67, 0, 140, -370, 697, -1111, 1588, -2086, 2554, -2938, 3190,
+        29502, 3190, -2938, 2554, -2086, 1588, -1111, 697, -370, 140, 0, -67, 84, -71, 48, -25,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{RateConverter, Resampler};
+    use crate::Sample;
+
+    #[test]
+    fn resample() {
+        assert!(Resampler::new(0, 48000, 2).is_none());
+        assert!(Resampler::new(44100, 48000, 3).is_none());
+
+        // doubling the rate puts a sample halfway between every pair
+        let mut resampler = Resampler::new(22050, 44100, 1).unwrap();
+        let input = [0i16, 100, 200, 300].map(Sample::from);
+        let mut output = [Sample::default(); 16];
+        assert_eq!(resampler.process(&input, &mut output), (4, 6));
+        assert_eq!(output[..6], [0i16, 50, 100, 150, 200, 250].map(Sample::from));
+        assert!(resampler.max_output_len(input.len()) >= 6);
+
+        // which continues seamlessly into the next call, even if the output fills up
+        let input = [400i16, 500].map(Sample::from);
+        assert_eq!(resampler.process(&input, &mut output[..3]), (1, 3));
+        assert_eq!(output[..3], [300i16, 350, 400].map(Sample::from));
+        assert_eq!(resampler.process(&input[1..], &mut output), (1, 1));
+        assert_eq!(output[0], Sample::from(450i16));
+
+        // and the position doesn't drift over many calls
+        let mut resampler = Resampler::new(44100, 48000, 2).unwrap();
+        let input = [Sample::default(); 882];
+        let mut written = 0;
+        for _ in 0..100 {
+            written += resampler.process(&input, &mut [Sample::default(); 1024]).1;
+        }
+        // the last output is held back until the input after it arrives, or it's flushed
+        assert_eq!(written, 47999 * 2);
+        assert_eq!(resampler.flush(&mut [Sample::default(); 1024]), 2);
+        assert_eq!(resampler.flush(&mut [Sample::default(); 1024]), 0);
+    }
+
+    #[test]
+    fn sinc() {
+        use super::Interpolation;
+
+        // the same amount of output as linear interpolation, once flushed, and level with DC
+        let dc = Sample::from(8000i16);
+        let mut resampler = Resampler::new(44100, 48000, 2).unwrap().interpolation(Interpolation::Sinc);
+        let mut output = [Sample::default(); 1024];
+        let mut written = 0;
+        for _ in 0..100 {
+            let len = resampler.process(&[dc; 882], &mut output).1;
+            assert!(output[..len].iter().all(|&x| x >= dc - Sample::from(8i16) && x <= dc + Sample::from(8i16)));
+            written += len;
+        }
+        assert!(written < 47999 * 2);
+        // a small buffer fills up, and the rest comes with the next call
+        written += resampler.flush(&mut output[..10]);
+        written += resampler.flush(&mut output);
+        assert_eq!(written, 48000 * 2);
+
+        // and closer to the real thing between the samples of a sine wave
+        let sine = |x: f64| (x * 2.0 * core::f64::consts::PI * 1000.0 / 44100.0).sin() * 16000.0;
+        let input: [Sample; 2205] = core::array::from_fn(|i| Sample::from(sine(i as f64 * 2.0) as i16));
+        let error = |interpolation| {
+            let mut resampler = Resampler::new(22050, 44100, 1).unwrap().interpolation(interpolation);
+            let mut output = [Sample::default(); 8192];
+            let (_, mut len) = resampler.process(&input, &mut output);
+            len += resampler.flush(&mut output[len..]);
+            assert_eq!(len, 4410);
+            // away from the ends, where the wave is cut off
+            (100..4300).map(|i| (f64::from(output[i]) - sine(i as f64)).abs()).fold(0.0, f64::max)
+        };
+        let (linear, sinc) = (error(Interpolation::Linear), error(Interpolation::Sinc));
+        assert!(sinc < 20.0 && linear > 100.0, "{} {}", sinc, linear);
+    }
+
+    #[test]
+    fn rate_converter() {
+        assert!(RateConverter::new(0).is_none());
+        let mut converter = RateConverter::new(44100).unwrap();
+        let input = [0i16, 100, 200, 300, 400].map(Sample::from);
+        let mut output = [Sample::default(); 16];
+
+        // audio at the output rate is copied, in whole sample frames
+        assert_eq!(converter.process(&input, 44100, 2, &mut output), (4, 4));
+        assert_eq!(output[..4], input[..4]);
+        assert_eq!(converter.process(&input, 44100, 3, &mut output), (0, 0));
+
+        // and when the rate changes, it's resampled
+        assert_eq!(converter.process(&input[..4], 22050, 1, &mut output), (4, 6));
+        assert_eq!(output[..6], [0i16, 50, 100, 150, 200, 250].map(Sample::from));
+
+        // with what was held back of it coming out before the next format
+        assert_eq!(converter.process(&input, 44100, 2, &mut output), (0, 2));
+        assert_eq!(output[..2], [300i16, 300].map(Sample::from));
+        assert_eq!(converter.process(&input, 44100, 2, &mut output), (4, 4));
+    }
+}
@@ -0,0 +1,88 @@
+//! Describing the total length of a stream.
+
+use core::time::Duration;
+
+/// Describes how the duration of a stream was found.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum DurationMethod {
+    /// The frame count stored in the [`XingHeader`](crate::XingHeader) was used,
+    /// which is instant, and accurate unless the file was cut without updating it.
+    Xing,
+
+    /// Every frame was stepped through and counted, which is always accurate.
+    Scan,
+}
+
+/// The total length of a stream, as found by [`Decoder::duration`](crate::Decoder::duration).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StreamDuration {
+    pub(crate) method: DurationMethod,
+    pub(crate) sample_count: u64,
+    pub(crate) sample_rate: u32,
+}
+
+impl StreamDuration {
+    /// Gets how the duration was found.
+    #[inline]
+    pub fn method(&self) -> DurationMethod {
+        self.method
+    }
+
+    /// Gets the total sample count per channel.
+    #[inline]
+    pub fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Gets the sample rate of the first frame in Hz, which the duration in seconds is based on.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Gets the duration in seconds.
+    ///
+    /// If the sample rate changes mid-stream, this is only an approximation.
+    #[inline]
+    pub fn seconds(&self) -> f64 {
+        self.sample_count as f64 / f64::from(self.sample_rate)
+    }
+
+    /// Gets the duration as a [`Duration`], rounded down to the nanosecond,
+    /// and computed without floating point.
+    ///
+    /// If the sample rate changes mid-stream, this is only an approximation.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        to_duration(self.sample_count, self.sample_rate)
+    }
+}
+
+/// Converts a sample count per channel to how long it plays for, rounded down to the nanosecond.
+pub(crate) fn to_duration(sample_count: u64, sample_rate: u32) -> Duration {
+    if sample_rate == 0 {
+        return Duration::ZERO;
+    }
+    let rate = u64::from(sample_rate);
+    // CAST: The remainder is less than the rate, so this is less than a billion.
+    let nanos = (sample_count % rate * 1_000_000_000 / rate) as u32;
+    Duration::new(sample_count / rate, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_duration;
+    use core::time::Duration;
+
+    #[test]
+    fn duration() {
+        assert_eq!(to_duration(1152, 44100), Duration::from_nanos(26_122_448));
+        assert_eq!(to_duration(44100 * 3600 + 22050, 44100), Duration::from_millis(3_600_500));
+        assert_eq!(to_duration(u64::MAX, 8000).as_secs(), u64::MAX / 8000);
+        assert_eq!(to_duration(1152, 0), Duration::ZERO);
+    }
+}
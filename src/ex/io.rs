@@ -0,0 +1,357 @@
+use crate::{ffi, Sample};
+use core::{fmt, marker::PhantomData, mem::MaybeUninit, slice};
+use libc::{c_int, c_void, size_t};
+
+/// A seekable byte source for an [`IoDecoder`], such as a file.
+///
+/// Opening a decoder always seeks to the start first, so sources which can't seek,
+/// such as sockets, aren't supported. With the `std` feature, this is implemented for everything that's `Read + Seek`.
+pub trait Source {
+    /// Reads some bytes into `buf`, returning how many were read,
+    /// or `None` if an error occurred. Zero means the end of the data was reached.
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize>;
+
+    /// Seeks to `position` bytes from the start of the data, returning whether it succeeded.
+    fn seek(&mut self, position: u64) -> bool;
+}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Read + std::io::Seek> Source for T {
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        loop {
+            match std::io::Read::read(self, buf) {
+                Ok(count) => break Some(count),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => break None,
+            }
+        }
+    }
+
+    fn seek(&mut self, position: u64) -> bool {
+        std::io::Seek::seek(self, std::io::SeekFrom::Start(position)).is_ok()
+    }
+}
+
+/// Describes what the position given to [`IoDecoder::seek`] is measured in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SeekMode {
+    /// Positions are byte offsets into the source,
+    /// which doesn't require scanning the stream, so only its first frame is read when it's opened.
+    Byte,
+
+    /// Positions are interleaved sample offsets, which are exact,
+    /// but the stream is scanned through to index it when it's opened, unless it has a Xing header.
+    Sample,
+}
+
+/// Errors which minimp3's extended API can return.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The [`Source`] failed to read or seek.
+    Io,
+
+    /// An allocation failed.
+    Memory,
+
+    /// The data couldn't be decoded, or contained no frames at all.
+    Decode,
+}
+
+impl Error {
+    fn from_code(code: c_int) -> Result<(), Self> {
+        match code {
+            0 => Ok(()),
+            ffi::MP3D_E_IOERROR => Err(Self::Io),
+            ffi::MP3D_E_MEMORY => Err(Self::Memory),
+            _ => Err(Self::Decode),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io => f.write_str("source failed to read or seek"),
+            Self::Memory => f.write_str("allocation failed"),
+            Self::Decode => f.write_str("data couldn't be decoded"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Storage for a [`Source`], which an [`IoDecoder`] borrows for as long as it's open,
+/// as minimp3 keeps pointers to it.
+pub struct Io<S> {
+    io: ffi::mp3dec_io_t,
+    source: S,
+    #[cfg(feature = "std")]
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+impl<S: Source> Io<S> {
+    /// Wraps a source.
+    pub fn new(source: S) -> Self {
+        Self {
+            io: ffi::mp3dec_io_t {
+                read: Some(read::<S>),
+                read_data: core::ptr::null_mut(),
+                seek: Some(seek::<S>),
+                seek_data: core::ptr::null_mut(),
+            },
+            source,
+            #[cfg(feature = "std")]
+            panic: None,
+        }
+    }
+
+    /// Gets the wrapped source back.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Calls into the source, stashing a panic for [`resume`](Self::resume) to continue
+    /// once out of minimp3, as unwinding through C isn't allowed.
+    #[inline]
+    fn guard<T>(&mut self, f: impl FnOnce(&mut S) -> Option<T>) -> Option<T> {
+        #[cfg(feature = "std")]
+        {
+            if self.panic.is_some() {
+                return None;
+            }
+            let source = &mut self.source;
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(source))) {
+                Ok(result) => result,
+                Err(panic) => {
+                    self.panic = Some(panic);
+                    None
+                },
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        f(&mut self.source)
+    }
+
+    #[inline]
+    fn resume(&mut self) {
+        #[cfg(feature = "std")]
+        if let Some(panic) = self.panic.take() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+unsafe extern "C" fn read<S: Source>(buf: *mut c_void, size: size_t, user_data: *mut c_void) -> size_t {
+    let io = &mut *(user_data as *mut Io<S>);
+    let buf = slice::from_raw_parts_mut(buf as *mut u8, size);
+    // minimp3 takes a short read as the end of the data, so this fills the buffer if it can
+    let mut filled = 0;
+    while filled < size {
+        match io.guard(|source| source.read(&mut buf[filled..])) {
+            Some(0) => break,
+            Some(count) => filled += count.min(size - filled),
+            None => return size_t::MAX,
+        }
+    }
+    filled
+}
+
+unsafe extern "C" fn seek<S: Source>(position: u64, user_data: *mut c_void) -> c_int {
+    let io = &mut *(user_data as *mut Io<S>);
+    match io.guard(|source| Some(source.seek(position))) {
+        Some(true) => 0,
+        _ => ffi::MP3D_E_IOERROR,
+    }
+}
+
+/// Decoder which reads from a [`Source`] with minimp3's extended API,
+/// so that neither the whole file nor its frame boundaries have to be known in advance.
+///
+/// Unlike the rest of the crate, this returns a continuous stream of interleaved samples,
+/// and skips the encoder delay and padding of LAME-tagged files.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{ex::{Io, IoDecoder, SeekMode}, Sample};
+///
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// # #[cfg(feature = "std")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut io = Io::new(std::fs::File::open("test.mp3")?);
+/// let mut decoder = IoDecoder::open(&mut io, SeekMode::Byte)?;
+/// let mut buf = [Sample::default(); 4096];
+/// loop {
+///     let count = decoder.read(&mut buf)?;
+///     if count == 0 {
+///         break;
+///     }
+///     // process &buf[..count] here!
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct IoDecoder<'io, S> {
+    dec: ffi::mp3dec_ex_t,
+    io: *mut Io<S>,
+    phantom: PhantomData<&'io mut Io<S>>,
+}
+
+impl<'io, S: Source> IoDecoder<'io, S> {
+    /// Opens a stream, reading its first frame to find its format.
+    pub fn open(io: &'io mut Io<S>, mode: SeekMode) -> Result<Self, Error> {
+        let io_ptr = io as *mut Io<S>;
+        io.io.read_data = io_ptr as *mut c_void;
+        io.io.seek_data = io_ptr as *mut c_void;
+        let flags = match mode {
+            // minimp3 would otherwise index the whole stream, which byte offsets don't need
+            SeekMode::Byte => ffi::MP3D_SEEK_TO_BYTE | ffi::MP3D_DO_NOT_SCAN,
+            SeekMode::Sample => ffi::MP3D_SEEK_TO_SAMPLE,
+        };
+        unsafe {
+            let mut dec = MaybeUninit::<ffi::mp3dec_ex_t>::zeroed();
+            let result = ffi::mp3dec_ex_open_cb(dec.as_mut_ptr(), &mut io.io, flags as c_int);
+            // closing only frees what's been allocated, so it's correct even on failure
+            let decoder = Self {
+                dec: dec.assume_init(),
+                io: io_ptr,
+                phantom: PhantomData,
+            };
+            (*decoder.io).resume();
+            Error::from_code(result)?;
+            // `open` succeeds on data without any frames, which is the same as being corrupt
+            if decoder.dec.info.channels == 0 {
+                return Err(Error::Decode);
+            }
+            Ok(decoder)
+        }
+    }
+
+    /// Gets the channel count of the stream.
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        self.dec.info.channels as u16
+    }
+
+    /// Gets the sample rate of the stream.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.dec.info.hz as u32
+    }
+
+    /// Gets the total interleaved sample count of the stream, as known so far.
+    ///
+    /// This is exact if the stream has a Xing header or was opened with [`SeekMode::Sample`],
+    /// and zero otherwise.
+    #[inline]
+    pub fn sample_count(&self) -> u64 {
+        self.dec.samples
+    }
+
+    /// Reads interleaved samples into `buf`, returning how many were read.
+    /// Zero means the end of the stream was reached.
+    pub fn read(&mut self, buf: &mut [Sample]) -> Result<usize, Error> {
+        let count = unsafe {
+            let count = ffi::mp3dec_ex_read(&mut self.dec, buf.as_mut_ptr(), buf.len());
+            (*self.io).resume();
+            count
+        };
+        if count < buf.len() {
+            Error::from_code(self.dec.last_error)?;
+        }
+        Ok(count)
+    }
+
+    /// Seeks to a position, measured as described by the [`SeekMode`] it was opened with.
+    pub fn seek(&mut self, position: u64) -> Result<(), Error> {
+        let result = unsafe {
+            let result = ffi::mp3dec_ex_seek(&mut self.dec, position);
+            (*self.io).resume();
+            result
+        };
+        Error::from_code(result)
+    }
+}
+
+impl<S> Drop for IoDecoder<'_, S> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::mp3dec_ex_close(&mut self.dec);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hands out a few bytes at a time, like a socket would
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Source for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+            let count = buf.len().min(self.0.len()).min(3);
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Some(count)
+        }
+
+        fn seek(&mut self, _position: u64) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn read_fills_buffer() {
+        let mut data = [0u8; 20];
+        data.iter_mut().enumerate().for_each(|(i, x)| *x = i as u8);
+        let mut io = Io::new(Trickle(&data));
+        let ptr = &mut io as *mut Io<Trickle> as *mut c_void;
+        let mut buf = [0u8; 16];
+        unsafe {
+            assert_eq!(read::<Trickle>(buf.as_mut_ptr() as *mut c_void, buf.len(), ptr), 16);
+            assert_eq!(buf[..], data[..16]);
+            assert_eq!(read::<Trickle>(buf.as_mut_ptr() as *mut c_void, buf.len(), ptr), 4);
+            assert_eq!(buf[..4], data[16..]);
+            assert_eq!(seek::<Trickle>(0, ptr), ffi::MP3D_E_IOERROR);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn open_reads_first_frame() {
+        struct Counting<'a> {
+            data: &'a [u8],
+            position: usize,
+            read: usize,
+        }
+
+        impl Source for Counting<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+                let count = buf.len().min(self.data.len() - self.position);
+                buf[..count].copy_from_slice(&self.data[self.position..self.position + count]);
+                self.position += count;
+                self.read += count;
+                Some(count)
+            }
+
+            fn seek(&mut self, position: u64) -> bool {
+                self.position = (position as usize).min(self.data.len());
+                true
+            }
+        }
+
+        // much more than minimp3 reads at once
+        let mp3 = crate::test_util::FrameBuilder::new().stream(2000).unwrap();
+        let mut io = Io::new(Counting { data: &mp3, position: 0, read: 0 });
+        let decoder = IoDecoder::open(&mut io, SeekMode::Byte).unwrap();
+        assert_eq!((decoder.channels(), decoder.sample_rate(), decoder.sample_count()), (2, 44100, 0));
+        drop(decoder);
+        assert!(io.into_inner().read < mp3.len());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{ffi, FrameHeader};
+use core::slice;
+use libc::{c_int, c_void};
+
+/// A frame visited by [`iterate`].
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInfo<'src> {
+    header: FrameHeader,
+    offset: usize,
+    source: &'src [u8],
+}
+
+impl<'src> FrameInfo<'src> {
+    /// Gets the header of this frame.
+    #[inline]
+    pub fn header(&self) -> FrameHeader {
+        self.header
+    }
+
+    /// Gets the byte offset of this frame from the start of the data.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Gets the source bytes of this frame, including the header.
+    ///
+    /// This is also how to get the size of free-format frames,
+    /// for which [`FrameHeader::frame_bytes`] doesn't know it.
+    #[inline]
+    pub fn source(&self) -> &'src [u8] {
+        self.source
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for FrameInfo<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "FrameInfo {{ header: {}, offset: {}, size: {} }}", self.header, self.offset, self.source.len())
+    }
+}
+
+struct State<'src, F> {
+    callback: F,
+    count: usize,
+    src: &'src [u8],
+    #[cfg(feature = "std")]
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+/// Visits every frame in `src` with `mp3dec_iterate_buf`, without decoding any of them,
+/// returning how many frames were visited.
+///
+/// This only has to find frames, so it's the cheapest way to index or validate a stream.
+/// A leading ID3v2 tag is skipped. The callback returns whether to keep going.
+///
+/// Panics in the callback are resumed after leaving minimp3 with the `std` feature,
+/// and abort the process without it.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut offsets = Vec::new();
+/// rmp3::ex::iterate(&mp3, |frame| {
+///     offsets.push(frame.offset());
+///     true
+/// });
+/// # Ok(())
+/// # }
+/// ```
+pub fn iterate<'src, F>(src: &'src [u8], callback: F) -> usize
+where
+    F: FnMut(FrameInfo<'src>) -> bool,
+{
+    let mut state = State {
+        callback,
+        count: 0,
+        src,
+        #[cfg(feature = "std")]
+        panic: None,
+    };
+    unsafe {
+        ffi::mp3dec_iterate_buf(
+            src.as_ptr(),
+            src.len(),
+            Some(visit::<F>),
+            &mut state as *mut State<F> as *mut c_void,
+        );
+    }
+    #[cfg(feature = "std")]
+    if let Some(panic) = state.panic {
+        std::panic::resume_unwind(panic);
+    }
+    state.count
+}
+
+unsafe extern "C" fn visit<'src, F>(
+    user_data: *mut c_void,
+    frame: *const u8,
+    frame_size: c_int,
+    _free_format_bytes: c_int,
+    _buf_size: libc::size_t,
+    offset: u64,
+    _info: *mut ffi::mp3dec_frame_info_t,
+) -> c_int
+where
+    F: FnMut(FrameInfo<'src>) -> bool,
+{
+    let state = &mut *(user_data as *mut State<'src, F>);
+    // `frame` points into `src`, so this and `offset` are always in bounds
+    let offset = offset as usize;
+    let source = slice::from_raw_parts(state.src.as_ptr().add(offset), frame_size as usize);
+    debug_assert_eq!(source.as_ptr(), frame);
+    let info = FrameInfo {
+        header: FrameHeader::from_frame_unchecked(source),
+        offset,
+        source,
+    };
+    state.count += 1;
+
+    #[cfg(feature = "std")]
+    let keep_going = {
+        let callback = &mut state.callback;
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(info))) {
+            Ok(keep_going) => keep_going,
+            Err(panic) => {
+                state.panic = Some(panic);
+                false
+            },
+        }
+    };
+    #[cfg(not(feature = "std"))]
+    let keep_going = (state.callback)(info);
+
+    // any non-zero value stops minimp3, and is returned from `mp3dec_iterate_buf`
+    (!keep_going) as c_int
+}
@@ -0,0 +1,10 @@
+//! Wrappers for minimp3's extended API (`minimp3_ex.h`).
+//!
+//! These are thin, and don't go through the decoders in the rest of the crate,
+//! so their results can differ slightly from them, such as around ID3 tags.
+
+mod io;
+mod iterate;
+
+pub use io::{Error, Io, IoDecoder, SeekMode, Source};
+pub use iterate::{iterate, FrameInfo};
@@ -191,3 +191,136 @@ extern "C" {
         info: *mut mp3dec_frame_info_t,
     ) -> libc::c_int;
 }
+#[cfg(feature = "minimp3-ex")]
+pub type MP3D_ITERATE_CB = ::core::option::Option<
+    unsafe extern "C" fn(
+        user_data: *mut libc::c_void,
+        frame: *const u8,
+        frame_size: libc::c_int,
+        free_format_bytes: libc::c_int,
+        buf_size: libc::size_t,
+        offset: u64,
+        info: *mut mp3dec_frame_info_t,
+    ) -> libc::c_int,
+>;
+#[cfg(feature = "minimp3-ex")]
+extern "C" {
+    pub fn mp3dec_iterate_buf(
+        buf: *const u8,
+        buf_size: libc::size_t,
+        callback: MP3D_ITERATE_CB,
+        user_data: *mut libc::c_void,
+    ) -> libc::c_int;
+}
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_SEEK_TO_BYTE: u32 = 0;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_SEEK_TO_SAMPLE: u32 = 1;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_DO_NOT_SCAN: u32 = 2;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_E_PARAM: i32 = -1;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_E_MEMORY: i32 = -2;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_E_IOERROR: i32 = -3;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_E_USER: i32 = -4;
+#[cfg(feature = "minimp3-ex")]
+pub const MP3D_E_DECODE: i32 = -5;
+#[cfg(feature = "minimp3-ex")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mp3dec_frame_t {
+    pub sample: u64,
+    pub offset: u64,
+}
+#[cfg(feature = "minimp3-ex")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mp3dec_index_t {
+    pub frames: *mut mp3dec_frame_t,
+    pub num_frames: libc::size_t,
+    pub capacity: libc::size_t,
+}
+#[cfg(feature = "minimp3-ex")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mp3dec_map_info_t {
+    pub buffer: *const u8,
+    pub size: libc::size_t,
+}
+#[cfg(feature = "minimp3-ex")]
+pub type MP3D_READ_CB = ::core::option::Option<
+    unsafe extern "C" fn(
+        buf: *mut libc::c_void,
+        size: libc::size_t,
+        user_data: *mut libc::c_void,
+    ) -> libc::size_t,
+>;
+#[cfg(feature = "minimp3-ex")]
+pub type MP3D_SEEK_CB = ::core::option::Option<
+    unsafe extern "C" fn(position: u64, user_data: *mut libc::c_void) -> libc::c_int,
+>;
+#[cfg(feature = "minimp3-ex")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mp3dec_io_t {
+    pub read: MP3D_READ_CB,
+    pub read_data: *mut libc::c_void,
+    pub seek: MP3D_SEEK_CB,
+    pub seek_data: *mut libc::c_void,
+}
+#[cfg(feature = "minimp3-ex")]
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mp3dec_ex_t {
+    pub mp3d: mp3dec_t,
+    pub file: mp3dec_map_info_t,
+    pub io: *mut mp3dec_io_t,
+    pub index: mp3dec_index_t,
+    pub offset: u64,
+    pub samples: u64,
+    pub detected_samples: u64,
+    pub cur_sample: u64,
+    pub start_offset: u64,
+    pub end_offset: u64,
+    pub info: mp3dec_frame_info_t,
+    pub buffer: [mp3d_sample_t; 2304usize],
+    pub input_consumed: libc::size_t,
+    pub input_filled: libc::size_t,
+    pub is_file: libc::c_int,
+    pub flags: libc::c_int,
+    pub vbr_tag_found: libc::c_int,
+    pub indexes_built: libc::c_int,
+    pub free_format_bytes: libc::c_int,
+    pub buffer_samples: libc::c_int,
+    pub buffer_consumed: libc::c_int,
+    pub to_skip: libc::c_int,
+    pub start_delay: libc::c_int,
+    pub last_error: libc::c_int,
+}
+#[cfg(feature = "minimp3-ex")]
+extern "C" {
+    pub fn mp3dec_ex_open_cb(
+        dec: *mut mp3dec_ex_t,
+        io: *mut mp3dec_io_t,
+        flags: libc::c_int,
+    ) -> libc::c_int;
+}
+#[cfg(feature = "minimp3-ex")]
+extern "C" {
+    pub fn mp3dec_ex_close(dec: *mut mp3dec_ex_t);
+}
+#[cfg(feature = "minimp3-ex")]
+extern "C" {
+    pub fn mp3dec_ex_seek(dec: *mut mp3dec_ex_t, position: u64) -> libc::c_int;
+}
+#[cfg(feature = "minimp3-ex")]
+extern "C" {
+    pub fn mp3dec_ex_read(
+        dec: *mut mp3dec_ex_t,
+        buf: *mut mp3d_sample_t,
+        samples: libc::size_t,
+    ) -> libc::size_t;
+}
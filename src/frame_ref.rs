@@ -0,0 +1,138 @@
+//! Finding frames apart from decoding them.
+
+use crate::{Frame, RawDecoder, Sample};
+
+/// A frame which was found by [`RawDecoder::find`], but not decoded yet.
+///
+/// This is for looking at a frame before deciding whether it's worth decoding,
+/// such as when only some of a stream is played, or frames are fetched over a network by priority.
+/// Finding is only header parsing, entirely in Rust, as with [`peek`](RawDecoder::peek).
+///
+/// A frame that's skipped instead of decoded leaves the bit reservoir short for the next one,
+/// which can then fail to decode, much like after seeking.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = RawDecoder::new();
+/// let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+/// let mut data = &mp3[..];
+/// while let Some((found, consumed)) = decoder.find(data) {
+///     data = &data[consumed..];
+///     // only spend time on stereo frames
+///     if matches!(found.frame(), Frame::Audio(audio) if audio.channels() == 2) {
+///         if let Frame::Audio(audio) = found.decode(&mut decoder, &mut pcm) {
+///             // process audio frame here!
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrameRef<'src> {
+    // the frame as peeked, and all the data it was found in, up to where it ends
+    frame: Frame<'src, 'static>,
+    data: &'src [u8],
+}
+
+impl<'src> FrameRef<'src> {
+    /// Gets the frame without its samples, which is what [`peek`](RawDecoder::peek) would've returned,
+    /// such as for looking at its [`header`](crate::Audio::header) or [`source`](crate::Audio::source).
+    #[inline]
+    pub fn frame(&self) -> &Frame<'src, 'static> {
+        &self.frame
+    }
+
+    /// Gets all the data the frame spans, including any garbage before it,
+    /// which is as many bytes as [`find`](RawDecoder::find) consumed.
+    #[inline]
+    pub fn data(&self) -> &'src [u8] {
+        self.data
+    }
+
+    /// Decodes the frame into `dest`, with the same decoder which found it,
+    /// returning what [`next`](RawDecoder::next) would've.
+    ///
+    /// Frames other than audio are returned as they were found, without doing anything.
+    /// Audio is decoded from exactly the bytes that were found, so the result consumes just as many.
+    /// The decoder shouldn't be used for anything else in between, or the bit reservoir
+    /// won't be in order for this frame.
+    pub fn decode<'pcm, const N: usize>(self, decoder: &mut RawDecoder, dest: &'pcm mut [Sample; N]) -> Frame<'src, 'pcm> {
+        let offset = match &self.frame {
+            Frame::Audio(audio) => audio.offset_in_source(),
+            _ => return self.frame,
+        };
+        // the garbage is left out, so the decoder re-syncs to this frame without searching
+        let frame = unsafe { decoder.next_into(self.data.get_unchecked(offset..), dest.as_mut_ptr(), N, usize::MAX) };
+        match frame {
+            Some((Frame::Audio(mut audio), _)) => {
+                audio.garbage = unsafe { self.data.get_unchecked(..offset) };
+                audio.offset += offset;
+                Frame::Audio(audio)
+            },
+            Some((frame, _)) => frame,
+            // there's a whole frame, so this is unreachable, and is the same as a frame with no audio
+            None => Frame::Other(self.data),
+        }
+    }
+}
+
+impl RawDecoder {
+    /// Finds the next frame without decoding it, so it can be looked at first
+    /// and then [decoded](FrameRef::decode) or skipped, returning it and how many bytes it spans.
+    ///
+    /// This is the same as [`peek`](Self::peek) followed by [`next`](Self::next) on the same data,
+    /// without searching for the frame twice.
+    ///
+    /// For [`Frame::FormatChanged`], no bytes are consumed, so the same data should be passed again.
+    /// The [non-audio limits](crate::DecoderOptions::max_non_audio_bytes) count frames as they're found,
+    /// whether they're decoded or not, so a frame past them is found as [`Error::NoAudio`](crate::Error::NoAudio).
+    pub fn find<'src>(&mut self, src: &'src [u8]) -> Option<(FrameRef<'src>, usize)> {
+        let (frame, len) = self.peek(src)?;
+        // frames other than audio are never passed to the decoder, so they're counted here
+        let (frame, len) = self.limit_non_audio(frame, len);
+        // SAFETY: Peeking never consumes more than it was given.
+        let data = unsafe { src.get_unchecked(..len) };
+        Some((FrameRef { frame, data }, len))
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::{test_util::FrameBuilder, DecoderOptions, Error, Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn non_audio_limits() {
+        let mut mp3 = [0xAA; 100].to_vec();
+        let mut info = FrameBuilder::new().build().unwrap();
+        info[36..40].copy_from_slice(b"Info");
+        mp3.extend(info);
+        mp3.extend(FrameBuilder::new().stream(3).unwrap());
+
+        let frames = |limit| {
+            let mut decoder = RawDecoder::with_options(DecoderOptions::new().max_non_audio_bytes(limit));
+            let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+            let mut data = &mp3[..];
+            let mut frames = Vec::new();
+            while let Some((found, consumed)) = decoder.find(data) {
+                data = &data[consumed..];
+                frames.push(match found.decode(&mut decoder, &mut pcm) {
+                    Frame::Audio(_) => "audio",
+                    Frame::Xing { .. } => "xing",
+                    Frame::Error(Error::NoAudio) => "no audio",
+                    _ => "other",
+                });
+            }
+            frames
+        };
+        assert_eq!(frames(None), ["xing", "audio", "audio", "audio"]);
+        assert_eq!(frames(Some(600)), ["xing", "audio", "audio", "audio"]);
+        // the garbage and the Xing frame are over the limit together
+        assert_eq!(frames(Some(500))[0], "no audio");
+    }
+}
@@ -0,0 +1,575 @@
+//! Pure-Rust parsing of MPEG Audio frame headers, without any decoder state.
+
+/// Describes the channel mode of a frame, as stored in its header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ChannelMode {
+    /// Two independent channels.
+    Stereo,
+
+    /// Two channels, coded with intensity and/or mid-side stereo.
+    ///
+    /// See [`FrameHeader::mode_extension`] for which of these are in use.
+    JointStereo,
+
+    /// Two independent mono channels, such as a bilingual broadcast.
+    DualChannel,
+
+    /// One channel.
+    Mono,
+}
+
+/// Describes the de-emphasis that should be applied to a frame, as stored in its header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Emphasis {
+    /// No emphasis.
+    None,
+
+    /// 50/15 µs emphasis.
+    FiftyFifteen,
+
+    /// Reserved value, which shouldn't appear in valid streams.
+    Reserved,
+
+    /// CCITT J.17 emphasis.
+    CcittJ17,
+}
+
+/// Describes the MPEG version of a frame, as stored in its header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Version {
+    /// MPEG-1 (ISO/IEC 11172-3), at 32, 44.1 and 48 kHz.
+    Mpeg1,
+
+    /// MPEG-2 (ISO/IEC 13818-3) low sampling frequencies, at 16, 22.05 and 24 kHz.
+    Mpeg2,
+
+    /// The unofficial MPEG-2.5 extension, at 8, 11.025 and 12 kHz.
+    Mpeg2_5,
+}
+
+/// A 4-byte MPEG Audio frame header.
+///
+/// This is parsed entirely in Rust, so it can be used for indexing or validating streams
+/// without going through the decoder at all.
+///
+/// # Example
+///
+/// ```
+/// use rmp3::{ChannelMode, FrameHeader, Version};
+///
+/// // MPEG-1 layer III, 128 kb/s, 44.1 kHz, joint stereo
+/// let header = FrameHeader::parse(&[0xFF, 0xFB, 0x90, 0x64]).unwrap();
+/// assert_eq!(header.version(), Version::Mpeg1);
+/// assert_eq!(header.mpeg_layer(), 3);
+/// assert_eq!(header.bitrate(), Some(128));
+/// assert_eq!(header.sample_rate(), 44100);
+/// assert_eq!(header.channel_mode(), ChannelMode::JointStereo);
+/// assert_eq!(header.frame_bytes(), Some(417));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FrameHeader([u8; 4]);
+
+// Bitrates in kb/s, indexed by [MPEG-1?][layer - 1][bitrate index - 1].
+const BITRATES: [[[u16; 14]; 3]; 2] = [
+    [
+        [32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ],
+    [
+        [32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+        [32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+        [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    ],
+];
+
+// These mirror the constants of the same name in minimp3.
+const HDR_SIZE: usize = 4;
+const MAX_FREE_FORMAT_FRAME_SIZE: usize = 2304;
+
+// Sample rates of MPEG-1 in Hz, which are halved for MPEG-2 and quartered for MPEG-2.5.
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+impl FrameHeader {
+    /// Parses a frame header, returning `None` if it's invalid.
+    ///
+    /// This accepts exactly the same headers that minimp3 does:
+    /// the sync word must be present, no field may be set to a reserved value,
+    /// and MPEG-2.5 is only allowed for layer III.
+    pub fn parse(header: &[u8; 4]) -> Option<Self> {
+        let sync = header[0] == 0xFF
+            && ((header[1] & 0xF0) == 0xF0 || (header[1] & 0xFE) == 0xE2);
+        let valid = sync
+            && (header[1] >> 1) & 0b11 != 0b00 // layer
+            && header[2] >> 4 != 0b1111 // bitrate
+            && (header[2] >> 2) & 0b11 != 0b11; // sample rate
+        if valid {
+            Some(Self(*header))
+        } else {
+            None
+        }
+    }
+
+    /// Constructs a `FrameHeader` from the start of a frame minimp3 has already validated.
+    #[inline(always)]
+    pub(crate) unsafe fn from_frame_unchecked(frame: &[u8]) -> Self {
+        Self([
+            *frame.get_unchecked(0),
+            *frame.get_unchecked(1),
+            *frame.get_unchecked(2),
+            *frame.get_unchecked(3),
+        ])
+    }
+
+    /// Gets the raw bytes of the header.
+    #[inline]
+    pub fn bytes(&self) -> [u8; 4] {
+        self.0
+    }
+
+    /// Gets the bitrate in kb/s, or `None` if it's [free-format](Self::is_free_format).
+    #[inline]
+    pub fn bitrate(&self) -> Option<u32> {
+        match self.bitrate_index() {
+            0 => None,
+            index => {
+                let mpeg1 = (self.version() == Version::Mpeg1) as usize;
+                let layer = self.mpeg_layer() as usize;
+                Some(BITRATES[mpeg1][layer - 1][index as usize - 1] as u32)
+            },
+        }
+    }
+
+    /// Gets the raw 4-bit bitrate index, which is 0 for [free-format](Self::is_free_format) frames.
+    ///
+    /// What it stands for depends on the version and layer, which [`bitrate`](Self::bitrate) takes care of,
+    /// so this is mostly for comparing headers or rewriting them.
+    #[inline]
+    pub fn bitrate_index(&self) -> u8 {
+        self.0[2] >> 4
+    }
+
+    /// Gets the channel mode.
+    #[inline]
+    pub fn channel_mode(&self) -> ChannelMode {
+        match self.0[3] >> 6 {
+            0b00 => ChannelMode::Stereo,
+            0b01 => ChannelMode::JointStereo,
+            0b10 => ChannelMode::DualChannel,
+            _ => ChannelMode::Mono,
+        }
+    }
+
+    /// Gets how many bytes after the header and checksum are covered by the CRC-16 checksum,
+    /// as in, the bit allocation of layer I, or the side information of layer III.
+    ///
+    /// Returns `None` for layer II, whose coverage depends on which bit allocation table is in use,
+    /// which isn't currently supported.
+    pub fn crc_bytes(&self) -> Option<usize> {
+        let mpeg1 = self.version() == Version::Mpeg1;
+        let mono = self.channel_mode() == ChannelMode::Mono;
+        match self.mpeg_layer() {
+            1 => {
+                // 4 bits of allocation per subband per channel, up to the intensity stereo bound
+                let bound = match self.channel_mode() {
+                    ChannelMode::Mono => 0,
+                    ChannelMode::JointStereo => (self.mode_extension() as usize + 1) * 4,
+                    _ => 32,
+                };
+                Some((32 + bound) / 2)
+            },
+            // side information
+            3 => Some(match (mpeg1, mono) {
+                (true, true) => 17,
+                (true, false) => 32,
+                (false, true) => 9,
+                (false, false) => 17,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Gets the channel count, which is 1 for [`Mono`](ChannelMode::Mono) and 2 otherwise.
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        match self.channel_mode() {
+            ChannelMode::Mono => 1,
+            _ => 2,
+        }
+    }
+
+    /// Gets whether the copyright bit is set.
+    #[inline]
+    pub fn copyright(&self) -> bool {
+        self.0[3] & 0b1000 != 0
+    }
+
+    /// Gets the emphasis.
+    #[inline]
+    pub fn emphasis(&self) -> Emphasis {
+        match self.0[3] & 0b11 {
+            0b00 => Emphasis::None,
+            0b01 => Emphasis::FiftyFifteen,
+            0b10 => Emphasis::Reserved,
+            _ => Emphasis::CcittJ17,
+        }
+    }
+
+    /// Gets the total length of the frame in bytes, including this header and the [`padding`](Self::padding).
+    ///
+    /// Returns `None` if the frame is [free-format](Self::is_free_format),
+    /// since its length can't be known from the header alone.
+    #[inline]
+    pub fn frame_bytes(&self) -> Option<usize> {
+        let bitrate = self.bitrate()? as usize;
+        let mut bytes = self.sample_count() * bitrate * 125 / self.sample_rate() as usize;
+        if self.mpeg_layer() == 1 {
+            // layer I is made out of 4-byte slots
+            bytes &= !3;
+        }
+        Some(bytes + self.padding_bytes())
+    }
+
+    /// Gets whether the frame is protected by a CRC-16 checksum, which follows the header.
+    #[inline]
+    pub fn has_crc(&self) -> bool {
+        // the header stores the inverse, a "protection absent" bit
+        self.0[1] & 0b1 == 0
+    }
+
+    /// Gets whether this header uses any values which the standard reserves or disallows,
+    /// but which [`parse`](Self::parse) doesn't reject, since minimp3 accepts them.
+    ///
+    /// Those are the reserved [emphasis](Emphasis::Reserved), and for MPEG-1 layer II,
+    /// bitrates which aren't allowed with the channel mode
+    /// (32, 48, 56 and 80 kb/s are mono only, and 224 kb/s and above aren't allowed in mono).
+    pub fn has_reserved_values(&self) -> bool {
+        if self.emphasis() == Emphasis::Reserved {
+            return true;
+        }
+        match (self.version(), self.mpeg_layer(), self.bitrate()) {
+            (Version::Mpeg1, 2, Some(bitrate)) => {
+                let mono = self.channel_mode() == ChannelMode::Mono;
+                match bitrate {
+                    32 | 48 | 56 | 80 => !mono,
+                    224 | 256 | 320 | 384 => mono,
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// Gets whether the frame is free-format, as in, has an unspecified bitrate.
+    ///
+    /// Free-format streams are rare, and allowed to exceed the usual bitrate limits.
+    #[inline]
+    pub fn is_free_format(&self) -> bool {
+        self.bitrate_index() == 0
+    }
+
+    /// Gets the raw 2-bit mode extension,
+    /// which is only meaningful for [`JointStereo`](ChannelMode::JointStereo) frames.
+    ///
+    /// For layer III, bit 0 (`0b01`) signals intensity stereo and bit 1 (`0b10`) mid-side stereo.
+    /// For layers I & II, it selects the subband from which intensity stereo begins (4, 8, 12 or 16).
+    #[inline]
+    pub fn mode_extension(&self) -> u8 {
+        (self.0[3] >> 4) & 0b11
+    }
+
+    /// Gets the MPEG layer, which is 1, 2 or 3.
+    #[inline]
+    pub fn mpeg_layer(&self) -> u8 {
+        4 - ((self.0[1] >> 1) & 0b11)
+    }
+
+    /// Gets whether the original bit is set (as opposed to it being a copy).
+    #[inline]
+    pub fn original(&self) -> bool {
+        self.0[3] & 0b100 != 0
+    }
+
+    /// Gets whether the frame is padded with an extra slot
+    /// (one byte, or four bytes for layer I).
+    #[inline]
+    pub fn padding(&self) -> bool {
+        self.0[2] & 0b10 != 0
+    }
+
+    /// Gets whether the private bit is set.
+    ///
+    /// This bit has no meaning in the standard and is free for applications to use.
+    #[inline]
+    pub fn private(&self) -> bool {
+        self.0[2] & 0b1 != 0
+    }
+
+    /// Gets the sample count per [`channel`](Self::channels) in the frame.
+    #[inline]
+    pub fn sample_count(&self) -> usize {
+        match (self.mpeg_layer(), self.version()) {
+            (1, _) => 384,
+            (3, Version::Mpeg2) | (3, Version::Mpeg2_5) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Gets the sample rate in Hz.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        let base = SAMPLE_RATES[self.sample_rate_index() as usize];
+        match self.version() {
+            Version::Mpeg1 => base,
+            Version::Mpeg2 => base / 2,
+            Version::Mpeg2_5 => base / 4,
+        }
+    }
+
+    /// Gets the raw 2-bit sample rate index, which is never the reserved `0b11`.
+    ///
+    /// See [`bitrate_index`](Self::bitrate_index) for more info.
+    #[inline]
+    pub fn sample_rate_index(&self) -> u8 {
+        (self.0[2] >> 2) & 0b11
+    }
+
+    /// Gets the MPEG version.
+    #[inline]
+    pub fn version(&self) -> Version {
+        match (self.0[1] >> 3) & 0b11 {
+            0b11 => Version::Mpeg1,
+            0b10 => Version::Mpeg2,
+            _ => Version::Mpeg2_5,
+        }
+    }
+
+    /// Gets the frame length excluding the padding, or `free_format_bytes` if it's free-format.
+    #[inline]
+    fn base_bytes(&self, free_format_bytes: usize) -> usize {
+        match self.frame_bytes() {
+            Some(bytes) => bytes - self.padding_bytes(),
+            None => free_format_bytes,
+        }
+    }
+
+    /// Gets the frame length including the padding, using `free_format_bytes` if it's free-format.
+    #[inline]
+    pub(crate) fn frame_bytes_or(&self, free_format_bytes: usize) -> usize {
+        self.base_bytes(free_format_bytes) + self.padding_bytes()
+    }
+
+    /// Checks whether `next` could belong to the same stream as this header (`hdr_compare` in minimp3).
+    #[inline]
+    pub(crate) fn matches(&self, next: &Self) -> bool {
+        (self.0[1] ^ next.0[1]) & 0xFE == 0
+            && (self.0[2] ^ next.0[2]) & 0x0C == 0
+            && self.is_free_format() == next.is_free_format()
+    }
+
+    /// Gets the length of the layer III side information which follows the header (and CRC).
+    #[inline]
+    pub(crate) fn side_info_bytes(&self) -> usize {
+        match (self.version(), self.channels()) {
+            (Version::Mpeg1, 1) => 17,
+            (Version::Mpeg1, _) => 32,
+            (_, 1) => 9,
+            (_, _) => 17,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn padding_bytes(&self) -> usize {
+        match (self.padding(), self.mpeg_layer()) {
+            (false, _) => 0,
+            (true, 1) => 4,
+            (true, _) => 1,
+        }
+    }
+}
+
+/// Parses a header from the start of `data`, if there's enough of it.
+#[inline]
+pub(crate) fn read(data: &[u8]) -> Option<FrameHeader> {
+    match data {
+        [a, b, c, d, ..] => FrameHeader::parse(&[*a, *b, *c, *d]),
+        _ => None,
+    }
+}
+
+/// Checks whether enough frames follow `header` to be sure it's not a false sync (`mp3d_match_frame` in minimp3).
+fn match_frame(header: FrameHeader, data: &[u8], free_format_bytes: usize, sync_matches: usize) -> bool {
+    let mut i = 0;
+    let mut current = header;
+    for matches in 0..sync_matches {
+        i += current.frame_bytes_or(free_format_bytes);
+        if i + HDR_SIZE > data.len() {
+            return matches > 0;
+        }
+        match read(&data[i..]) {
+            Some(next) if header.matches(&next) => current = next,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Finds the next frame in `data`, returning its offset and length (`mp3d_find_frame` in minimp3).
+///
+/// If there's none, the offset is `data.len()` and the length is 0,
+/// unless the search stopped after `max_offset`, in which case the offset is where it stopped.
+/// `free_format_bytes` is updated when a free-format stream is found,
+/// and `sync_matches` is how many frames have to follow (`MAX_FRAME_SYNC_MATCHES` in minimp3).
+pub(crate) fn find_frame(
+    data: &[u8],
+    free_format_bytes: &mut usize,
+    sync_matches: usize,
+    max_offset: usize,
+) -> (usize, usize) {
+    let mut i = 0;
+    while i + HDR_SIZE < data.len() {
+        if i > max_offset {
+            return (i, 0);
+        }
+        let mp3 = &data[i..];
+        if let Some(header) = read(mp3) {
+            let mut frame_bytes = header.base_bytes(*free_format_bytes);
+            let mut frame_and_padding = frame_bytes + header.padding_bytes();
+
+            // free-format, so the length is the distance to the next matching header
+            let mut k = HDR_SIZE;
+            while frame_bytes == 0 && k < MAX_FREE_FORMAT_FRAME_SIZE && i + 2 * k < data.len() - HDR_SIZE {
+                if let Some(next) = read(&mp3[k..]).filter(|x| header.matches(x)) {
+                    let fb = k - header.padding_bytes();
+                    let next_fb = fb + next.padding_bytes();
+                    let followed = i + k + next_fb + HDR_SIZE <= data.len()
+                        && matches!(read(&mp3[k + next_fb..]), Some(x) if header.matches(&x));
+                    if followed {
+                        frame_and_padding = k;
+                        frame_bytes = fb;
+                        *free_format_bytes = fb;
+                    }
+                }
+                k += 1;
+            }
+
+            let matched = frame_bytes != 0
+                && i + frame_and_padding <= data.len()
+                && match_frame(header, mp3, frame_bytes, sync_matches);
+            if matched || (i == 0 && frame_and_padding == data.len()) {
+                return (i, frame_and_padding);
+            }
+            *free_format_bytes = 0;
+        }
+        i += 1;
+    }
+    (data.len(), 0)
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for FrameHeader {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "FrameHeader {{ version: {}, layer: {}, bitrate: {}, sample_rate: {}, channel_mode: {} }}",
+            self.version(),
+            self.mpeg_layer(),
+            self.bitrate(),
+            self.sample_rate(),
+            self.channel_mode(),
+        )
+    }
+}
+
+// These go through the raw bytes, so that deserializing validates them like `parse`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FrameHeader {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FrameHeader {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = <[u8; 4] as serde::Deserialize>::deserialize(deserializer)?;
+        Self::parse(&bytes).ok_or_else(|| serde::de::Error::custom("invalid frame header"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        // missing sync
+        assert_eq!(FrameHeader::parse(&[0xFF, 0x0B, 0x90, 0x64]), None);
+        // reserved bitrate & sample rate
+        assert_eq!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x64]), None);
+        assert_eq!(FrameHeader::parse(&[0xFF, 0xFB, 0x9C, 0x64]), None);
+        // MPEG-2.5 layer II isn't allowed
+        assert_eq!(FrameHeader::parse(&[0xFF, 0xE5, 0x90, 0x64]), None);
+
+        // MPEG-2 layer III, 64 kb/s, 22.05 kHz, padded mono
+        let header = FrameHeader::parse(&[0xFF, 0xF3, 0x82, 0xC4]).unwrap();
+        assert_eq!(header.version(), Version::Mpeg2);
+        assert_eq!(header.bitrate(), Some(64));
+        assert_eq!(header.bitrate_index(), 8);
+        assert_eq!(header.sample_rate(), 22050);
+        assert_eq!(header.sample_rate_index(), 0);
+        assert_eq!(header.channels(), 1);
+        assert_eq!(header.sample_count(), 576);
+        assert_eq!(header.frame_bytes(), Some(209));
+
+        assert!(!header.has_reserved_values());
+
+        // MPEG-1 layer II, 384 kb/s, 44.1 kHz, mono (disallowed)
+        let header = FrameHeader::parse(&[0xFF, 0xFD, 0xE0, 0xC0]).unwrap();
+        assert_eq!(header.bitrate(), Some(384));
+        assert!(header.has_reserved_values());
+
+        // MPEG-1 layer I, 32 kb/s, 32 kHz
+        let header = FrameHeader::parse(&[0xFF, 0xFF, 0x18, 0x00]).unwrap();
+        assert_eq!(header.mpeg_layer(), 1);
+        assert_eq!(header.frame_bytes(), Some(48));
+    }
+
+    #[test]
+    fn find_frame() {
+        fn stream(data: &mut [u8], garbage: usize, header: [u8; 4], frame_bytes: usize) {
+            for frame in data[garbage..].chunks_mut(frame_bytes) {
+                frame[..4].copy_from_slice(&header);
+            }
+        }
+
+        let mut free_format_bytes = 0;
+        let mut data = [0u8; 10 + 12 * 417];
+        stream(&mut data, 10, [0xFF, 0xFB, 0x90, 0x64], 417);
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 10, usize::MAX), (10, 417));
+        assert_eq!(free_format_bytes, 0);
+
+        // free-format, so it has to be detected from the distance between headers
+        let mut data = [0u8; 12 * 300];
+        stream(&mut data, 0, [0xFF, 0xFB, 0x00, 0x64], 300);
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 10, usize::MAX), (0, 300));
+        assert_eq!(free_format_bytes, 300);
+
+        // a stray frame which the next one doesn't match, only accepted without strictness
+        let mut data = [0u8; 417 + 12 * 384];
+        stream(&mut data[..417], 0, [0xFF, 0xFB, 0x90, 0x64], 417);
+        stream(&mut data, 417, [0xFF, 0xFB, 0x94, 0x64], 384);
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 10, usize::MAX), (417, 384));
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 0, usize::MAX), (0, 417));
+
+        let data = [0u8; 1000];
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 10, usize::MAX), (1000, 0));
+        assert_eq!(super::find_frame(&data, &mut free_format_bytes, 10, 99), (100, 0));
+    }
+}
@@ -1,15 +1,65 @@
 //! Idiomatic `no_std` bindings to [minimp3](https://github.com/lieff/minimp3) which don't allocate.
 //!
 //! # Features
+//! - `alloc`: Adds things that only require an allocator, like [`Decoder::boxed`], [`ChunksDecoder`]
+//! and [`decode_all`] for decoding a whole file at once. Implied by `std`.
+//! - `analysis`: Adds the [`analysis`] module for measuring decoded audio, such as ReplayGain and EBU R128.
+//! Implies `std`.
+//! - `bytes`: Adds the [`buf`] module, for packing PCM into and decoding from `bytes` buffers.
+//! Implies `alloc`.
+//! - `defmt`: Implements `defmt::Format` for metadata, errors and options, for embedded logging.
 //! - `float`: Changes the type of [`Sample`] to a single-precision float,
 //! and thus decoders will output float PCM.
 //!     - **This is a non-additive feature and will change API.**
 //!     **Do not do this in a library without notice [(why?)](
 //! https://github.com/rust-lang/cargo/issues/4328#issuecomment-652075026).**
+//! - `log`: Logs decoder anomalies with the `log` crate, such as losing sync, skipping garbage,
+//! reserved header values and concealed frames, to find out why a file sounds glitchy.
+//! - `minimp3-ex`: Adds the [`ex`] module, wrapping minimp3's extended API.
+//! This calls into the C allocator.
+//! - `mmap`: Adds [`DecoderOwned::open`] for decoding memory-mapped files. Implies `std`.
 //! - `mp1-mp2`: Includes MP1 and MP2 decoding code.
+//! - `net`: Adds the [`net`] module, for streaming internet radio over HTTP with ICY metadata.
+//! Implies `std`.
+//! - `nonstandard-but-logical`: Builds minimp3 with `MINIMP3_NONSTANDARD_BUT_LOGICAL`,
+//! which decodes some streams the way minimp3 considers logical rather than strictly by the standard,
+//! for output of unusual encoders. Frames are still found and sliced the same way.
+//! - `rayon`: Adds the [`parallel`] module for decoding on multiple threads. Implies `std`.
+//! - `rodio`: Adds the [`rodio`] module, for playing streams with rodio. Implies `std`.
+//! - `serde`: Implements `Serialize` and `Deserialize` for metadata, options, analysis results
+//! and frame indexes. Xing headers are only `Serialize`, since they borrow their data.
 //! - `simd` *(default)*: Enables handwritten SIMD optimizations on eligible targets.
+//! On WebAssembly, that requires building with the `simd128` target feature.
 //! - `std` *(default)*: Adds things that require `std`,
-//! right now that's just [`DecoderOwned`] for owned data on the heap.
+//! like [`DecoderOwned`] for owned data on the heap, [`DecoderPool`] for reusing decoders,
+//! and [`StreamMonitor`] for live stream metrics.
+//! - `symphonia`: Adds the [`symphonia`] module, for decoding with this crate in Symphonia pipelines.
+//! Implies `std`.
+//! - `test-util`: Adds the [`test_util`] module, for building MP3 frames to test with. Implies `alloc`.
+//! - `wasm`: Adds the [`wasm`] module, a JavaScript interface over wasm-bindgen,
+//! including a decoder for Web Audio worklets. Implies `std`.
+//! - `unsafe-ffi`: Makes the raw minimp3 bindings public as the `ffi` module,
+//! which is unsafe to use and exempt from semver.
+//!
+//! # Memory usage
+//!
+//! The decoder state is minimp3's `mp3dec_t`, which is 6668 bytes in every configuration,
+//! since it's only the bit reservoir and the overlap and filterbank history of layer III.
+//! Leaving out the `mp1-mp2` feature makes the code smaller, but not the state.
+//!
+//! On 64-bit targets, that makes a [`RawDecoder`] 6784 bytes, and a [`Decoder`] 11448 bytes
+//! (16056 with the `float` feature), as it holds the samples of a frame as well.
+//! With [storage of your own](Decoder::with_storage), a `Decoder` is 6848 bytes.
+//! On top of that, decoding a frame uses about 16 KiB of stack in minimp3 as scratch space.
+//!
+//! # Targets without an FPU
+//!
+//! Without the `float` feature, samples are `i16` all the way from minimp3 to the caller,
+//! and nothing on the decoding path in this crate uses floating point,
+//! other than measuring the [`energy`](DecoderOptions::energy) of frames if that's enabled.
+//! minimp3 itself still does its transforms and synthesis in `f32` regardless of output,
+//! as it has no integer implementation, so on cores like the Cortex-M0 or M3
+//! those are software floats. Only seeking by time and estimating positions use `f64`.
 //!
 //! # Example
 //!
@@ -36,12 +86,106 @@
 #![cfg_attr(feature = "nightly-docs", feature(doc_cfg))]
 #![cfg_attr(not(feature = "std"), no_std)]
 
-#[doc(hidden)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Raw bindings to minimp3, for what the rest of the crate doesn't wrap.
+///
+/// Nothing here is checked, and the layout of these types follows whichever minimp3 version
+/// is bundled, so it may change in any release. A [`RawDecoder`] doesn't expose its `mp3dec_t`,
+/// so these can't be mixed with it.
+#[cfg(feature = "unsafe-ffi")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "unsafe-ffi")))]
+#[allow(missing_docs)]
 pub mod ffi;
+#[cfg(not(feature = "unsafe-ffi"))]
+#[allow(dead_code)]
+mod ffi;
+
+#[cfg(feature = "analysis")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "analysis")))]
+pub mod analysis;
+#[cfg(feature = "bytes")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytes")))]
+pub mod buf;
+pub mod dsp;
+#[cfg(feature = "minimp3-ex")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "minimp3-ex")))]
+pub mod ex;
+#[cfg(feature = "net")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "net")))]
+pub mod net;
+#[cfg(feature = "rayon")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "rayon")))]
+pub mod parallel;
+#[cfg(feature = "rodio")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "rodio")))]
+pub mod rodio;
+#[cfg(feature = "symphonia")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "symphonia")))]
+pub mod symphonia;
+#[cfg(feature = "test-util")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "test-util")))]
+pub mod test_util;
+#[cfg(feature = "wasm")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "wasm")))]
+pub mod wasm;
+
+mod backend;
+mod buffer;
+#[cfg(feature = "alloc")]
+mod chunks;
+#[cfg(feature = "alloc")]
+mod decoded;
+mod duration;
+mod frame_ref;
+mod header;
+mod loop_region;
+#[cfg(feature = "std")]
+mod metrics;
+mod options;
+#[cfg(feature = "std")]
+mod pool;
+mod read_at;
+#[cfg(target_has_atomic = "ptr")]
+mod ring;
+mod samples;
+mod sink;
+mod storage;
+mod xing;
+
+pub use buffer::PcmBuffer;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+pub use chunks::ChunksDecoder;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+pub use decoded::{decode_all, DecodedAudio};
+pub use duration::{DurationMethod, StreamDuration};
+pub use frame_ref::FrameRef;
+pub use header::{ChannelMode, Emphasis, FrameHeader, Version};
+pub use loop_region::LoopRegion;
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+pub use metrics::{StreamMetrics, StreamMonitor};
+pub use options::{Concealment, CorruptPolicy, DecoderOptions, FormatPolicy, OutputLayout};
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+pub use pool::{DecoderPool, PooledDecoder, PooledPcm};
+pub use read_at::{ReadAt, ReadAtDecoder};
+#[cfg(target_has_atomic = "ptr")]
+pub use ring::{RingBuffer, RingConsumer, RingProducer};
+pub use samples::{Blocks, ChannelSamples, Resampled, SampleFrames, Samples};
+pub use sink::PcmSink;
+pub use storage::{InlinePcm, PcmStorage};
+pub use xing::{LameInfo, XingHeader};
 
-use core::{marker::PhantomData, mem::{MaybeUninit}, num::NonZeroUsize, ptr};
+use backend::Backend;
+use core::{fmt, marker::PhantomData, mem::{MaybeUninit}, num::NonZeroUsize, ops::ControlFlow, ptr, slice, task::Poll};
 use libc::c_int;
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 #[cfg(feature = "std")]
 use std::{rc::Rc, sync::Arc};
 
@@ -54,6 +198,107 @@ fn data_len_safe(len: usize) -> c_int {
     len.min(c_int::max_value() as usize) as c_int
 }
 
+/// Fills in the first `len` samples of a frame which couldn't be decoded.
+///
+/// If `repeat` is set, the samples still at `pcm` are from a frame of the same size,
+/// and otherwise they may be uninitialized.
+///
+/// `pcm` must be valid for writing `len` samples.
+unsafe fn conceal(pcm: *mut Sample, len: usize, concealment: Concealment, repeat: bool) {
+    match concealment {
+        Concealment::Repeat if repeat => (),
+        Concealment::FadeOut if repeat => {
+            let pcm = slice::from_raw_parts_mut(pcm, len);
+            for (i, sample) in pcm.iter_mut().enumerate() {
+                // linearly, from full volume to silence
+                let remaining = (len - i) as i32;
+                #[cfg(not(feature = "float"))]
+                let faded = (*sample as i32 * remaining / len as i32) as Sample;
+                #[cfg(feature = "float")]
+                let faded = *sample * remaining as f32 / len as f32;
+                *sample = faded;
+            }
+        },
+        _ => (0..len).for_each(|i| pcm.add(i).write(Sample::default())),
+    }
+}
+
+/// Converts `sample_count` samples per channel of mono PCM to stereo or vice versa, in place.
+///
+/// `pcm` must be valid for writing `sample_count * 2` samples, of which only the ones
+/// of the frame before converting it have to be initialized.
+unsafe fn conform_channels(pcm: *mut Sample, sample_count: usize, channels: u16) {
+    if channels == 2 {
+        // backwards, since every sample is written to its own index or after
+        for i in (0..sample_count).rev() {
+            let sample = pcm.add(i).read();
+            pcm.add(i * 2).write(sample);
+            pcm.add(i * 2 + 1).write(sample);
+        }
+    } else {
+        for i in 0..sample_count {
+            let (left, right) = (pcm.add(i * 2).read(), pcm.add(i * 2 + 1).read());
+            #[cfg(not(feature = "float"))]
+            let mixed = ((left as i32 + right as i32) / 2) as Sample;
+            #[cfg(feature = "float")]
+            let mixed = (left + right) * 0.5;
+            pcm.add(i).write(mixed);
+        }
+    }
+}
+
+/// Measures the mean square of samples, where 1.0 is full scale.
+fn energy(samples: &[Sample]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    #[cfg(not(feature = "float"))]
+    let sum = samples.iter().map(|&x| (i32::from(x) * i32::from(x)) as u64).sum::<u64>() as f32 / (32768.0 * 32768.0);
+    #[cfg(feature = "float")]
+    let sum = samples.iter().map(|&x| x * x).sum::<f32>();
+    sum / samples.len() as f32
+}
+
+/// Computes the CRC-16 used by MPEG Audio (polynomial 0x8005, non-reflected) over `data`.
+fn crc16(mut crc: u16, data: &[u8]) -> u16 {
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Computes the CRC-16 checksum of an MPEG Audio frame,
+/// which is stored right after the header if [`FrameHeader::has_crc`] is set.
+///
+/// The checksum covers the last two bytes of the header, and the [`crc_bytes`](FrameHeader::crc_bytes)
+/// after the checksum. If the frame isn't protected, those bytes are taken right after the header instead,
+/// so this can also be used to add a checksum to a frame, or to fix one after rewriting the header.
+/// [`Audio::verify_crc`] checks the checksum of decoded frames.
+///
+/// Returns `None` if `frame` doesn't start with a valid header, is too short, or is a layer II frame.
+///
+/// # Example
+///
+/// ```
+/// // MPEG-1 layer III, 128 kb/s, 44.1 kHz, joint stereo, with a checksum
+/// let mut frame = [0; 417];
+/// frame[..4].copy_from_slice(&[0xFF, 0xFA, 0x90, 0x64]);
+/// let crc = rmp3::frame_crc(&frame).unwrap();
+/// frame[4..6].copy_from_slice(&crc.to_be_bytes());
+/// ```
+pub fn frame_crc(frame: &[u8]) -> Option<u16> {
+    let header = match frame.get(..4) {
+        Some(&[a, b, c, d]) => FrameHeader::parse(&[a, b, c, d])?,
+        _ => return None,
+    };
+    let start = if header.has_crc() { 6 } else { 4 };
+    let protected = frame.get(start..start + header.crc_bytes()?)?;
+    Some(crc16(crc16(0xFFFF, &frame[2..4]), protected))
+}
+
 /// Returns the source slice from a received `mp3dec_frame_info_t`.
 #[inline(always)]
 unsafe fn source_slice<'src, 'frame>(
@@ -70,17 +315,38 @@ unsafe fn source_slice<'src, 'frame>(
 /// Maximum amount of samples that can be yielded per frame.
 pub const MAX_SAMPLES_PER_FRAME: usize = 0x900;
 
+/// How many frames are decoded and discarded before the target of a seek.
+const PREROLL_FRAMES: usize = 2;
+
+/// How much data the streaming decoders buffer past the start of a frame before it's decoded,
+/// which is what minimp3 recommends for finding frames confidently.
+#[cfg(feature = "alloc")]
+pub(crate) const LOOKAHEAD: usize = 16 * 1024;
+
+/// The position of a Xing/Info frame in the input data, and its header.
+type XingFrame<'src> = (usize, XingHeader<'src>);
+
 /// Describes audio samples in a frame.
 pub struct Audio<'src, 'pcm> {
     // entire result from minimp3 as-is
     info: ffi::mp3dec_frame_info_t,
 
+    // whether the samples were made up by the concealment policy
+    concealed: bool,
+
+    // mean square of the samples, if measured
+    energy: Option<f32>,
+
     // pcm data, if any
     pcm: Option<ptr::NonNull<Sample>>, // of lifetime 'pcm
     sample_count: usize,
 
-    // source slice (without garbage)
+    // source slice (without garbage), and where it starts in the decoder's input
     source: &'src [u8],
+    offset: usize,
+
+    // garbage that was skipped right before the source slice
+    garbage: &'src [u8],
 
     // 👻
     phantom: PhantomData<&'pcm [Sample]>,
@@ -97,11 +363,68 @@ pub enum Frame<'src, 'pcm> {
 
     /// ID3 or other unknown data
     Other(&'src [u8]),
+
+    /// A frame which was rejected by the decoder, with the reason why
+    Error(Error),
+
+    /// A Xing/Info header, which is stored in place of audio in the first frame,
+    /// and would otherwise decode as a frame of silence
+    Xing {
+        /// The parsed header
+        header: XingHeader<'src>,
+
+        /// Source slice of the whole frame
+        source: &'src [u8],
+    },
+
+    /// The format of the stream is about to change, which is announced
+    /// before the first frame of the new format, according to [`FormatPolicy`]
+    FormatChanged {
+        /// Channel count of the upcoming frames
+        channels: u16,
+
+        /// Sample rate of the upcoming frames in Hz
+        sample_rate: u32,
+    },
+}
+
+/// Describes why the decoder rejected a frame.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[non_exhaustive]
+pub enum Error {
+    /// The frame header uses values which are reserved or disallowed by the standard,
+    /// which is only checked in strict mode.
+    ///
+    /// See [`FrameHeader::has_reserved_values`] for more info.
+    ReservedValue,
+
+    /// No frame was found within the scan limit.
+    ///
+    /// See [`DecoderOptions::scan_limit`] for more info.
+    NoSync,
+
+    /// The frame was found, but its data is corrupt and couldn't be decoded.
+    ///
+    /// See [`CorruptPolicy`] for more info.
+    Corrupt,
+
+    /// The frame was found, but the output buffer is too small to decode it into.
+    ///
+    /// See [`RawDecoder::next`] for more info.
+    BufferTooSmall,
+
+    /// Too many frames or bytes in a row weren't audio, so the data likely isn't MPEG Audio at all.
+    ///
+    /// See [`DecoderOptions::max_non_audio_frames`] for more info.
+    NoAudio,
 }
 
 /// High-level streaming iterator for parsing or decoding MPEG Audio data.
 ///
 /// If the decoder should own the data, use a [`DecoderOwned`].
+/// If it should decode into a buffer of your own, use [`with_storage`](Self::with_storage).
 ///
 /// # Examples
 ///
@@ -145,12 +468,16 @@ pub enum Frame<'src, 'pcm> {
 /// # Ok(())
 /// # }
 /// ```
-pub struct Decoder<'src> {
+pub struct Decoder<'src, P = InlinePcm> {
+    // one more than the length, so that a zero-length peek is cached too without taking more space
     cached_peek_len: Option<NonZeroUsize>,
-    pcm: MaybeUninit<[Sample; MAX_SAMPLES_PER_FRAME]>,
+    pcm: P,
     raw: RawDecoder,
     source: &'src [u8],
     source_copy: &'src [u8],
+    trim: usize,
+    // bytes searched by calls to `next_limited` which returned `Pending`, for the scan limit
+    scanned: usize,
 }
 
 /// Exactly the same as [`Decoder`], but owns the data. Check [`Decoder`] for examples.
@@ -188,7 +515,16 @@ pub struct DecoderOwned<T> {
 /// # Ok(())
 /// # }
 /// ```
-pub struct RawDecoder(MaybeUninit<ffi::mp3dec_t>);
+pub struct RawDecoder {
+    // channels & sample rate, for the format policy
+    format: Option<(u16, u32)>,
+    // sample count & channels of the last frame written, for the concealment policy
+    last_frame: Option<(usize, u16)>,
+    // frames & bytes in a row which weren't audio, for the non-audio limits
+    non_audio: (usize, usize),
+    options: DecoderOptions,
+    state: backend::DefaultBackend,
+}
 
 /// Conditional type used to represent one PCM sample in output data.
 ///
@@ -202,42 +538,395 @@ pub type Sample = f32;
 impl<'src> Decoder<'src> {
     /// Constructs a new `Decoder` for processing MPEG Audio.
     pub fn new(source: &'src [u8]) -> Self {
+        Self::with_options(source, DecoderOptions::new())
+    }
+
+    /// Constructs a new `Decoder` for processing MPEG Audio with the given options.
+    pub fn with_options(source: &'src [u8], options: DecoderOptions) -> Self {
+        Self {
+            cached_peek_len: None,
+            pcm: InlinePcm::new(),
+            raw: RawDecoder::with_options(options),
+            source,
+            source_copy: source,
+            trim: 0,
+            scanned: 0,
+        }
+    }
+
+    /// Constructs a new `Decoder` for processing MPEG Audio directly on the heap.
+    ///
+    /// A `Decoder` is big, as it holds the decoder state and a whole frame of samples
+    /// (about 11 KiB, or 16 KiB with the `float` feature), so this is for when it shouldn't
+    /// even pass through the stack, such as on small embedded stacks.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+    pub fn boxed(source: &'src [u8]) -> Box<Self> {
+        Self::boxed_with_options(source, DecoderOptions::new())
+    }
+
+    /// Constructs a new `Decoder` for processing MPEG Audio with the given options directly on the heap.
+    ///
+    /// See [`boxed`](Self::boxed) for more info.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+    pub fn boxed_with_options(source: &'src [u8], options: DecoderOptions) -> Box<Self> {
+        let layout = alloc::alloc::Layout::new::<Self>();
+        unsafe {
+            let this = alloc::alloc::alloc(layout) as *mut Self;
+            if this.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+            // the samples are left uninitialized, as with `with_options`
+            ptr::addr_of_mut!((*this).cached_peek_len).write(None);
+            RawDecoder::init(ptr::addr_of_mut!((*this).raw), options);
+            ptr::addr_of_mut!((*this).source).write(source);
+            ptr::addr_of_mut!((*this).source_copy).write(source);
+            ptr::addr_of_mut!((*this).trim).write(0);
+            ptr::addr_of_mut!((*this).scanned).write(0);
+            Box::from_raw(this)
+        }
+    }
+}
+
+impl<'src, P: PcmStorage> Decoder<'src, P> {
+    /// Constructs a new `Decoder` for processing MPEG Audio, which decodes into `storage`
+    /// instead of a buffer of its own.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::{Decoder, Sample, MAX_SAMPLES_PER_FRAME};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+    /// let mut decoder = Decoder::with_storage(&mp3, &mut pcm);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_storage(source: &'src [u8], storage: P) -> Self {
         Self {
             cached_peek_len: None,
-            pcm: MaybeUninit::uninit(),
+            pcm: storage,
             raw: RawDecoder::new(),
             source,
             source_copy: source,
+            trim: 0,
+            scanned: 0,
         }
     }
 
+    /// Consumes the `Decoder`, returning the storage it decodes into.
+    #[inline]
+    pub fn into_storage(self) -> P {
+        self.pcm
+    }
+
     /// Reads the next frame, skipping over potential garbage data.
     pub fn next<'pcm>(&'pcm mut self) -> Option<Frame<'src, 'pcm>> {
-        self.cached_peek_len = None; // clear cache
         unsafe {
-            let (frame, len) = self.raw.next(self.source, &mut *self.pcm.as_mut_ptr())?;
-            self.offset_trusted(len);
+            let dest = self.pcm.as_mut_ptr() as *mut Sample;
+            self.next_into(dest, MAX_SAMPLES_PER_FRAME, usize::MAX)
+        }
+    }
+
+    /// Reads the next frame like [`next`](Self::next), but gives up after looking through about
+    /// `max_bytes` of input, such as to keep the latency of a cooperatively scheduled loop bounded.
+    ///
+    /// If no frame was found within that, the decoder moves past what was searched and
+    /// [`Poll::Pending`] is returned, and calling this again continues where it left off.
+    /// Corrupt frames skipped by [`CorruptPolicy::Skip`] count towards the limit too,
+    /// and are returned as [`Frame::Other`] once they reach it. The [scan limit](DecoderOptions::scan_limit)
+    /// still applies to the search as a whole, across the calls returning [`Poll::Pending`],
+    /// and [`Error::NoSync`] is returned as usual once it's reached.
+    ///
+    /// Only one frame is decoded per call, so this is bounded by the limit plus the
+    /// lookahead for finding frames (see [`DecoderOptions::sync_matches`]).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use core::task::Poll;
+    /// use rmp3::{Decoder, Frame};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut decoder = Decoder::new(&mp3);
+    /// loop {
+    ///     match decoder.next_limited(4096) {
+    ///         Poll::Ready(Some(Frame::Audio(audio))) => (), // play `audio.samples()` here!
+    ///         Poll::Ready(Some(_)) => (),
+    ///         Poll::Ready(None) => break,
+    ///         Poll::Pending => (), // yield to other tasks here!
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next_limited<'pcm>(&'pcm mut self, max_bytes: usize) -> Poll<Option<Frame<'src, 'pcm>>> {
+        let scan_limit = self.raw.options.scan_limit;
+        // what's left of it after the previous calls, which were searching for the same frame
+        let remaining = scan_limit.map(|x| x.saturating_sub(self.scanned));
+        self.raw.options.scan_limit = Some(remaining.map_or(max_bytes, |x| x.min(max_bytes)));
+        let position = self.position();
+        let frame = unsafe {
+            let dest = self.pcm.as_mut_ptr() as *mut Sample;
+            self.next_into(dest, MAX_SAMPLES_PER_FRAME, max_bytes)
+        };
+        self.raw.options.scan_limit = scan_limit;
+        match frame {
+            Some(Frame::Error(Error::NoSync)) if !matches!(remaining, Some(x) if x <= max_bytes) => {
+                self.scanned += self.position() - position;
+                Poll::Pending
+            },
+            frame => {
+                self.scanned = 0;
+                Poll::Ready(frame)
+            },
+        }
+    }
+
+    /// Reads only every `every`th audio frame, skipping the ones in between without decoding them,
+    /// for a rough but fast preview of a stream, such as when scanning large archives for features.
+    ///
+    /// The frames right before each returned one are decoded and thrown away too, as when seeking,
+    /// so that it has the bit reservoir and overlap it needs. That makes this decode about
+    /// 3 frames out of every `every`, so it only saves time for 4 and up.
+    ///
+    /// Anything other than audio in the skipped frames is skipped over too,
+    /// including [`Frame::FormatChanged`], but each [`Audio`] has its own format.
+    /// The frame after the skipped ones is returned as [`next`](Self::next) would, even if it isn't audio.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use core::num::NonZeroUsize;
+    /// use rmp3::{Decoder, Frame};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut decoder = Decoder::new(&mp3);
+    /// let every = NonZeroUsize::new(20).unwrap();
+    /// while let Some(frame) = decoder.next_sparse(every) {
+    ///     if let Frame::Audio(audio) = frame {
+    ///         // process one frame out of 20 here!
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next_sparse<'pcm>(&'pcm mut self, every: NonZeroUsize) -> Option<Frame<'src, 'pcm>> {
+        let mut left = every.get() - 1;
+        while left > PREROLL_FRAMES {
+            if let Frame::Audio(_) = self.peek()? {
+                left -= 1;
+            }
+            self.skip();
+        }
+        while left != 0 {
+            if let Frame::Audio(_) = self.next()? {
+                left -= 1;
+            }
+        }
+        self.next()
+    }
+
+    /// Reads the next frame, appending its samples to `dest` if it's audio.
+    ///
+    /// The samples are decoded straight into the spare capacity of `dest`, rather than
+    /// being copied from the decoder's own storage, which makes this the fastest way
+    /// to collect a whole stream. [`Concealment::Repeat`] and [`Concealment::FadeOut`]
+    /// mute instead though, since the previous frame isn't known to be at the end of `dest`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::Decoder;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut decoder = Decoder::new(&mp3);
+    /// let mut pcm = Vec::new();
+    /// while decoder.decode_append(&mut pcm).is_some() {}
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+    pub fn decode_append<'pcm>(&mut self, dest: &'pcm mut alloc::vec::Vec<Sample>) -> Option<Frame<'src, 'pcm>> {
+        dest.reserve(MAX_SAMPLES_PER_FRAME);
+        let len = dest.len();
+        unsafe {
+            // the spare capacity is uninitialized, so there's nothing to repeat
+            self.raw.last_frame = None;
+            let start = dest.as_mut_ptr().add(len);
+            let frame = self.next_into(start, MAX_SAMPLES_PER_FRAME, usize::MAX);
+            // nor is there after, as the frame isn't in the storage which later frames are decoded into
+            self.raw.last_frame = None;
+            let mut frame = frame?;
+            if let Frame::Audio(audio) = &mut frame {
+                let samples = audio.samples();
+                // moved back over what `seek_to_sample` trimmed off, if anything
+                ptr::copy(samples.as_ptr(), start, samples.len());
+                audio.pcm = ptr::NonNull::new(start);
+                dest.set_len(len + samples.len());
+            }
             Some(frame)
         }
     }
 
+    /// Reads the next frame into `dest`, which must be valid for writing `capacity` samples
+    /// for as long as `'pcm`, skipping up to `max_skip` bytes of corrupt frames.
+    unsafe fn next_into<'pcm>(
+        &mut self,
+        dest: *mut Sample,
+        capacity: usize,
+        max_skip: usize,
+    ) -> Option<Frame<'src, 'pcm>> {
+        self.cached_peek_len = None; // clear cache
+        let (mut frame, len) = self.raw.next_into(self.source, dest, capacity, max_skip)?;
+        if let Frame::Audio(audio) = &mut frame {
+            audio.offset += self.position();
+            if self.trim != 0 {
+                // left over from `seek_to_sample`, which is always less than a frame
+                audio.pcm = audio.pcm.map(|pcm| pcm.add(self.trim * audio.info.channels as usize));
+                audio.sample_count -= self.trim;
+                self.trim = 0;
+            }
+        }
+        match frame {
+            Frame::Error(Error::Corrupt) if self.raw.options.corrupt_policy == CorruptPolicy::Abort => {
+                self.offset_trusted(self.source.len())
+            },
+            Frame::Error(Error::NoAudio) => self.offset_trusted(self.source.len()),
+            _ => self.offset_trusted(len),
+        }
+        Some(frame)
+    }
+
+    /// Decodes the rest of the stream into `sink`, one audio frame at a time,
+    /// returning how many samples per channel were written.
+    ///
+    /// Frames which aren't audio are skipped over. See [`PcmSink`] for more info.
+    pub fn decode_into<S: PcmSink + ?Sized>(&mut self, sink: &mut S) -> u64 {
+        let mut sample_count = 0;
+        while let Some(frame) = self.next() {
+            if let Frame::Audio(audio) = frame {
+                sink.write_samples(&audio, audio.samples());
+                sample_count += audio.sample_count() as u64;
+            }
+        }
+        sample_count
+    }
+
+    /// Gets an iterator over the samples from here on, continuing across frames.
+    ///
+    /// See [`Samples`] for more info.
+    #[inline]
+    pub fn samples(&mut self) -> Samples<'_, 'src, P> {
+        Samples::new(self)
+    }
+
     /// Reads the next frame without decoding it, or advancing the decoder.
     /// Use [`skip`](Self::skip) to advance.
     ///
     /// This means that the samples will always be empty in [`Audio`],
     /// and [`sample_count`](Audio::sample_count) should be used to inspect the length.
     pub fn peek(&mut self) -> Option<Frame<'src, 'static>> {
-        let (frame, len) = self.raw.peek(self.source)?;
-        self.cached_peek_len = NonZeroUsize::new(len);
+        let (mut frame, len) = self.raw.peek(self.source)?;
+        if let Frame::Audio(audio) = &mut frame {
+            audio.offset += self.position();
+        }
+        self.cached_peek_len = NonZeroUsize::new(len + 1);
         Some(frame)
     }
 
+    /// Gets the total duration of the stream, without changing the position.
+    ///
+    /// If the stream starts with a [`XingHeader`] storing the frame count, that's used,
+    /// otherwise every frame is stepped through with [`peek`](Self::peek).
+    /// Returns `None` if there aren't any audio frames.
+    pub fn duration(&mut self) -> Option<StreamDuration> {
+        self.preserving(Self::measure_duration)
+    }
+
+    /// Gets the options the decoder is currently using.
+    #[inline]
+    pub fn options(&self) -> DecoderOptions {
+        self.raw.options()
+    }
+
+    /// Gets where a slice returned by the decoder, such as [`Frame::Other`],
+    /// starts in the input data, or `None` if it's not from the input data.
+    ///
+    /// For [`Audio`], [`offset_in_source`](Audio::offset_in_source) does the same.
+    pub fn offset_of(&self, data: &[u8]) -> Option<usize> {
+        let start = self.source_copy.as_ptr() as usize;
+        let offset = (data.as_ptr() as usize).checked_sub(start)?;
+        if offset + data.len() <= self.source_copy.len() {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
     /// Gets the current position in the input data, starting from 0.
     #[inline]
     pub fn position(&self) -> usize {
         unsafe { self.source.as_ptr().sub(self.source_copy.as_ptr() as usize) as usize }
     }
 
+    /// Gets all of the input data, regardless of the position.
+    ///
+    /// Positions given by the decoder, such as [`Audio::offset_in_source`] and [`offset_of`](Self::offset_of),
+    /// are indices into this, so regions of the stream can be sliced out of it, such as for cutting it losslessly.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::{Decoder, Frame};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut decoder = Decoder::new(&mp3);
+    /// // the first ten audio frames, as they are
+    /// let mut end = 0;
+    /// let mut frames = 0;
+    /// while let (Some(frame), true) = (decoder.peek(), frames < 10) {
+    ///     if let Frame::Audio(audio) = frame {
+    ///         end = audio.offset_in_source() + audio.source().len();
+    ///         frames += 1;
+    ///     }
+    ///     decoder.skip();
+    /// }
+    /// let cut = &decoder.source()[..end];
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    #[allow(clippy::misnamed_getters)] // `source` is only what's left of it
+    pub fn source(&self) -> &'src [u8] {
+        self.source_copy
+    }
+
+    /// Gets how many bytes of the input data are left after the current position,
+    /// such as for showing progress.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.source.len()
+    }
+
+    /// Gets whether the end of the input data was reached,
+    /// after which [`next`](Self::next) and [`peek`](Self::peek) always return `None`.
+    ///
+    /// Before that, they can still return `None` if all that's left is the start of
+    /// an incomplete frame, so this is for knowing when there's certainly nothing left.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.source.is_empty()
+    }
+
     /// Sets the current position in the input data.
     ///
     /// If `position` is out of bounds, it's set to the end of the data instead.
@@ -246,17 +935,259 @@ impl<'src> Decoder<'src> {
         let position = self.source_copy.len().min(position);
         self.source = unsafe { self.source_copy.get_unchecked(position..) };
         self.cached_peek_len = None;
+        self.trim = 0;
+        self.scanned = 0;
+    }
+
+    /// Sets the options the decoder should use from now on.
+    #[inline]
+    pub fn set_options(&mut self, options: DecoderOptions) {
+        self.raw.set_options(options);
+        self.cached_peek_len = None;
+    }
+
+    /// Seeks to approximately `seconds` into the stream.
+    ///
+    /// If the stream is VBR and starts with a [`XingHeader`] with a table of contents,
+    /// its position is looked up in that, which is fast but coarse.
+    /// Otherwise, this is the same as [`seek_to_sample`](Self::seek_to_sample).
+    /// If `seconds` is past the end, the decoder moves to the end and `None` is returned.
+    pub fn seek_to_time(&mut self, seconds: f64) -> Option<()> {
+        let (_, header, xing) = self.first_audio()?;
+        let (sample_count, sample_rate) = (header.sample_count() as f64, f64::from(header.sample_rate()));
+
+        if let Some((start, xing)) = xing.filter(|(_, x)| x.is_vbr()) {
+            if let (Some(frames), Some(_)) = (xing.frames(), xing.toc()) {
+                let duration = f64::from(frames) * sample_count / sample_rate;
+                if seconds >= duration {
+                    self.set_position(usize::MAX);
+                    return None;
+                }
+                if let Some(offset) = xing.seek_offset(seconds / duration) {
+                    self.set_position(start.saturating_add(offset as usize));
+                    return Some(());
+                }
+            }
+        }
+        self.seek_to_sample((seconds.max(0.0) * sample_rate) as u64).map(drop)
+    }
+
+    /// Seeks to approximately `seconds` into the stream and decodes the frame there,
+    /// such as for playing snippets while dragging a playhead in a scrubbing UI.
+    ///
+    /// This is [`seek_to_time`](Self::seek_to_time) followed by [`next`](Self::next),
+    /// so the samples start at `seconds` unless the seek was coarse, and the decoder is left right after the frame,
+    /// for decoding more of it if the playhead stays there. Returns `None` if there's no audio at `seconds`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::Decoder;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut decoder = Decoder::new(&mp3);
+    /// if let Some(audio) = decoder.decode_at(42.5) {
+    ///     // play `audio.samples()` here!
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decode_at(&mut self, seconds: f64) -> Option<Audio<'src, '_>> {
+        self.seek_to_time(seconds)?;
+        loop {
+            match self.peek()? {
+                Frame::Audio(_) => break,
+                _ => self.skip()?,
+            }
+        }
+        match self.next()? {
+            Frame::Audio(audio) => Some(audio),
+            _ => None,
+        }
+    }
+
+    /// Seeks to the frame containing the sample at `sample` (counted per channel),
+    /// so that the next [`Audio`] frame starts exactly at it, returning the sample it starts at.
+    ///
+    /// To get the same output as decoding from the start, a couple frames before it
+    /// are decoded and discarded, which fills the bit reservoir and overlap buffers.
+    /// If `sample` is past the end, the decoder moves to the end and `None` is returned.
+    ///
+    /// CBR streams are detected by an `Info` [`XingHeader`], in which case the frame's position is calculated.
+    /// If the frames aren't quite where they should be, that can land on another frame, whose first sample is returned.
+    /// Otherwise, this has to step through every frame up until `sample` with [`peek`](Self::peek),
+    /// since the data doesn't say where any sample is in it.
+    pub fn seek_to_sample(&mut self, sample: u64) -> Option<u64> {
+        let result = match self.detect_cbr() {
+            Some((start, header)) => self.seek_cbr(sample, start, header),
+            None => self.seek_scan(sample),
+        };
+        if result.is_none() {
+            self.set_position(usize::MAX);
+        }
+        result
+    }
+
+    /// Runs `f`, then puts the decoder back how it was.
+    fn preserving<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        // peeking resynchronizes, which would forget the bit reservoir and overlap carried over
+        // to the next frame, so the whole state is put back as well
+        let (position, trim) = (self.position(), self.trim);
+        let (format, non_audio, state) = (self.raw.format, self.raw.non_audio, self.raw.state.clone());
+        let result = f(self);
+        self.set_position(position);
+        self.trim = trim;
+        self.raw.format = format;
+        self.raw.non_audio = non_audio;
+        self.raw.state = state;
+        result
+    }
+
+    /// Counts the samples per channel before the next one to be decoded,
+    /// as in, in the audio frames before the position and whatever was trimmed after seeking.
+    #[cfg(feature = "rodio")]
+    pub(crate) fn samples_played(&mut self) -> u64 {
+        let (position, trim) = (self.position(), self.trim as u64);
+        self.preserving(|this| {
+            this.set_position(0);
+            let mut sample_count = trim;
+            while this.position() < position {
+                match this.peek() {
+                    Some(Frame::Audio(audio)) => sample_count += audio.sample_count() as u64,
+                    Some(_) => (),
+                    None => break,
+                }
+                this.skip();
+            }
+            sample_count
+        })
+    }
+
+    fn measure_duration(&mut self) -> Option<StreamDuration> {
+        let (_, header, xing) = self.first_audio()?;
+        let sample_rate = header.sample_rate();
+        if let Some(frames) = xing.and_then(|(_, x)| x.frames()) {
+            return Some(StreamDuration {
+                method: DurationMethod::Xing,
+                sample_count: u64::from(frames) * header.sample_count() as u64,
+                sample_rate,
+            });
+        }
+
+        let mut sample_count = 0;
+        while let Some(frame) = self.peek() {
+            if let Frame::Audio(audio) = frame {
+                sample_count += audio.sample_count() as u64;
+            }
+            self.skip();
+        }
+        Some(StreamDuration { method: DurationMethod::Scan, sample_count, sample_rate })
+    }
+
+    /// Moves to the start and finds the first audio frame, returning its position,
+    /// header, and the position of the Xing/Info header before it if there's one.
+    fn first_audio(&mut self) -> Option<(usize, FrameHeader, Option<XingFrame<'src>>)> {
+        self.set_position(0);
+        let mut xing = None;
+        loop {
+            match self.peek()? {
+                Frame::Audio(audio) => return Some((audio.offset_in_source(), audio.header(), xing)),
+                Frame::Xing { header, source } => xing = Some((self.offset_of(source)?, header)),
+                _ => (),
+            }
+            self.skip()?;
+        }
+    }
+
+    /// Checks whether the stream is CBR, returning the position and header of the first audio frame if so.
+    ///
+    /// Only an `Info` header says so, since a VBR stream can look CBR for as long as
+    /// anyone would care to check, and a wrong guess seeks to the wrong sample.
+    fn detect_cbr(&mut self) -> Option<(usize, FrameHeader)> {
+        let (start, header, xing) = self.first_audio()?;
+        header.bitrate()?;
+        match xing {
+            Some((_, xing)) if !xing.is_vbr() => Some((start, header)),
+            _ => None,
+        }
+    }
+
+    /// Seeks in a CBR stream by calculating where the frame is, since every frame is about the same size.
+    fn seek_cbr(&mut self, sample: u64, start: usize, header: FrameHeader) -> Option<u64> {
+        // some bytes before where the frame should be, so rounding never makes it skip over it
+        const MARGIN: usize = 8;
+
+        let sample_count = header.sample_count() as u64;
+        let frame_bytes = (sample_count * u64::from(header.bitrate()?) * 125) as f64 / f64::from(header.sample_rate());
+        let frame = sample / sample_count;
+        let preroll = frame.min(PREROLL_FRAMES as u64);
+        let position = start + ((frame - preroll) as f64 * frame_bytes) as usize;
+        if position >= self.source_copy.len() {
+            return None;
+        }
+        self.set_position(position.saturating_sub(MARGIN).max(start));
+
+        // the frames are counted through `peek` since the first ones may not decode
+        let mut remaining = preroll;
+        while remaining != 0 {
+            if let Frame::Audio(_) = self.peek()? {
+                remaining -= 1;
+            }
+            self.next()?;
+        }
+        let landed = match self.peek()? {
+            // CAST: Rounded to the nearest frame, whose index is nowhere near the limits of either.
+            Frame::Audio(audio) => ((audio.offset_in_source() - start) as f64 / frame_bytes + 0.5) as u64 * sample_count,
+            _ => return None,
+        };
+        if (landed..landed + sample_count).contains(&sample) {
+            self.trim = (sample - landed) as usize;
+            Some(sample)
+        } else {
+            Some(landed)
+        }
+    }
+
+    /// Seeks by stepping through every frame.
+    fn seek_scan(&mut self, sample: u64) -> Option<u64> {
+        self.set_position(0);
+        let mut preroll = [0; PREROLL_FRAMES];
+        let mut frames = 0;
+        let mut total = 0;
+        loop {
+            let position = self.position();
+            if let Frame::Audio(audio) = self.peek()? {
+                let sample_count = audio.sample_count() as u64;
+                if total + sample_count > sample {
+                    // `peek` doesn't move, so `position` is the frame itself
+                    let start = if frames < PREROLL_FRAMES { preroll[0] } else { preroll[frames % PREROLL_FRAMES] };
+                    self.set_position(start);
+                    while self.position() < position && self.next().is_some() {}
+                    self.trim = (sample - total) as usize;
+                    return Some(sample);
+                }
+                total += sample_count;
+                preroll[frames % PREROLL_FRAMES] = position;
+                frames += 1;
+            }
+            self.skip();
+        }
     }
 
     /// Skips the current frame the decoder is over, if any.
+    ///
+    /// After [`peek`](Self::peek) returns [`Frame::FormatChanged`], which takes up no bytes,
+    /// this doesn't move, so the frame after it is the one that was there.
     pub fn skip(&mut self) -> Option<()> {
         unsafe {
             let offset = match self.cached_peek_len.take() {
-                Some(offset) => offset.get(),
+                Some(offset) => offset.get() - 1,
                 None => self.raw.peek(self.source)?.1,
             };
             self.offset_trusted(offset);
         }
+        self.trim = 0;
         Some(())
     }
 
@@ -270,6 +1201,14 @@ impl<'src> Decoder<'src> {
 impl DecoderOwned<Vec<u8>> {
     /// Constructs a new `DecoderBox` for processing MPEG Audio.
     pub fn new<T>(source: T) -> Self
+    where
+        T: Into<Vec<u8>>,
+    {
+        Self::with_options(source, DecoderOptions::new())
+    }
+
+    /// Constructs a new `DecoderBox` for processing MPEG Audio with the given options.
+    pub fn with_options<T>(source: T, options: DecoderOptions) -> Self
     where
         T: Into<Vec<u8>>,
     {
@@ -282,12 +1221,62 @@ impl DecoderOwned<Vec<u8>> {
         };
 
         Self {
-            decoder: Decoder::new(self_reference),
+            decoder: Decoder::with_options(self_reference, options),
             owned: source,
         }
     }
 }
 
+#[cfg(feature = "mmap")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "mmap")))]
+impl DecoderOwned<memmap2::Mmap> {
+    /// Opens a file for processing MPEG Audio by memory-mapping it,
+    /// so that it's paged in as it's decoded, rather than read into memory up front.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated for as long as the decoder exists,
+    /// including by other processes, as the decoder would see the data change underneath it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::{DecoderOwned, Frame};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // SAFETY: Nothing else writes to the file.
+    /// let mut decoder = unsafe { DecoderOwned::open("test.mp3")? };
+    /// while let Some(frame) = decoder.next() {
+    ///     if let Frame::Audio(audio) = frame {
+    ///         // process audio frame here!
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn open<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Self::open_with_options(path, DecoderOptions::new())
+    }
+
+    /// Opens a file for processing MPEG Audio with the given options by memory-mapping it.
+    ///
+    /// # Safety
+    ///
+    /// See [`open`](Self::open).
+    pub unsafe fn open_with_options<P: AsRef<std::path::Path>>(path: P, options: DecoderOptions) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let map = memmap2::Mmap::map(&file)?;
+
+        // SAFETY: See `DecoderOwned::new`, as the mapping doesn't move along with the `Mmap`.
+        let self_reference = std::mem::transmute::<_, &'static [u8]>(&map[..]);
+
+        Ok(Self {
+            decoder: Decoder::with_options(self_reference, options),
+            owned: map,
+        })
+    }
+}
+
 #[cfg(feature = "std")]
 impl<T: Into<Vec<u8>>> From<T> for DecoderOwned<Vec<u8>> {
     fn from(x: T) -> Self {
@@ -333,6 +1322,14 @@ impl<T> DecoderOwned<T> {
         self.decoder.next()
     }
 
+    /// Reads only every `every`th audio frame, skipping the ones in between.
+    ///
+    /// See [`Decoder::next_sparse`] for more info.
+    #[inline]
+    pub fn next_sparse<'a>(&'a mut self, every: NonZeroUsize) -> Option<Frame<'a, 'a>> {
+        self.decoder.next_sparse(every)
+    }
+
     /// Reads the next frame without decoding it, or advancing the decoder.
     /// Use [`skip`](Self::skip) to advance.
     ///
@@ -343,20 +1340,90 @@ impl<T> DecoderOwned<T> {
         self.decoder.peek()
     }
 
-    /// Gets the current position in the input data, starting from 0.
+    /// Gets the total duration of the stream, without changing the position.
+    ///
+    /// See [`Decoder::duration`] for more info.
     #[inline]
-    pub fn position(&self) -> usize {
-        self.decoder.position()
+    pub fn duration(&mut self) -> Option<StreamDuration> {
+        self.decoder.duration()
     }
 
-    /// Sets the current position in the input data.
-    ///
-    /// If `position` is out of bounds, it's set to the end of the data instead.
+    /// Gets the options the decoder is currently using.
+    #[inline]
+    pub fn options(&self) -> DecoderOptions {
+        self.decoder.options()
+    }
+
+    /// Gets where a slice returned by the decoder, such as [`Frame::Other`],
+    /// starts in the input data, or `None` if it's not from the input data.
+    ///
+    /// For [`Audio`], [`offset_in_source`](Audio::offset_in_source) does the same.
+    #[inline]
+    pub fn offset_of(&self, data: &[u8]) -> Option<usize> {
+        self.decoder.offset_of(data)
+    }
+
+    /// Gets the current position in the input data, starting from 0.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.decoder.position()
+    }
+
+    /// Gets all of the input data, regardless of the position.
+    ///
+    /// See [`Decoder::source`] for more info.
+    #[inline]
+    pub fn source(&self) -> &[u8] {
+        self.decoder.source()
+    }
+
+    /// Gets how many bytes of the input data are left after the current position.
+    ///
+    /// See [`Decoder::remaining`] for more info.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.decoder.remaining()
+    }
+
+    /// Gets whether the end of the input data was reached.
+    ///
+    /// See [`Decoder::is_finished`] for more info.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Sets the current position in the input data.
+    ///
+    /// If `position` is out of bounds, it's set to the end of the data instead.
     #[inline]
     pub fn set_position(&mut self, position: usize) {
         self.decoder.set_position(position)
     }
 
+    /// Sets the options the decoder should use from now on.
+    #[inline]
+    pub fn set_options(&mut self, options: DecoderOptions) {
+        self.decoder.set_options(options)
+    }
+
+    /// Seeks to approximately `seconds` into the stream and decodes the frame there.
+    ///
+    /// See [`Decoder::decode_at`] for more info.
+    #[inline]
+    pub fn decode_at(&mut self, seconds: f64) -> Option<Audio<'_, '_>> {
+        self.decoder.decode_at(seconds)
+    }
+
+    /// Seeks to the frame containing the sample at `sample` (counted per channel),
+    /// so that the next [`Audio`] frame starts exactly at it, returning the sample it starts at.
+    ///
+    /// See [`Decoder::seek_to_sample`] for more info.
+    #[inline]
+    pub fn seek_to_sample(&mut self, sample: u64) -> Option<u64> {
+        self.decoder.seek_to_sample(sample)
+    }
+
     /// Skips the current frame the decoder is over, if any.
     #[inline]
     pub fn skip(&mut self) -> Option<()> {
@@ -367,80 +1434,541 @@ impl<T> DecoderOwned<T> {
 impl RawDecoder {
     /// Constructs a new `RawDecoder` for processing MPEG Audio.
     pub fn new() -> Self {
-        let mut decoder = MaybeUninit::uninit();
-        unsafe {
-            ffi::mp3dec_init(decoder.as_mut_ptr());
+        Self::with_options(DecoderOptions::new())
+    }
+
+    /// Constructs a new `RawDecoder` for processing MPEG Audio with the given options.
+    pub fn with_options(options: DecoderOptions) -> Self {
+        Self {
+            format: None,
+            last_frame: None,
+            non_audio: (0, 0),
+            options,
+            state: Backend::new(),
         }
-        Self(decoder)
+    }
+
+    /// Constructs a `RawDecoder` in place, which is the same as `with_options`.
+    #[cfg(feature = "alloc")]
+    unsafe fn init(this: *mut Self, options: DecoderOptions) {
+        ptr::addr_of_mut!((*this).format).write(None);
+        ptr::addr_of_mut!((*this).last_frame).write(None);
+        ptr::addr_of_mut!((*this).non_audio).write((0, 0));
+        ptr::addr_of_mut!((*this).options).write(options);
+        Backend::init(ptr::addr_of_mut!((*this).state));
+    }
+
+    /// Gets the options the decoder is currently using.
+    #[inline]
+    pub fn options(&self) -> DecoderOptions {
+        self.options
+    }
+
+    /// Sets the options the decoder should use from now on.
+    #[inline]
+    pub fn set_options(&mut self, options: DecoderOptions) {
+        self.options = options;
     }
 
     /// Reads the next frame, skipping over potential garbage data.
     ///
     /// If the frame contains audio data, [`samples`](Audio::samples) should be used
     /// to get the slice, as not all of the `dest` slice may be filled up.
-    #[inline]
-    pub fn next<'src, 'pcm>(
+    ///
+    /// For [`Frame::FormatChanged`], no bytes are consumed, so the same data should be passed again.
+    ///
+    /// `dest` may be smaller than [`MAX_SAMPLES_PER_FRAME`] when the streams are known to be,
+    /// such as 1152 samples for mono MPEG-1 or 1152 * 2 for stereo MPEG-2 Layer III.
+    /// Frames which don't fit are consumed without being decoded and returned as
+    /// [`Error::BufferTooSmall`], which also leaves the bit reservoir short for the next frame.
+    pub fn next<'src, 'pcm, const N: usize>(
+        &mut self,
+        src: &'src [u8],
+        dest: &'pcm mut [Sample; N],
+    ) -> Option<(Frame<'src, 'pcm>, usize)> {
+        unsafe { self.next_into(src, dest.as_mut_ptr(), N, usize::MAX) }
+    }
+
+    /// Reads the next frame into an uninitialized buffer, which is otherwise the same as
+    /// [`next`](Self::next), for not having to zero a buffer before its first use.
+    ///
+    /// Since the buffer isn't known to hold the previous frame, [`Concealment::Repeat`]
+    /// and [`Concealment::FadeOut`] mute instead.
+    pub fn next_uninit<'src, 'pcm, const N: usize>(
         &mut self,
         src: &'src [u8],
-        dest: &'pcm mut [Sample; MAX_SAMPLES_PER_FRAME],
+        dest: &'pcm mut [MaybeUninit<Sample>; N],
     ) -> Option<(Frame<'src, 'pcm>, usize)> {
-        self.call(src, Some(dest))
+        self.last_frame = None;
+        // SAFETY: Samples are only ever read back after they're written, except when repeating.
+        unsafe { self.next_into(src, dest.as_mut_ptr() as *mut Sample, N, usize::MAX) }
+    }
+
+    /// Reads the next frame into `dest`, which is what [`next`](Self::next) does,
+    /// but returning corrupt frames skipped so far as [`Frame::Other`] once there's `max_skip` bytes of them.
+    ///
+    /// `dest` must be valid for writing `capacity` samples.
+    unsafe fn next_into<'src, 'pcm>(
+        &mut self,
+        src: &'src [u8],
+        dest: *mut Sample,
+        capacity: usize,
+        max_skip: usize,
+    ) -> Option<(Frame<'src, 'pcm>, usize)> {
+        let src = src.get_unchecked(..data_len_safe(src.len()) as usize);
+        let mut skipped = 0;
+        loop {
+            let rest = src.get_unchecked(skipped..);
+            match self.decode(rest, dest, capacity) {
+                Some((Frame::Error(Error::Corrupt), len)) if self.options.corrupt_policy == CorruptPolicy::Skip => {
+                    skipped += len;
+                    if skipped >= max_skip {
+                        return Some(self.limit_non_audio(Frame::Other(src.get_unchecked(..skipped)), skipped));
+                    }
+                },
+                Some((mut frame, len)) => {
+                    if let Frame::Audio(audio) = &mut frame {
+                        audio.offset += skipped;
+                    }
+                    return Some(self.limit_non_audio(frame, skipped + len));
+                },
+                None if skipped == 0 => return None,
+                None => return Some(self.limit_non_audio(Frame::Other(src.get_unchecked(..skipped)), skipped)),
+            }
+        }
+    }
+
+    /// Counts the frames and bytes in a row without audio,
+    /// replacing the frame with [`Error::NoAudio`] once either limit is reached.
+    fn limit_non_audio<'src, 'pcm>(&mut self, frame: Frame<'src, 'pcm>, len: usize) -> (Frame<'src, 'pcm>, usize) {
+        match frame {
+            Frame::Audio(_) => self.non_audio = (0, 0),
+            Frame::FormatChanged { .. } => (),
+            _ => {
+                let (frames, bytes) = (self.non_audio.0 + 1, self.non_audio.1 + len);
+                let reached = |limit: Option<usize>, count: usize| matches!(limit, Some(limit) if count >= limit);
+                if reached(self.options.max_non_audio_frames, frames) || reached(self.options.max_non_audio_bytes, bytes) {
+                    #[cfg(feature = "log")]
+                    log::warn!("no audio in {} frames, or {} bytes", frames, bytes);
+                    self.non_audio = (0, 0);
+                    return (Frame::Error(Error::NoAudio), len);
+                }
+                self.non_audio = (frames, bytes);
+            },
+        }
+        (frame, len)
+    }
+
+    /// Decodes every frame in `src`, calling `f` with each one until it returns
+    /// [`ControlFlow::Break`], and returns how many bytes were consumed.
+    ///
+    /// This takes care of advancing through `src` and of the output buffer, which lives on the stack,
+    /// so it's the same as calling [`next`](Self::next) in a loop. If stopped early,
+    /// decoding can be continued from `&src[consumed..]`, which is right after the frame `f` stopped at.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use core::ops::ControlFlow;
+    /// use rmp3::{Frame, RawDecoder};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut sample_count = 0;
+    /// RawDecoder::new().decode_all(&mp3, |frame| {
+    ///     if let Frame::Audio(audio) = frame {
+    ///         sample_count += audio.sample_count();
+    ///     }
+    ///     ControlFlow::Continue(())
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decode_all<'src, F>(&mut self, src: &'src [u8], mut f: F) -> usize
+    where
+        F: for<'pcm> FnMut(Frame<'src, 'pcm>) -> ControlFlow<()>,
+    {
+        let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+        let mut consumed = 0;
+        while let Some((frame, len)) = self.next(unsafe { src.get_unchecked(consumed..) }, &mut pcm) {
+            consumed += len;
+            if let ControlFlow::Break(()) = f(frame) {
+                break;
+            }
+        }
+        consumed
+    }
+
+    /// Decodes one frame which is already known to be valid, without searching for it
+    /// or checking it beforehand, for re-decoding streams which were indexed before.
+    ///
+    /// This skips syncing, the format policy, concealment and the non-audio limits,
+    /// so unlike [`next`](Self::next) it never returns [`Frame::FormatChanged`], [`Frame::Xing`] or
+    /// [`Error::NoAudio`]. A frame which doesn't decode, such as one with a starved bit reservoir,
+    /// is returned as [`Frame::Other`]. [`mono`](DecoderOptions::mono), [`energy`](DecoderOptions::energy)
+    /// and the options which only look at the header still apply.
+    ///
+    /// Frames still have to be given in order, as the bit reservoir carries over between them.
+    ///
+    /// # Safety
+    ///
+    /// `frame` has to be exactly one whole audio frame, starting with its header,
+    /// such as the [`source`](Audio::source) of a frame which was peeked at on the same data before.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::{Decoder, Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mp3 = std::fs::read("test.mp3")?;
+    /// let mut frames = Vec::new();
+    /// let mut peeker = Decoder::new(&mp3);
+    /// while let Some(frame) = peeker.peek() {
+    ///     if let Frame::Audio(audio) = frame {
+    ///         frames.push(audio.source());
+    ///     }
+    ///     peeker.skip();
+    /// }
+    ///
+    /// // later, as many times as it's needed
+    /// let mut decoder = RawDecoder::new();
+    /// let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+    /// for &frame in &frames {
+    ///     if let Frame::Audio(audio) = unsafe { decoder.decode_unchecked(frame, &mut pcm) } {
+    ///         // process audio frame here!
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn decode_unchecked<'src, 'pcm, const N: usize>(
+        &mut self,
+        frame: &'src [u8],
+        dest: &'pcm mut [Sample; N],
+    ) -> Frame<'src, 'pcm> {
+        let header = FrameHeader::from_frame_unchecked(frame);
+        let channels = self.options.output_layout.channels().unwrap_or(0).max(header.channels());
+        if header.sample_count() * channels as usize > N {
+            return Frame::Error(Error::BufferTooSmall);
+        }
+        // minimp3 only clears the rest of its state when it has no header yet,
+        // which it won't check for once one is given to it
+        if self.state.header().is_none() {
+            self.state.reset();
+        }
+        self.state.set_header(header);
+        let mut info = MaybeUninit::zeroed().assume_init();
+        let sample_count = self.state.decode(frame, dest.as_mut_ptr(), &mut info);
+        match self.options.output_layout.channels() {
+            Some(channels) if channels as c_int != info.channels => {
+                info.channels = channels as c_int;
+                conform_channels(dest.as_mut_ptr(), sample_count, channels);
+            },
+            _ => (),
+        }
+        if sample_count != 0 {
+            self.last_frame = Some((sample_count, info.channels as u16));
+        }
+
+        match self.frame(frame, info, sample_count, ptr::NonNull::new(dest.as_mut_ptr())) {
+            Some((Frame::Audio(mut audio), _)) => {
+                if self.options.energy {
+                    audio.energy = Some(energy(audio.samples()));
+                }
+                Frame::Audio(audio)
+            },
+            Some((frame, _)) => frame,
+            None => Frame::Other(frame),
+        }
+    }
+
+    /// Decodes the next frame into `dest`, which is what [`next`](Self::next) does
+    /// other than skipping corrupt frames.
+    ///
+    /// `dest` must be valid for writing `capacity` samples.
+    unsafe fn decode<'src, 'pcm>(
+        &mut self,
+        src: &'src [u8],
+        dest_ptr: *mut Sample,
+        capacity: usize,
+    ) -> Option<(Frame<'src, 'pcm>, usize)> {
+        #[cfg(feature = "log")]
+        let synced = self.state.header().is_some();
+        let (offset, size) = match self.sync(src) {
+            Ok(frame) => frame,
+            Err(other) => {
+                #[cfg(feature = "log")]
+                match &other {
+                    Some((Frame::Error(Error::NoSync), len)) => log::warn!("no frame found within {} bytes", len),
+                    Some((Frame::Other(_), len)) => log::debug!("skipping {} bytes of non-audio data", len),
+                    _ => (),
+                }
+                return other;
+            },
+        };
+        #[cfg(feature = "log")]
+        match offset {
+            0 => (),
+            _ if synced => log::warn!("lost sync, resynchronized after {} bytes of garbage", offset),
+            _ => log::debug!("skipping {} bytes of garbage before a frame", offset),
+        }
+        if let Some(event) = self.check_format(src.get_unchecked(offset..)) {
+            return Some((event, 0));
+        }
+        let frame = src.get_unchecked(offset..offset + size);
+        let header = FrameHeader::from_frame_unchecked(frame);
+        // conforming stereo to mono happens in place after decoding both channels
+        let channels = match (self.options.output_layout.channels(), self.options.format_policy, self.format) {
+            (Some(channels), ..) | (None, FormatPolicy::Conform, Some((channels, _))) => channels.max(header.channels()),
+            _ => header.channels(),
+        };
+        if header.sample_count() * channels as usize > capacity {
+            #[cfg(feature = "log")]
+            log::warn!("frame of {} samples doesn't fit a buffer of {}", header.sample_count() * channels as usize, capacity);
+            return Some((Frame::Error(Error::BufferTooSmall), offset + size));
+        }
+        let mut info = MaybeUninit::zeroed().assume_init();
+        let mut sample_count = self.state.decode(frame, dest_ptr, &mut info);
+        // the backend resets itself on corrupt data, but not when the bit reservoir is starved
+        let corrupt = sample_count == 0 && self.state.header().is_none();
+        info.frame_offset += offset as c_int;
+        info.frame_bytes += offset as c_int;
+
+        let concealed = sample_count == 0 && self.options.concealment != Concealment::None;
+        if concealed {
+            // this frame was found, but couldn't be decoded
+            sample_count = header.sample_count();
+            let channels = match self.conform_channels(&mut info) {
+                Some(channels) => channels,
+                None => header.channels(),
+            };
+            let repeat = self.last_frame == Some((sample_count, channels));
+            conceal(dest_ptr, sample_count * channels as usize, self.options.concealment, repeat);
+            #[cfg(feature = "log")]
+            match corrupt {
+                true => log::warn!("concealed a corrupt frame of {} bytes", size),
+                false => log::debug!("concealed a frame of {} bytes with a starved bit reservoir", size),
+            }
+        } else if corrupt {
+            #[cfg(feature = "log")]
+            log::warn!("corrupt frame of {} bytes", size);
+            if self.options.corrupt_policy != CorruptPolicy::Other {
+                return Some((Frame::Error(Error::Corrupt), info.frame_bytes as usize));
+            }
+        } else if let Some(channels) = self.conform_channels(&mut info) {
+            conform_channels(dest_ptr, sample_count, channels);
+        }
+        if sample_count != 0 {
+            self.last_frame = Some((sample_count, info.channels as u16));
+        }
+
+        let mut frame = self.frame(src, info, sample_count, ptr::NonNull::new(dest_ptr));
+        match &mut frame {
+            Some((Frame::Audio(audio), _)) => {
+                audio.concealed = concealed;
+                if self.options.energy {
+                    audio.energy = Some(energy(audio.samples()));
+                }
+            },
+            #[cfg(feature = "log")]
+            Some((Frame::Error(Error::ReservedValue), _)) => {
+                log::warn!("frame header {:02X?} uses reserved values", FrameHeader::from_frame_unchecked(src.get_unchecked(offset..)).bytes())
+            },
+            _ => (),
+        }
+        frame
     }
 
     /// Reads the next frame without decoding it.
     ///
     /// This means that the samples will always be empty in [`Audio`],
     /// and [`sample_count`](Audio::sample_count) should be used to inspect the length.
-    #[inline]
+    ///
+    /// Only the frame headers are looked at, entirely in Rust,
+    /// so this is much faster than [`next`](Self::next) for tasks like calculating the length.
+    ///
+    /// For [`Frame::FormatChanged`], no bytes are consumed, so the same data should be passed again.
     pub fn peek<'src>(&mut self, src: &'src [u8]) -> Option<(Frame<'src, 'static>, usize)> {
-        self.call(src, None)
+        let src = unsafe { src.get_unchecked(..data_len_safe(src.len()) as usize) };
+        match self.sync(src) {
+            Ok((offset, size)) => unsafe {
+                if let Some(event) = self.check_format(src.get_unchecked(offset..)) {
+                    return Some((event, 0));
+                }
+                let header = FrameHeader::from_frame_unchecked(src.get_unchecked(offset..));
+                let mut info = ffi::mp3dec_frame_info_t {
+                    frame_bytes: (offset + size) as c_int,
+                    frame_offset: offset as c_int,
+                    channels: header.channels() as c_int,
+                    hz: header.sample_rate() as c_int,
+                    layer: header.mpeg_layer() as c_int,
+                    bitrate_kbps: header.bitrate().unwrap_or(0) as c_int,
+                };
+                self.conform_channels(&mut info);
+                self.frame(src, info, header.sample_count(), None)
+            },
+            Err(other) => other,
+        }
     }
 
-    fn call<'src, 'pcm>(
-        &mut self,
+    /// Finds the next frame, returning its offset and length,
+    /// or what to return instead if there's none.
+    ///
+    /// This is what `mp3dec_decode_frame` does up until it would start decoding,
+    /// so that peeking and decoding always agree on where frames are.
+    fn sync<'src>(&mut self, src: &'src [u8]) -> Result<(usize, usize), Option<(Frame<'src, 'static>, usize)>> {
+        let mut offset = 0;
+        let mut frame_size = 0;
+
+        // fast path: the previous frame's header is remembered, and this one fits right after it
+        if let Some(previous) = self.state.header().filter(|_| src.len() > 4) {
+            if let Some(header) = header::read(src).filter(|x| previous.matches(x)) {
+                frame_size = header.frame_bytes_or(self.state.free_format_bytes());
+                let next = src.get(frame_size..).and_then(header::read);
+                let followed = matches!(next, Some(x) if header.matches(&x));
+                if frame_size != src.len() && !followed {
+                    frame_size = 0;
+                }
+            }
+        }
+
+        if frame_size == 0 {
+            self.state.reset();
+            let mut max_offset = self.options.scan_limit.unwrap_or(usize::MAX);
+            // scanning further couldn't find anything before the non-audio limit is reached anyway
+            if let Some(limit) = self.options.max_non_audio_bytes {
+                max_offset = max_offset.min(limit.saturating_sub(self.non_audio.1));
+            }
+            let mut free_format_bytes = 0;
+            let (frame_offset, size) = header::find_frame(src, &mut free_format_bytes, self.options.sync_matches, max_offset);
+            self.state.set_free_format_bytes(free_format_bytes);
+            if size == 0 && frame_offset < src.len() {
+                return Err(Some((Frame::Error(Error::NoSync), frame_offset)));
+            } else if size == 0 || frame_offset + size > src.len() {
+                return Err(match frame_offset {
+                    0 => None,
+                    _ => Some((Frame::Other(unsafe { src.get_unchecked(..frame_offset) }), frame_offset)),
+                });
+            }
+            offset = frame_offset;
+            frame_size = size;
+        }
+
+        let source = unsafe { src.get_unchecked(offset..offset + frame_size) };
+        self.state.set_header(unsafe { FrameHeader::from_frame_unchecked(source) });
+        if let Some(header) = XingHeader::parse(source) {
+            return Err(Some((Frame::Xing { header, source }, offset + frame_size)));
+        }
+        Ok((offset, frame_size))
+    }
+
+    /// Checks whether an upcoming frame's format should be announced first, according to the format policy.
+    fn check_format(&mut self, frame: &[u8]) -> Option<Frame<'static, 'static>> {
+        let header = unsafe { FrameHeader::from_frame_unchecked(frame) };
+        let channels = self.options.output_layout.channels().unwrap_or_else(|| header.channels());
+        let sample_rate = header.sample_rate();
+        match (self.options.format_policy, self.format) {
+            (FormatPolicy::PassThrough, _) => None,
+            (_, None) => {
+                self.format = Some((channels, sample_rate));
+                None
+            },
+            (FormatPolicy::Notify, Some(format)) if format != (channels, sample_rate) => {
+                self.format = Some((channels, sample_rate));
+                Some(Frame::FormatChanged { channels, sample_rate })
+            },
+            (FormatPolicy::Conform, Some((channels, rate))) if rate != sample_rate => {
+                self.format = Some((channels, sample_rate));
+                Some(Frame::FormatChanged { channels, sample_rate })
+            },
+            _ => None,
+        }
+    }
+
+    /// Changes the channel count of a frame to the output layout's, or the first-seen one if conforming,
+    /// returning the new channel count if it changed.
+    fn conform_channels(&self, info: &mut ffi::mp3dec_frame_info_t) -> Option<u16> {
+        match (self.options.output_layout.channels(), self.options.format_policy, self.format) {
+            (Some(channels), ..) | (None, FormatPolicy::Conform, Some((channels, _))) if channels as c_int != info.channels => {
+                info.channels = channels as c_int;
+                Some(channels)
+            },
+            _ => None,
+        }
+    }
+
+    unsafe fn frame<'src, 'pcm>(
+        &self,
         src: &'src [u8],
-        dest: Option<&'pcm mut [Sample; MAX_SAMPLES_PER_FRAME]>,
+        info: ffi::mp3dec_frame_info_t,
+        sample_count: usize,
+        pcm: Option<ptr::NonNull<Sample>>,
     ) -> Option<(Frame<'src, 'pcm>, usize)> {
-        let src_length = data_len_safe(src.len());
-        let dest_ptr: *mut Sample = dest.map_or(ptr::null_mut(), |x| x).cast();
-        unsafe {
-            let mut info = MaybeUninit::uninit().assume_init();
-            let result = ffi::mp3dec_decode_frame(
-                self.0.as_mut_ptr(),
-                src.as_ptr(),
-                src_length,
-                dest_ptr,
-                &mut info,
-            );
-            let skip = info.frame_bytes as usize;
-
-            if result != 0 {
-                Some((
-                    Frame::Audio(Audio {
-                        info,
-                        pcm: ptr::NonNull::new(dest_ptr),
-                        sample_count: result as usize,
-                        source: source_slice(src, &info),
-                        phantom: PhantomData,
-                    }),
-                    skip,
-                ))
-            } else if info.frame_bytes != 0 {
-                Some((Frame::Other(source_slice(src, &info)), skip))
-            } else {
-                None
+        let skip = info.frame_bytes as usize;
+
+        if self.options.strict && sample_count != 0 {
+            let header = FrameHeader::from_frame_unchecked(source_slice(src, &info));
+            if header.has_reserved_values() {
+                return Some((Frame::Error(Error::ReservedValue), skip));
             }
         }
+
+        // free-format frames are the only ones without a bitrate
+        if sample_count != 0 && (self.options.free_format || info.bitrate_kbps != 0) {
+            Some((
+                Frame::Audio(Audio {
+                    info,
+                    concealed: false,
+                    energy: None,
+                    garbage: src.get_unchecked(..info.frame_offset as usize),
+                    offset: info.frame_offset as usize,
+                    pcm,
+                    sample_count,
+                    source: source_slice(src, &info),
+                    phantom: PhantomData,
+                }),
+                skip,
+            ))
+        } else {
+            Some((Frame::Other(source_slice(src, &info)), skip))
+        }
     }
 }
 
 impl<'src, 'pcm> Audio<'src, 'pcm> {
     /// Gets the bitrate of this frame in kb/s.
+    ///
+    /// Free-format frames don't store their bitrate, so this is 0 for them.
+    /// See [`is_free_format`](Self::is_free_format) for more info.
     #[inline]
     pub fn bitrate(&self) -> u32 {
         self.info.bitrate_kbps as u32
     }
 
+    /// Gets an iterator over the samples of one channel in this frame, counting from zero,
+    /// without deinterleaving them into a buffer.
+    ///
+    /// Like [`samples`](Self::samples), this is empty if the frame was `peek`ed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` isn't less than [`channels`](Self::channels).
+    #[inline]
+    pub fn channel(&self, index: usize) -> impl ExactSizeIterator<Item = Sample> + 'pcm {
+        let channels = self.info.channels as usize;
+        assert!(index < channels, "channel {} out of range for {} channels", index, channels);
+        self.samples().get(index..).unwrap_or(&[]).iter().copied().step_by(channels)
+    }
+
+    /// Gets the channel mode of this frame.
+    ///
+    /// Unlike [`channels`](Self::channels), this tells apart joint stereo and dual channel frames.
+    #[inline]
+    pub fn channel_mode(&self) -> ChannelMode {
+        self.header().channel_mode()
+    }
+
     /// Gets the channel count of this frame.
     #[inline]
     pub fn channels(&self) -> u16 {
@@ -449,6 +1977,121 @@ impl<'src, 'pcm> Audio<'src, 'pcm> {
         self.info.channels as u16
     }
 
+    /// Gets whether the copyright bit is set in this frame.
+    #[inline]
+    pub fn copyright(&self) -> bool {
+        self.header().copyright()
+    }
+
+    /// Gets whether this frame is protected by a CRC-16 checksum.
+    ///
+    /// Use [`verify_crc`](Self::verify_crc) to check it.
+    #[inline]
+    pub fn has_crc(&self) -> bool {
+        self.header().has_crc()
+    }
+
+    /// Verifies the CRC-16 checksum of this frame, returning whether it matched.
+    ///
+    /// Returns `None` if the frame has no checksum (see [`has_crc`](Self::has_crc)),
+    /// or if it's a layer II frame, whose checksum isn't currently supported.
+    pub fn verify_crc(&self) -> Option<bool> {
+        if !self.has_crc() {
+            return None;
+        }
+
+        // layer II isn't supported, while anything else failing means the frame is too short
+        self.header().crc_bytes()?;
+        match frame_crc(self.source) {
+            Some(crc) => Some(crc == u16::from_be_bytes([self.source[4], self.source[5]])),
+            None => Some(false),
+        }
+    }
+
+    /// Gets whether the samples of this frame were made up because it couldn't be decoded,
+    /// according to the [`Concealment`] mode.
+    #[inline]
+    pub fn is_concealed(&self) -> bool {
+        self.concealed
+    }
+
+    /// Gets the mean square of the samples of this frame, where 1.0 is full scale,
+    /// if [`DecoderOptions::energy`] is enabled.
+    ///
+    /// This is a cheap loudness value for visualizers and silence detection, measured over
+    /// the whole decoded frame, including any samples a [`Decoder`] trims off after seeking to a sample.
+    /// It's `None` if the frame was `peek`ed.
+    #[inline]
+    pub fn energy(&self) -> Option<f32> {
+        self.energy
+    }
+
+    /// Gets the emphasis of this frame.
+    #[inline]
+    pub fn emphasis(&self) -> Emphasis {
+        self.header().emphasis()
+    }
+
+    /// Gets the size in bytes of this frame if it's free-format, excluding the [`padding`](Self::padding).
+    ///
+    /// Free-format streams don't store their bitrate and instead have a fixed frame size,
+    /// which minimp3 detects by finding the distance between sync words.
+    /// See [`is_free_format`](Self::is_free_format) for more info.
+    #[inline]
+    pub fn free_format_bytes(&self) -> Option<usize> {
+        let header = self.header();
+        if header.is_free_format() {
+            Some(self.source.len() - header.padding_bytes())
+        } else {
+            None
+        }
+    }
+
+    /// Gets the garbage data which was skipped over right before this frame,
+    /// which is empty in a clean stream.
+    ///
+    /// Its length can be used to tell how corrupted a stream is,
+    /// since minimp3 otherwise skips over it silently.
+    #[inline]
+    pub fn garbage(&self) -> &'src [u8] {
+        self.garbage
+    }
+
+    /// Gets the header of this frame, which was already validated by the decoder.
+    #[inline]
+    pub fn header(&self) -> FrameHeader {
+        // SAFETY: The source slice of an audio frame always starts with its 4-byte header.
+        unsafe { FrameHeader::from_frame_unchecked(self.source) }
+    }
+
+    /// Gets whether this frame is free-format, as in, has an unspecified bitrate.
+    ///
+    /// Free-format streams are rare, and allowed to exceed the usual bitrate limits.
+    /// They can be rejected with [`DecoderOptions::free_format`].
+    #[inline]
+    pub fn is_free_format(&self) -> bool {
+        self.header().is_free_format()
+    }
+
+    /// Gets an iterator over the samples of the left channel in this frame,
+    /// which is the only channel of mono frames.
+    ///
+    /// See [`channel`](Self::channel) for more info.
+    #[inline]
+    pub fn left(&self) -> impl ExactSizeIterator<Item = Sample> + 'pcm {
+        self.channel(0)
+    }
+
+    /// Gets the raw 2-bit mode extension of this frame,
+    /// which is only meaningful for [`JointStereo`](ChannelMode::JointStereo) frames.
+    ///
+    /// For layer III, bit 0 (`0b01`) signals intensity stereo and bit 1 (`0b10`) mid-side stereo.
+    /// For layers I & II, it selects the subband from which intensity stereo begins (4, 8, 12 or 16).
+    #[inline]
+    pub fn mode_extension(&self) -> u8 {
+        self.header().mode_extension()
+    }
+
     /// Gets the MPEG layer of this frame.
     #[inline]
     pub fn mpeg_layer(&self) -> u8 {
@@ -457,6 +2100,46 @@ impl<'src, 'pcm> Audio<'src, 'pcm> {
         self.info.layer as u8
     }
 
+    /// Gets whether the original bit is set in this frame (as opposed to it being a copy).
+    #[inline]
+    pub fn original(&self) -> bool {
+        self.header().original()
+    }
+
+    /// Gets whether this frame is padded with an extra slot
+    /// (one byte, or four bytes for layer I).
+    #[inline]
+    pub fn padding(&self) -> bool {
+        self.header().padding()
+    }
+
+    /// Gets whether the private bit is set in this frame.
+    ///
+    /// This bit has no meaning in the standard and is free for applications to use.
+    #[inline]
+    pub fn private(&self) -> bool {
+        self.header().private()
+    }
+
+    /// Gets an iterator over the samples of the right channel in this frame,
+    /// which is the only channel of mono frames.
+    ///
+    /// See [`channel`](Self::channel) for more info.
+    #[inline]
+    pub fn right(&self) -> impl ExactSizeIterator<Item = Sample> + 'pcm {
+        self.channel(if self.info.channels == 1 { 0 } else { 1 })
+    }
+
+    /// Gets where the [`source`](Self::source) slice of this frame starts
+    /// in the input data, in bytes.
+    ///
+    /// For a [`Decoder`], this is from the start of all its data, regardless of the position.
+    /// For a [`RawDecoder`], it's from the start of the data that was passed in.
+    #[inline]
+    pub fn offset_in_source(&self) -> usize {
+        self.offset
+    }
+
     /// Gets the sample rate of this frame in Hz.
     #[inline]
     pub fn sample_rate(&self) -> u32 {
@@ -473,19 +2156,47 @@ impl<'src, 'pcm> Audio<'src, 'pcm> {
     pub fn samples(&self) -> &'pcm [Sample] {
         match self.pcm {
             Some(ptr) => unsafe {
-                (&*ptr.cast::<[Sample; MAX_SAMPLES_PER_FRAME]>().as_ptr())
-                    .get_unchecked(..self.sample_count * self.info.channels as usize)
+                core::slice::from_raw_parts(ptr.as_ptr(), self.sample_count * self.info.channels as usize)
             },
             None => &[],
         }
     }
 
+    /// Copies the samples in this frame into one slice per channel, deinterleaving them,
+    /// and returns how many were written to each, which is as many as fit in both.
+    ///
+    /// Mono frames are copied to both slices, like [`right`](Self::right) repeats the left channel.
+    /// Like [`samples`](Self::samples), nothing is written if this frame was `peek`ed.
+    pub fn samples_planar(&self, left: &mut [Sample], right: &mut [Sample]) -> usize {
+        let samples = self.samples();
+        let len = (samples.len() / self.info.channels as usize).min(left.len()).min(right.len());
+        if self.info.channels == 1 {
+            left[..len].copy_from_slice(&samples[..len]);
+            right[..len].copy_from_slice(&samples[..len]);
+        } else {
+            for ((left, right), frame) in left.iter_mut().zip(right.iter_mut()).zip(samples.chunks_exact(2)) {
+                *left = frame[0];
+                *right = frame[1];
+            }
+        }
+        len
+    }
+
     /// Gets the sample count per [`channel`](Self::channels).
     #[inline]
     pub fn sample_count(&self) -> usize {
         self.sample_count
     }
 
+    /// Gets how long this frame plays for, as in, the [`sample_count`](Self::sample_count)
+    /// (which is per channel) over the [`sample_rate`](Self::sample_rate).
+    ///
+    /// This is computed without floating point, rounded down to the nanosecond.
+    #[inline]
+    pub fn duration(&self) -> core::time::Duration {
+        duration::to_duration(self.sample_count as u64, self.sample_rate())
+    }
+
     /// Gets the source slice with potential garbage stripped.
     #[inline]
     pub fn source(&self) -> &'src [u8] {
@@ -493,8 +2204,281 @@ impl<'src, 'pcm> Audio<'src, 'pcm> {
     }
 }
 
+impl<'src> Frame<'src, '_> {
+    /// Gets the slice of input data this frame was read from, if any,
+    /// which is `None` for [`Error`](Self::Error) and [`FormatChanged`](Self::FormatChanged).
+    ///
+    /// [`Decoder::offset_of`] gets where it starts in all of the input data.
+    #[inline]
+    pub fn source(&self) -> Option<&'src [u8]> {
+        match self {
+            Self::Audio(audio) => Some(audio.source()),
+            Self::Other(data) => Some(data),
+            Self::Xing { source, .. } => Some(source),
+            Self::Error(_) | Self::FormatChanged { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReservedValue => f.write_str("frame header uses a reserved value"),
+            Self::NoSync => f.write_str("no frame found within the scan limit"),
+            Self::Corrupt => f.write_str("frame data is corrupt"),
+            Self::BufferTooSmall => f.write_str("output buffer is too small for the frame"),
+            Self::NoAudio => f.write_str("no audio found within the non-audio limit"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// Only the metadata is logged, as the samples and source data would be far too much.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Audio<'_, '_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Audio {{ header: {}, sample_count: {}, offset: {}, concealed: {} }}",
+            self.header(),
+            self.sample_count,
+            self.offset,
+            self.concealed,
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Frame<'_, '_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Audio(audio) => defmt::write!(f, "Audio({})", audio),
+            Self::Other(data) => defmt::write!(f, "Other({} bytes)", data.len()),
+            Self::Error(error) => defmt::write!(f, "Error({})", error),
+            Self::Xing { header, source } => {
+                defmt::write!(f, "Xing {{ header: {}, source: {} bytes }}", header, source.len())
+            },
+            Self::FormatChanged { channels, sample_rate } => {
+                defmt::write!(f, "FormatChanged {{ channels: {}, sample_rate: {} }}", channels, sample_rate)
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::{Sample, MAX_SAMPLES_PER_FRAME};
+
+    #[test]
+    fn channel() {
+        let mut pcm = [1i16, 2, 3, 4, 5, 6].map(Sample::from);
+        let pcm = core::ptr::NonNull::new(pcm.as_mut_ptr());
+        let audio = |channels| crate::Audio::<'_, '_> {
+            info: crate::ffi::mp3dec_frame_info_t {
+                frame_bytes: 0,
+                frame_offset: 0,
+                channels,
+                hz: 44100,
+                layer: 3,
+                bitrate_kbps: 128,
+            },
+            concealed: false,
+            energy: None,
+            pcm,
+            sample_count: 6 / channels as usize,
+            source: &[],
+            offset: 0,
+            garbage: &[],
+            phantom: core::marker::PhantomData,
+        };
+
+        let stereo = audio(2);
+        assert!(stereo.left().eq([1i16, 3, 5].map(Sample::from)));
+        assert!(stereo.right().eq([2i16, 4, 6].map(Sample::from)));
+        let mono = audio(1);
+        assert_eq!(mono.right().len(), 6);
+        assert!(mono.left().eq(mono.right()));
+
+        let (mut left, mut right) = ([Sample::default(); 2], [Sample::default(); 4]);
+        assert_eq!(stereo.samples_planar(&mut left, &mut right), 2);
+        assert_eq!((left, right), ([1i16, 3].map(Sample::from), [2i16, 4, 0, 0].map(Sample::from)));
+        assert_eq!(mono.samples_planar(&mut right, &mut left), 2);
+        assert_eq!((left, right), ([1i16, 2].map(Sample::from), [1i16, 2, 0, 0].map(Sample::from)));
+    }
+
+    #[test]
+    fn conform_channels() {
+        fn pcm(samples: &[i16]) -> [Sample; MAX_SAMPLES_PER_FRAME] {
+            let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+            for (dest, &sample) in pcm.iter_mut().zip(samples) {
+                *dest = Sample::from(sample);
+            }
+            pcm
+        }
+
+        let mut mono = pcm(&[2, 4, 6]);
+        unsafe { crate::conform_channels(mono.as_mut_ptr(), 3, 2) };
+        assert_eq!(mono[..6], pcm(&[2, 2, 4, 4, 6, 6])[..6]);
+
+        let mut stereo = pcm(&[2, 4, 6, 8]);
+        unsafe { crate::conform_channels(stereo.as_mut_ptr(), 2, 1) };
+        assert_eq!(stereo[..2], pcm(&[3, 7])[..2]);
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn decode_append_doesnt_conceal_with_storage() {
+        let mp3 = crate::test_util::FrameBuilder::new().stream(3).unwrap();
+        let mut decoder = crate::Decoder::new(&mp3);
+        let mut pcm = alloc::vec::Vec::new();
+        assert!(matches!(decoder.decode_append(&mut pcm), Some(crate::Frame::Audio(_))));
+        // the next frame would be concealed with the decoder's own storage, which was never written
+        assert_eq!(decoder.raw.last_frame, None);
+        assert!(matches!(decoder.next(), Some(crate::Frame::Audio(_))));
+        assert!(decoder.raw.last_frame.is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn next_limited() {
+        use crate::test_util::FrameBuilder;
+        use core::task::Poll;
+        let mut mp3 = [0u8; 10_000].to_vec();
+        mp3.extend(FrameBuilder::new().stream(5).unwrap());
+        let (mut pending, mut audio) = (0, 0);
+        let mut decoder = crate::Decoder::new(&mp3);
+        loop {
+            match decoder.next_limited(4096) {
+                Poll::Ready(Some(crate::Frame::Audio(_))) => audio += 1,
+                Poll::Ready(Some(_)) => panic!("expected only audio"),
+                Poll::Ready(None) => break,
+                Poll::Pending => pending += 1,
+            }
+        }
+        assert_eq!((pending, audio), (2, 5));
+
+        // the scan limit counts across the pending calls
+        let options = crate::DecoderOptions::new().scan_limit(Some(6000));
+        let mut decoder = crate::Decoder::with_options(&mp3, options);
+        assert!(decoder.next_limited(4096).is_pending());
+        assert!(matches!(decoder.next_limited(4096), Poll::Ready(Some(crate::Frame::Error(crate::Error::NoSync)))));
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn next_limited_caps_skipping() {
+        use crate::{options::CorruptPolicy, test_util::FrameBuilder};
+        use core::task::Poll;
+        let mut mp3 = FrameBuilder::new().stream(10).unwrap();
+        for frame in mp3.chunks_mut(417) {
+            // a big_values count past the end of the spectrum, which minimp3 rejects
+            frame[8] = 0xFF;
+            frame[9] |= 0x80;
+        }
+        let options = crate::DecoderOptions::new().corrupt_policy(CorruptPolicy::Skip);
+        let mut decoder = crate::Decoder::with_options(&mp3, options);
+        match decoder.next_limited(1000) {
+            Poll::Ready(Some(crate::Frame::Other(skipped))) => assert_eq!(skipped.len(), 417 * 3),
+            _ => panic!("expected the skipped frames"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn skip_after_format_changed() {
+        use crate::{options::FormatPolicy, test_util::FrameBuilder, ChannelMode};
+        let mut mp3 = FrameBuilder::new().stream(3).unwrap();
+        mp3.extend(FrameBuilder::new().channel_mode(ChannelMode::Mono).stream(3).unwrap());
+        let options = crate::DecoderOptions::new().format_policy(FormatPolicy::Notify);
+        let mut decoder = crate::Decoder::with_options(&mp3, options);
+        let (mut audio, mut changes) = (0, 0);
+        while let Some(frame) = decoder.peek() {
+            match frame {
+                crate::Frame::Audio(_) => audio += 1,
+                crate::Frame::FormatChanged { .. } => changes += 1,
+                _ => (),
+            }
+            decoder.skip();
+        }
+        assert_eq!((audio, changes), (6, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn seek_vbr_starting_like_cbr() {
+        use crate::test_util::FrameBuilder;
+        let mut mp3 = FrameBuilder::new().stream(10).unwrap();
+        let small = FrameBuilder::new().bitrate(32).build().unwrap();
+        mp3.extend(FrameBuilder::new().bitrate(32).stream(20).unwrap());
+        let mut decoder = crate::Decoder::new(&mp3);
+        decoder.seek_to_sample(1152 * 25 + 100).unwrap();
+        match decoder.peek() {
+            Some(crate::Frame::Audio(audio)) => assert_eq!(audio.offset_in_source(), 417 * 10 + small.len() * 15),
+            _ => panic!("expected audio"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn duration_keeps_state() {
+        let mp3 = crate::test_util::FrameBuilder::new().stream(3).unwrap();
+        let mut decoder = crate::Decoder::new(&mp3);
+        assert!(matches!(decoder.next(), Some(crate::Frame::Audio(_))));
+        let header = crate::Backend::header(&decoder.raw.state);
+        assert_eq!(decoder.duration().map(|x| x.sample_count()), Some(3 * 1152));
+        // the next frame carries on from the previous one, rather than from a reset
+        assert_eq!(crate::Backend::header(&decoder.raw.state), header);
+        assert!(header.is_some());
+        assert_eq!(decoder.position(), 417);
+    }
+
+    #[test]
+    fn max_non_audio_bytes() {
+        let garbage = [0u8; 1 << 16];
+        let mut decoder = crate::RawDecoder::with_options(crate::DecoderOptions::new().max_non_audio_bytes(Some(1000)));
+        let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+        // the scan stops at the limit, rather than going through all of the garbage first
+        let (frame, len) = decoder.next(&garbage, &mut pcm).unwrap();
+        assert!(matches!(frame, crate::Frame::Error(crate::Error::NoAudio)));
+        assert!(len <= 1001, "{}", len);
+    }
+
+    #[test]
+    fn energy() {
+        assert_eq!(crate::energy(&[]), 0.0);
+        #[cfg(not(feature = "float"))]
+        let samples: [Sample; 4] = [16384, -16384, 0, 0];
+        #[cfg(feature = "float")]
+        let samples: [Sample; 4] = [0.5, -0.5, 0.0, 0.0];
+        assert_eq!(crate::energy(&samples), 0.125);
+    }
+
+    #[test]
+    fn crc16() {
+        // CRC-16/CMS check value, which shares the parameters used by MPEG Audio
+        assert_eq!(crate::crc16(0xFFFF, b"123456789"), 0xAEE7);
+    }
+
+    #[test]
+    fn frame_crc() {
+        // MPEG-1 layer III joint stereo, whose side information is 32 bytes
+        let mut frame = [0x55; 38];
+        frame[..4].copy_from_slice(&[0xFF, 0xFA, 0x90, 0x64]);
+        let crc = crate::frame_crc(&frame).unwrap();
+        assert_eq!(crc, crate::crc16(crate::crc16(0xFFFF, &[0x90, 0x64]), &[0x55; 32]));
+        assert!(crate::frame_crc(&frame[..37]).is_none());
+
+        // without protection, the same data is right after the header
+        frame[1] = 0xFB;
+        assert_eq!(crate::frame_crc(&frame[..36]), Some(crc));
+
+        // layer II isn't supported
+        frame[1] = 0xFD;
+        assert!(crate::frame_crc(&frame).is_none());
+    }
+
     #[test]
     fn sanity() {
         // See the comment on `crate::MAX_SAMPLES_PER_FRAME`
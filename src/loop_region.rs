@@ -0,0 +1,145 @@
+//! Looping part of a stream.
+
+use crate::{Audio, Decoder, Frame, PcmStorage};
+
+/// Plays a [`Decoder`] in a loop between two samples, such as for game music with an intro.
+///
+/// Everything before the loop plays once, and at the end of it, the decoder
+/// [seeks](Decoder::seek_to_sample) back to the start, which decodes the frames before it too.
+/// That way the first frame after each wrap is exactly what it'd be without seeking,
+/// so the loop point doesn't click from a cold bit reservoir. The last frame of the loop
+/// is cut short, so the loop is exact to the sample.
+///
+/// Unless the stream starts with an `Info` header saying it's CBR, seeking steps through the frames before the loop start by their headers
+/// on every wrap, which takes much less time than decoding them, but grows with the length of the intro.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Decoder, LoopRegion};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut looped = LoopRegion::new(44100 * 4, 44100 * 60).unwrap();
+/// while let Some(audio) = looped.next(&mut decoder) {
+///     // play `audio.samples()` here, forever!
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct LoopRegion {
+    start: u64,
+    end: u64,
+
+    // the sample the decoder is at, counted from the start of the stream
+    position: u64,
+}
+
+impl LoopRegion {
+    /// Constructs a `LoopRegion` from `start` up until `end`, counted per channel,
+    /// for a decoder which is at the start of the stream.
+    ///
+    /// Returns `None` if the loop is empty, as in, `start` isn't less than `end`.
+    pub fn new(start: u64, end: u64) -> Option<Self> {
+        if start >= end {
+            return None;
+        }
+        Some(Self { start, end, position: 0 })
+    }
+
+    /// Gets the sample the loop starts at.
+    #[inline]
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Gets the sample the loop ends before.
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Gets the sample which plays next, counted from the start of the stream.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Decodes the next audio frame of `decoder`, wrapping around at the end of the loop,
+    /// and skipping over frames which aren't audio.
+    ///
+    /// If the stream ends before the loop does, it wraps around there instead.
+    /// Returns `None` if there's no audio at the start of the loop.
+    pub fn next<'src, 'pcm, P: PcmStorage>(&mut self, decoder: &'pcm mut Decoder<'src, P>) -> Option<Audio<'src, 'pcm>> {
+        if self.position >= self.end {
+            self.wrap(decoder)?;
+        }
+        loop {
+            match decoder.peek() {
+                Some(Frame::Audio(_)) => break,
+                Some(_) => {
+                    decoder.skip();
+                },
+                None if self.position > self.start => self.wrap(decoder)?,
+                None => return None,
+            }
+        }
+        let mut audio = match decoder.next()? {
+            Frame::Audio(audio) => audio,
+            _ => return None,
+        };
+        let left = self.end - self.position;
+        if audio.sample_count as u64 > left {
+            // CAST: This is less than the sample count, which is a usize.
+            audio.sample_count = left as usize;
+        }
+        self.position += audio.sample_count as u64;
+        Some(audio)
+    }
+
+    fn wrap<P: PcmStorage>(&mut self, decoder: &mut Decoder<'_, P>) -> Option<()> {
+        self.position = decoder.seek_to_sample(self.start)?;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoopRegion;
+
+    #[test]
+    fn loop_region() {
+        assert!(LoopRegion::new(10, 10).is_none());
+        let looped = LoopRegion::new(10, 20).unwrap();
+        assert_eq!((looped.start(), looped.end(), looped.position()), (10, 20, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn wraps_around() {
+        let mp3 = crate::test_util::FrameBuilder::new().stream(10).unwrap();
+        let mut decoder = crate::Decoder::new(&mp3);
+        let mut looped = LoopRegion::new(1152 * 2 + 100, 1152 * 5).unwrap();
+        let mut lengths = [0; 8];
+        for length in &mut lengths {
+            *length = looped.next(&mut decoder).unwrap().sample_count();
+        }
+        assert_eq!(lengths, [1152, 1152, 1152, 1152, 1152, 1052, 1152, 1152]);
+        assert_eq!(looped.position(), 1152 * 5);
+        assert_eq!(looped.next(&mut decoder).unwrap().sample_count(), 1052);
+        assert_eq!(looped.position(), 1152 * 3);
+
+        // the stream ends before the loop does, so it wraps there
+        let mut decoder = crate::Decoder::new(&mp3);
+        let mut looped = LoopRegion::new(1152 * 8, 1152 * 100).unwrap();
+        for _ in 0..10 {
+            looped.next(&mut decoder).unwrap();
+        }
+        assert_eq!(looped.position(), 1152 * 10);
+        looped.next(&mut decoder).unwrap();
+        assert_eq!(looped.position(), 1152 * 9);
+    }
+}
@@ -0,0 +1,236 @@
+//! Health metrics of live streams.
+
+use crate::{Error, Frame};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A snapshot of how a stream is doing, returned by [`StreamMonitor::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StreamMetrics {
+    bitrate: Option<f64>,
+    drift: f64,
+    frames_per_second: f64,
+    resyncs: u64,
+}
+
+impl StreamMetrics {
+    /// Gets the average bitrate of the audio frames in the window in kb/s,
+    /// or `None` if there weren't any.
+    #[inline]
+    pub fn bitrate(&self) -> Option<f64> {
+        self.bitrate
+    }
+
+    /// Gets how many seconds the wall clock is ahead of the decoded audio since the first audio frame,
+    /// which grows when the stream arrives slower than real time, and goes negative when it's faster.
+    #[inline]
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Gets how many audio frames per second were decoded over the window.
+    #[inline]
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames_per_second
+    }
+
+    /// Gets how many times the decoder lost sync and found it again, in total.
+    #[inline]
+    pub fn resyncs(&self) -> u64 {
+        self.resyncs
+    }
+}
+
+/// Keeps rolling metrics of a stream as it's decoded, such as for monitoring long-running radio relays.
+///
+/// Every frame a decoder returns should be [recorded](Self::record), as they're returned,
+/// and the [`metrics`](Self::metrics) can be looked at any time. Rates are measured over
+/// a window of the last minute by default, and all frames from within it are kept, so it should
+/// be kept short enough for that.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Decoder, StreamMonitor};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let stream = [];
+/// let mut decoder = Decoder::new(&stream);
+/// let mut monitor = StreamMonitor::new();
+/// while let Some(frame) = decoder.next() {
+///     monitor.record(&frame);
+///     // relay the frame here!
+/// }
+/// let metrics = monitor.metrics();
+/// println!("{:.1} frames/s, {:.2}s of drift", metrics.frames_per_second(), metrics.drift());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamMonitor {
+    window: Duration,
+
+    // when, how many bytes and how long each audio frame in the window was
+    frames: VecDeque<(Instant, usize, Duration)>,
+
+    // when the first audio frame was recorded, and the length of all audio since
+    start: Option<Instant>,
+    decoded: Duration,
+
+    resyncs: u64,
+}
+
+impl StreamMonitor {
+    /// Constructs a new `StreamMonitor` of nothing yet.
+    pub fn new() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            frames: VecDeque::new(),
+            start: None,
+            decoded: Duration::ZERO,
+            resyncs: 0,
+        }
+    }
+
+    /// Sets how far back the rates are measured, which is a minute by default.
+    #[inline]
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Records a frame which was just returned by a decoder.
+    #[inline]
+    pub fn record(&mut self, frame: &Frame<'_, '_>) {
+        self.record_at(frame, Instant::now());
+    }
+
+    /// Records a frame which was returned by a decoder at `now`.
+    pub fn record_at(&mut self, frame: &Frame<'_, '_>, now: Instant) {
+        match frame {
+            Frame::Audio(audio) => {
+                // garbage before a frame after the first one means sync was lost
+                if self.start.is_some() && !audio.garbage().is_empty() {
+                    self.resyncs += 1;
+                }
+                let duration = audio.duration();
+                self.start.get_or_insert(now);
+                self.decoded += duration;
+                self.frames.push_back((now, audio.source().len(), duration));
+            },
+            Frame::Error(Error::NoSync) if self.start.is_some() => self.resyncs += 1,
+            _ => (),
+        }
+        self.expire(now);
+    }
+
+    /// Gets the metrics as of now.
+    #[inline]
+    pub fn metrics(&self) -> StreamMetrics {
+        self.metrics_at(Instant::now())
+    }
+
+    /// Gets the metrics as of `now`, which shouldn't be before the last recorded frame.
+    pub fn metrics_at(&self, now: Instant) -> StreamMetrics {
+        let frames = self.frames.iter().filter(|(time, ..)| now.saturating_duration_since(*time) <= self.window);
+        let (count, bytes, duration) =
+            frames.fold((0usize, 0usize, Duration::ZERO), |(count, bytes, duration), &(_, len, x)| {
+                (count + 1, bytes + len, duration + x)
+            });
+
+        // the window is shorter until a whole window has passed
+        let elapsed = match self.start {
+            Some(start) => now.saturating_duration_since(start).min(self.window),
+            None => Duration::ZERO,
+        };
+        StreamMetrics {
+            bitrate: match duration.as_secs_f64() {
+                seconds if seconds > 0.0 => Some(bytes as f64 * 8.0 / 1000.0 / seconds),
+                _ => None,
+            },
+            drift: match self.start {
+                Some(start) => now.saturating_duration_since(start).as_secs_f64() - self.decoded.as_secs_f64(),
+                None => 0.0,
+            },
+            frames_per_second: match elapsed.as_secs_f64() {
+                seconds if seconds > 0.0 => count as f64 / seconds,
+                _ => 0.0,
+            },
+            resyncs: self.resyncs,
+        }
+    }
+
+    /// Forgets everything recorded, such as after reconnecting to a stream.
+    pub fn reset(&mut self) {
+        self.frames.clear();
+        self.start = None;
+        self.decoded = Duration::ZERO;
+        self.resyncs = 0;
+    }
+
+    /// Forgets frames which are out of the window.
+    fn expire(&mut self, now: Instant) {
+        while matches!(self.frames.front(), Some((time, ..)) if now.saturating_duration_since(*time) > self.window) {
+            self.frames.pop_front();
+        }
+    }
+}
+
+impl Default for StreamMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamMonitor;
+    use crate::{ffi, Audio, Error, Frame};
+    use std::time::{Duration, Instant};
+
+    fn frame(source: &'static [u8], garbage: &'static [u8]) -> Frame<'static, 'static> {
+        Frame::Audio(Audio {
+            info: ffi::mp3dec_frame_info_t {
+                frame_bytes: 0,
+                frame_offset: 0,
+                channels: 2,
+                hz: 48000,
+                layer: 3,
+                bitrate_kbps: 128,
+            },
+            concealed: false,
+            energy: None,
+            pcm: None,
+            sample_count: 1152,
+            source,
+            offset: 0,
+            garbage,
+            phantom: core::marker::PhantomData,
+        })
+    }
+
+    #[test]
+    fn monitor() {
+        // 1152 samples at 48 kHz is 24 ms, so 384 bytes is 128 kb/s
+        let start = Instant::now();
+        let mut monitor = StreamMonitor::new().window(Duration::from_secs(1));
+        for i in 0..100 {
+            let garbage: &[u8] = if i == 50 { &[0; 4] } else { &[] };
+            monitor.record_at(&frame(&[0; 384], garbage), start + Duration::from_millis(i * 25));
+        }
+        monitor.record_at(&Frame::Error(Error::NoSync), start + Duration::from_millis(2500));
+
+        let metrics = monitor.metrics_at(start + Duration::from_millis(2500));
+        assert_eq!(metrics.resyncs(), 2);
+        assert!((metrics.bitrate().unwrap() - 128.0).abs() < 1e-9);
+        // only the 40 frames from 1.5 seconds on are in the window
+        assert!((metrics.frames_per_second() - 40.0).abs() < 1e-9);
+        assert!((metrics.drift() - (2.5 - 2.4)).abs() < 1e-9);
+
+        monitor.reset();
+        assert_eq!(monitor.metrics(), Default::default());
+    }
+}
@@ -0,0 +1,521 @@
+//! Streaming internet radio over HTTP, such as from Icecast and SHOUTcast servers.
+//!
+//! [`IcyStream`] connects to a URL, asks for ICY metadata, and reconnects whenever the connection
+//! drops, handing out the audio in chunks for a [`ChunksDecoder`](crate::ChunksDecoder).
+//! Only plain `http://` URLs are supported, as there's no TLS without dependencies.
+//! For HTTPS, an [`IcyReader`] splits the metadata out of any other client's response body,
+//! given the `icy-metaint` header, which [`IcyHeaders::from_headers`] picks out.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rmp3::{net::IcyStream, ChunksDecoder, Frame};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let stream = IcyStream::connect("http://radio.example.com:8000/stream")?;
+//! println!("listening to {:?}", stream.headers().name());
+//! let mut decoder = ChunksDecoder::new(stream);
+//! while let Some(frame) = decoder.next() {
+//!     if let Frame::Audio(audio) = frame {
+//!         // play `audio.samples()` here!
+//!     }
+//!     if let Some(title) = decoder.chunks().metadata().title() {
+//!         // show what's playing here!
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+/// How many redirects are followed before giving up on connecting.
+const MAX_REDIRECTS: usize = 5;
+
+/// How long a read can wait before the connection is considered dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The ICY headers a server describes its stream with.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct IcyHeaders {
+    bitrate: Option<u32>,
+    description: Option<String>,
+    genre: Option<String>,
+    metaint: Option<usize>,
+    name: Option<String>,
+    url: Option<String>,
+}
+
+impl IcyHeaders {
+    /// Picks the ICY headers out of HTTP headers, given as names and values.
+    ///
+    /// Names are matched case-insensitively, and unrelated headers are ignored.
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut icy = Self::default();
+        for (name, value) in headers {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                // some servers list the bitrate of every quality, as in "128,128"
+                "icy-br" => icy.bitrate = value.split(',').next().and_then(|x| x.trim().parse().ok()),
+                "icy-description" => icy.description = Some(value.to_owned()),
+                "icy-genre" => icy.genre = Some(value.to_owned()),
+                "icy-metaint" => icy.metaint = value.parse().ok().filter(|&x| x != 0),
+                "icy-name" => icy.name = Some(value.to_owned()),
+                "icy-url" => icy.url = Some(value.to_owned()),
+                _ => (),
+            }
+        }
+        icy
+    }
+
+    /// Gets the advertised bitrate in kb/s.
+    #[inline]
+    pub fn bitrate(&self) -> Option<u32> {
+        self.bitrate
+    }
+
+    /// Gets the description of the station.
+    #[inline]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Gets the genre of the station.
+    #[inline]
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    /// Gets how many bytes of audio there are between metadata blocks,
+    /// or `None` if the stream has no metadata.
+    #[inline]
+    pub fn metaint(&self) -> Option<usize> {
+        self.metaint
+    }
+
+    /// Gets the name of the station.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Gets the homepage of the station.
+    #[inline]
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+/// ICY metadata sent within a stream, which is usually what's playing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct IcyMetadata {
+    title: Option<String>,
+    url: Option<String>,
+}
+
+impl IcyMetadata {
+    /// Parses a metadata block, such as `StreamTitle='Artist - Title';StreamUrl='';`,
+    /// without its length byte.
+    ///
+    /// Values end at the next `';`, so they can have quotes in them, and aren't required
+    /// to be UTF-8, being converted lossily.
+    pub fn parse(block: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(block);
+        let mut rest = text.trim_end_matches('\0');
+        let mut metadata = Self::default();
+        while let Some(start) = rest.find("='") {
+            let key = rest[..start].trim();
+            rest = &rest[start + 2..];
+            let (value, next) = match rest.find("';") {
+                Some(end) => (&rest[..end], &rest[end + 2..]),
+                None => (rest.trim_end_matches('\''), ""),
+            };
+            rest = next;
+            let value = Some(value.to_owned()).filter(|x| !x.is_empty());
+            match key {
+                "StreamTitle" => metadata.title = value,
+                "StreamUrl" => metadata.url = value,
+                _ => (),
+            }
+        }
+        metadata
+    }
+
+    /// Gets the title of what's playing, usually written as "Artist - Title".
+    #[inline]
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Gets the URL sent with the title, such as for artwork.
+    #[inline]
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+/// Reads the audio of an ICY stream body, splitting out the metadata blocks within it.
+#[derive(Debug)]
+pub struct IcyReader<R> {
+    inner: R,
+    metaint: Option<usize>,
+
+    // how much audio there is until the next metadata block
+    left: usize,
+
+    metadata: IcyMetadata,
+    changed: bool,
+}
+
+impl<R: Read> IcyReader<R> {
+    /// Constructs a new `IcyReader` over a response body from its start,
+    /// with metadata blocks every `metaint` bytes of audio, or none if it's `None`.
+    pub fn new(inner: R, metaint: Option<usize>) -> Self {
+        Self {
+            inner,
+            metaint,
+            left: metaint.unwrap_or(0),
+            metadata: IcyMetadata::default(),
+            changed: false,
+        }
+    }
+
+    /// Gets the latest metadata.
+    #[inline]
+    pub fn metadata(&self) -> &IcyMetadata {
+        &self.metadata
+    }
+
+    /// Returns the latest metadata if it changed since this was last called.
+    pub fn take_metadata(&mut self) -> Option<IcyMetadata> {
+        if self.changed {
+            self.changed = false;
+            Some(self.metadata.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consumes the `IcyReader`, returning the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the metadata block the body is at, if it doesn't end first.
+    fn read_metadata(&mut self) -> io::Result<bool> {
+        let mut len = [0];
+        if self.inner.read(&mut len)? == 0 {
+            return Ok(false);
+        }
+        // empty blocks mean nothing changed
+        if len[0] != 0 {
+            let mut block = [0; 255 * 16];
+            let block = &mut block[..usize::from(len[0]) * 16];
+            self.inner.read_exact(block)?;
+            let metadata = IcyMetadata::parse(block);
+            if metadata != self.metadata {
+                self.metadata = metadata;
+                self.changed = true;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let metaint = match self.metaint {
+            Some(metaint) => metaint,
+            None => return self.inner.read(buf),
+        };
+        if self.left == 0 {
+            if !self.read_metadata()? {
+                return Ok(0);
+            }
+            self.left = metaint;
+        }
+        let len = buf.len().min(self.left);
+        let len = self.inner.read(&mut buf[..len])?;
+        self.left -= len;
+        Ok(len)
+    }
+}
+
+/// A client for an HTTP radio stream, which hands out its audio in chunks as an [`Iterator`].
+///
+/// When the connection drops, it reconnects, a few times by default, waiting a bit before each try.
+/// The decoder loses sync for a moment when that happens, then finds it again on its own.
+/// If it fails to reconnect, the iterator ends, and the error can be [taken](Self::take_error).
+///
+/// Metadata is read as the audio goes, which a [`ChunksDecoder`](crate::ChunksDecoder) buffers
+/// a little ahead of, so it's updated about a second before the audio with it is decoded.
+#[derive(Debug)]
+pub struct IcyStream {
+    url: String,
+    headers: IcyHeaders,
+    reader: IcyReader<BufReader<TcpStream>>,
+
+    retries: u32,
+    retry_delay: Duration,
+    error: Option<io::Error>,
+}
+
+impl IcyStream {
+    /// Connects to the stream at `url`, following redirects.
+    ///
+    /// Fails if the URL isn't `http://`, the connection fails,
+    /// or the server doesn't respond with success.
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let (headers, stream) = open(url)?;
+        Ok(Self {
+            url: url.to_owned(),
+            reader: IcyReader::new(stream, headers.metaint()),
+            headers,
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+            error: None,
+        })
+    }
+
+    /// Sets how many times it tries to reconnect when the connection drops, which is 3 by default.
+    #[inline]
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long it waits before every try to reconnect, which is a second by default.
+    #[inline]
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Gets the URL that was connected to, from before any redirects.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gets the ICY headers of the latest connection.
+    #[inline]
+    pub fn headers(&self) -> &IcyHeaders {
+        &self.headers
+    }
+
+    /// Gets the latest metadata.
+    #[inline]
+    pub fn metadata(&self) -> &IcyMetadata {
+        self.reader.metadata()
+    }
+
+    /// Returns the latest metadata if it changed since this was last called.
+    #[inline]
+    pub fn take_metadata(&mut self) -> Option<IcyMetadata> {
+        self.reader.take_metadata()
+    }
+
+    /// Takes the error reconnecting failed with, once the iterator has ended.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Tries to reconnect, keeping the metadata from before.
+    fn reconnect(&mut self) -> io::Result<()> {
+        let mut error = io::Error::new(io::ErrorKind::UnexpectedEof, "rmp3: the stream ended");
+        for _ in 0..self.retries {
+            thread::sleep(self.retry_delay);
+            match open(&self.url) {
+                Ok((headers, stream)) => {
+                    let mut reader = IcyReader::new(stream, headers.metaint());
+                    reader.metadata = core::mem::take(&mut self.reader.metadata);
+                    reader.changed = self.reader.changed;
+                    self.headers = headers;
+                    self.reader = reader;
+                    return Ok(());
+                },
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
+
+impl Iterator for IcyStream {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        if self.error.is_some() {
+            return None;
+        }
+        let mut chunk = vec![0; 4096];
+        loop {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => (),
+                Ok(len) => {
+                    chunk.truncate(len);
+                    return Some(chunk);
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => (),
+            }
+            if let Err(e) = self.reconnect() {
+                self.error = Some(e);
+                return None;
+            }
+        }
+    }
+}
+
+/// Splits an `http://` URL into its host, port and path, and the host as it goes in the `Host` header.
+fn parse_url(url: &str) -> io::Result<(&str, u16, &str, &str)> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let rest = match url.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("http://") => &url[7..],
+        _ => return Err(invalid("rmp3: only http:// URLs are supported")),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    // the colons in IPv6 addresses are within brackets
+    let (host, port) = match authority.rfind(':') {
+        Some(i) if !authority[i..].contains(']') => {
+            (&authority[..i], authority[i + 1..].parse().map_err(|_| invalid("rmp3: invalid port"))?)
+        },
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(invalid("rmp3: missing host"));
+    }
+    Ok((host, port, path, authority))
+}
+
+/// Requests the stream at `url` and reads the response headers, following redirects.
+fn open(url: &str) -> io::Result<(IcyHeaders, BufReader<TcpStream>)> {
+    let mut url = url.to_owned();
+    for _ in 0..=MAX_REDIRECTS {
+        let (host, port, path, authority) = parse_url(&url)?;
+        let stream = TcpStream::connect((host, port))?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut stream = BufReader::new(stream);
+
+        // HTTP/1.0 so that the body isn't chunked
+        write!(
+            stream.get_mut(),
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: rmp3/{}\r\nIcy-MetaData: 1\r\n\r\n",
+            path,
+            authority,
+            env!("CARGO_PKG_VERSION"),
+        )?;
+
+        // SHOUTcast servers respond with "ICY 200 OK", which is the same otherwise
+        let mut line = String::new();
+        stream.read_line(&mut line)?;
+        let status: u16 = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "rmp3: invalid HTTP response"))?;
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some(i) = line.find(':') {
+                headers.push((line[..i].trim().to_owned(), line[i + 1..].trim().to_owned()));
+            }
+        }
+
+        let header = |name: &str| headers.iter().find(|(x, _)| x.eq_ignore_ascii_case(name)).map(|(_, x)| x);
+        match status {
+            200 => {
+                let icy = IcyHeaders::from_headers(headers.iter().map(|(x, y)| (x.as_str(), y.as_str())));
+                return Ok((icy, stream));
+            },
+            301 | 302 | 303 | 307 | 308 => {
+                let location = header("location")
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "rmp3: redirect without a location"))?;
+                url = if location.starts_with('/') {
+                    format!("http://{}{}", authority, location)
+                } else {
+                    location.clone()
+                };
+            },
+            _ => return Err(io::Error::other(format!("rmp3: HTTP status {}", status))),
+        }
+    }
+    Err(io::Error::other("rmp3: too many redirects"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_url, IcyHeaders, IcyMetadata, IcyReader};
+    use std::io::Read;
+
+    #[test]
+    fn headers() {
+        let headers = IcyHeaders::from_headers(vec![
+            ("Content-Type", "audio/mpeg"),
+            ("icy-br", "128,128"),
+            ("ICY-Name", " Radio "),
+            ("icy-metaint", "16000"),
+        ]);
+        assert_eq!(headers.bitrate(), Some(128));
+        assert_eq!(headers.name(), Some("Radio"));
+        assert_eq!(headers.metaint(), Some(16000));
+        assert_eq!(headers.genre(), None);
+    }
+
+    #[test]
+    fn metadata() {
+        let metadata = IcyMetadata::parse(b"StreamTitle='Guns N' Roses - Patience';StreamUrl='';\0\0\0");
+        assert_eq!(metadata.title(), Some("Guns N' Roses - Patience"));
+        assert_eq!(metadata.url(), None);
+        assert_eq!(IcyMetadata::parse(b"StreamTitle='Cut off").title(), Some("Cut off"));
+    }
+
+    #[test]
+    fn reader() {
+        let mut data = b"abcd".to_vec();
+        let block = b"StreamTitle='A';";
+        data.push(1);
+        data.extend_from_slice(block);
+        data.extend_from_slice(b"efgh\0ij");
+
+        let mut reader = IcyReader::new(&data[..], Some(4));
+        let mut audio = Vec::new();
+        reader.read_to_end(&mut audio).unwrap();
+        assert_eq!(audio, b"abcdefghij");
+        assert_eq!(reader.take_metadata().unwrap().title(), Some("A"));
+        assert_eq!(reader.take_metadata(), None);
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(parse_url("http://example.com").unwrap(), ("example.com", 80, "/", "example.com"));
+        assert_eq!(
+            parse_url("HTTP://[::1]:8000/stream?x=1").unwrap(),
+            ("::1", 8000, "/stream?x=1", "[::1]:8000")
+        );
+        assert!(parse_url("https://example.com/").is_err());
+        assert!(parse_url("http://example.com:port/").is_err());
+    }
+}
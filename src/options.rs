@@ -0,0 +1,291 @@
+//! Configuration of decoder behaviour.
+
+/// Describes what a decoder does with frames that were found but couldn't be decoded,
+/// which happens with corrupt data, or when the bit reservoir isn't filled after seeking.
+///
+/// Concealed frames keep the length of the stream intact, unlike returning them as
+/// [`Frame::Other`](crate::Frame::Other), and can be told apart with
+/// [`Audio::is_concealed`](crate::Audio::is_concealed).
+///
+/// Repeating relies on the previous frame's samples still being in the output buffer,
+/// so with a [`RawDecoder`](crate::RawDecoder), the same buffer should be used every time.
+/// It falls back to muting if there's no previous frame of the same size.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Concealment {
+    /// Frames are returned as [`Frame::Other`](crate::Frame::Other), which is the default,
+    /// unless they're corrupt and the [`CorruptPolicy`] says otherwise.
+    None,
+
+    /// Frames are replaced with silence.
+    Mute,
+
+    /// Frames are replaced with the previous frame.
+    Repeat,
+
+    /// Frames are replaced with the previous frame, linearly faded out to silence,
+    /// which masks the gap without the hard cut of muting.
+    FadeOut,
+}
+
+/// Describes what a decoder does with frames that were found but are corrupt,
+/// when they aren't [concealed](Concealment).
+///
+/// Frames which only couldn't be decoded because the bit reservoir isn't filled yet,
+/// as happens at the start of a stream or after seeking, aren't considered corrupt,
+/// and are always returned as [`Frame::Other`](crate::Frame::Other).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum CorruptPolicy {
+    /// Frames are returned as [`Frame::Other`](crate::Frame::Other), which is the default.
+    Other,
+
+    /// Frames are skipped over silently, and the next frame is returned instead.
+    Skip,
+
+    /// Frames are returned as [`Error::Corrupt`](crate::Error::Corrupt).
+    Report,
+
+    /// Frames are returned as [`Error::Corrupt`](crate::Error::Corrupt),
+    /// after which a [`Decoder`](crate::Decoder) moves to the end of its data.
+    ///
+    /// A [`RawDecoder`](crate::RawDecoder) doesn't own its data, so for it this is the same as
+    /// [`Report`](Self::Report), and it's up to the caller to stop.
+    Abort,
+}
+
+/// Describes what a decoder does when the channel count or sample rate changes mid-stream,
+/// which happens in some internet radio streams.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum FormatPolicy {
+    /// Frames are returned as-is, which is the default.
+    PassThrough,
+
+    /// A [`Frame::FormatChanged`](crate::Frame::FormatChanged) is returned before
+    /// the first frame of a new format.
+    Notify,
+
+    /// Frames are converted to the channel count of the first frame,
+    /// by duplicating mono to stereo or averaging stereo to mono.
+    ///
+    /// Sample rate changes can't be conformed, since that would require resampling,
+    /// so those are announced as with [`Notify`](Self::Notify).
+    Conform,
+}
+
+/// Describes the channel layout of a decoder's output, for when it's played on a device
+/// with a fixed channel count.
+///
+/// Mixing happens in place in the output buffer as part of decoding, so there's no separate pass over it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum OutputLayout {
+    /// Frames are returned with their own channel count, which is the default.
+    Source,
+
+    /// Stereo frames are downmixed by averaging both channels.
+    Mono,
+
+    /// Mono frames are upmixed by copying the channel to both sides.
+    Stereo,
+}
+
+impl OutputLayout {
+    /// Gets the channel count all output is mixed to, if any.
+    #[inline]
+    pub(crate) fn channels(self) -> Option<u16> {
+        match self {
+            Self::Source => None,
+            Self::Mono => Some(1),
+            Self::Stereo => Some(2),
+        }
+    }
+}
+
+/// Options for configuring how a decoder behaves, built up from the defaults.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Decoder, DecoderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let options = DecoderOptions::new()
+///     .strict(true)
+///     .scan_limit(Some(64 * 1024));
+/// let mut decoder = Decoder::with_options(&mp3, options);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct DecoderOptions {
+    pub(crate) concealment: Concealment,
+    pub(crate) corrupt_policy: CorruptPolicy,
+    pub(crate) energy: bool,
+    pub(crate) format_policy: FormatPolicy,
+    pub(crate) free_format: bool,
+    pub(crate) max_non_audio_bytes: Option<usize>,
+    pub(crate) max_non_audio_frames: Option<usize>,
+    pub(crate) output_layout: OutputLayout,
+    pub(crate) scan_limit: Option<usize>,
+    pub(crate) strict: bool,
+    pub(crate) sync_matches: usize,
+}
+
+impl DecoderOptions {
+    /// Constructs the default `DecoderOptions`.
+    pub const fn new() -> Self {
+        Self {
+            concealment: Concealment::None,
+            corrupt_policy: CorruptPolicy::Other,
+            energy: false,
+            format_policy: FormatPolicy::PassThrough,
+            free_format: true,
+            max_non_audio_bytes: None,
+            max_non_audio_frames: None,
+            output_layout: OutputLayout::Source,
+            scan_limit: None,
+            strict: false,
+            sync_matches: 10,
+        }
+    }
+
+    /// Sets what happens to frames that couldn't be decoded,
+    /// which is [`None`](Concealment::None) by default.
+    #[inline]
+    pub fn concealment(mut self, concealment: Concealment) -> Self {
+        self.concealment = concealment;
+        self
+    }
+
+    /// Sets what happens to corrupt frames which aren't concealed,
+    /// which is [`Other`](CorruptPolicy::Other) by default.
+    #[inline]
+    pub fn corrupt_policy(mut self, policy: CorruptPolicy) -> Self {
+        self.corrupt_policy = policy;
+        self
+    }
+
+    /// Sets whether the energy of every decoded frame is measured, which it isn't by default.
+    ///
+    /// See [`Audio::energy`](crate::Audio::energy) for more info.
+    #[inline]
+    pub fn energy(mut self, measure: bool) -> Self {
+        self.energy = measure;
+        self
+    }
+
+    /// Sets what happens when the channel count or sample rate changes mid-stream,
+    /// which is [`PassThrough`](FormatPolicy::PassThrough) by default.
+    #[inline]
+    pub fn format_policy(mut self, policy: FormatPolicy) -> Self {
+        self.format_policy = policy;
+        self
+    }
+
+    /// Sets whether free-format frames are accepted, which is the default.
+    ///
+    /// If not, they're returned as [`Frame::Other`](crate::Frame::Other) instead.
+    /// See [`Audio::is_free_format`](crate::Audio::is_free_format) for more info.
+    #[inline]
+    pub fn free_format(mut self, accept: bool) -> Self {
+        self.free_format = accept;
+        self
+    }
+
+    /// Sets how many bytes in a row may be decoded without any audio, which is unlimited by default.
+    ///
+    /// See [`max_non_audio_frames`](Self::max_non_audio_frames) for more info.
+    #[inline]
+    pub fn max_non_audio_bytes(mut self, limit: Option<usize>) -> Self {
+        self.max_non_audio_bytes = limit;
+        self
+    }
+
+    /// Sets how many frames in a row may be decoded without any audio, which is unlimited by default.
+    ///
+    /// Once either this or the [byte limit](Self::max_non_audio_bytes) is reached, the frame that
+    /// reached it is returned as [`Error::NoAudio`](crate::Error::NoAudio) instead,
+    /// after which a [`Decoder`](crate::Decoder) moves to the end of its data.
+    /// This makes feeding a decoder something huge which isn't MPEG Audio fail fast,
+    /// rather than skipping over all of it. Only decoding counts frames, not peeking.
+    #[inline]
+    pub fn max_non_audio_frames(mut self, limit: Option<usize>) -> Self {
+        self.max_non_audio_frames = limit;
+        self
+    }
+
+    /// Sets whether all output is mono, which it isn't by default.
+    /// This is the same as setting the [`output_layout`](Self::output_layout) to
+    /// [`Mono`](OutputLayout::Mono), or back to [`Source`](OutputLayout::Source).
+    ///
+    /// Stereo frames are downmixed by averaging both channels as part of decoding them,
+    /// so only [`MAX_SAMPLES_PER_FRAME`](crate::MAX_SAMPLES_PER_FRAME) / 2 samples are ever returned,
+    /// and format changes are only reported for the sample rate.
+    /// The output buffer must still fit a stereo frame, as it's decoded in full before downmixing.
+    #[inline]
+    pub fn mono(mut self, mono: bool) -> Self {
+        self.output_layout = if mono { OutputLayout::Mono } else { OutputLayout::Source };
+        self
+    }
+
+    /// Sets the channel layout all output is mixed to, which is [`Source`](OutputLayout::Source) by default.
+    ///
+    /// Format changes are only reported for the sample rate if the layout is fixed.
+    /// With [`Stereo`](OutputLayout::Stereo), the output buffer must fit a stereo frame even for mono frames,
+    /// and [`Error::BufferTooSmall`](crate::Error::BufferTooSmall) is returned if it doesn't.
+    #[inline]
+    pub fn output_layout(mut self, layout: OutputLayout) -> Self {
+        self.output_layout = layout;
+        self
+    }
+
+    /// Sets how many bytes of garbage may precede a frame, which is unlimited by default.
+    ///
+    /// If no frame starts within that many bytes, [`Error::NoSync`](crate::Error::NoSync)
+    /// is returned instead along with the amount of bytes that were scanned,
+    /// as to not scan through potentially huge amounts of data which isn't MPEG Audio at all.
+    #[inline]
+    pub fn scan_limit(mut self, limit: Option<usize>) -> Self {
+        self.scan_limit = limit;
+        self
+    }
+
+    /// Sets whether strict mode is enabled, which it isn't by default.
+    ///
+    /// In strict mode, frames using reserved or disallowed header values are returned as
+    /// [`Error::ReservedValue`](crate::Error::ReservedValue) instead of being decoded as usual.
+    /// See [`FrameHeader::has_reserved_values`](crate::FrameHeader::has_reserved_values)
+    /// for which values those are.
+    #[inline]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets how many frames must follow a header in a row for it to be accepted
+    /// when (re)synchronizing, which is 10 by default.
+    ///
+    /// Corrupted live streams benefit from stricter matching to avoid false syncs,
+    /// while less of it helps with tiny clips. Zero accepts any valid-looking header.
+    /// Fewer frames are required if the data ends before that.
+    #[inline]
+    pub fn sync_matches(mut self, matches: usize) -> Self {
+        self.sync_matches = matches;
+        self
+    }
+}
+
+impl Default for DecoderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,354 @@
+//! Decoding on multiple threads with [rayon](https://docs.rs/rayon).
+//!
+//! Frames depend on the ones before them through the bit reservoir and the MDCT overlap,
+//! so each chunk of the stream is decoded from a few frames early, and the output of those
+//! warm-up frames is thrown away. The result is the same as decoding it in one go.
+
+use crate::{dsp::to_f32, Audio, Decoder, Frame, PREROLL_FRAMES};
+use core::ops::Range;
+use rayon::prelude::*;
+
+/// How many bytes of main data back the bit reservoir of a layer III frame can reach.
+const MAX_RESERVOIR_BYTES: usize = 511;
+
+/// How many frames a chunk has at least, so that the warm-up stays cheap in comparison.
+const MIN_CHUNK_FRAMES: usize = 64;
+
+/// Decodes a whole stream on rayon's thread pool, returning its interleaved samples.
+///
+/// The samples are as [`Audio::samples`](crate::Audio::samples) of each frame would be,
+/// one frame after another, so like with a [`Decoder`], the channel count and sample rate
+/// could change mid-stream. Samples are converted to floats in the range \[-1.0, 1.0\).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let pcm = rmp3::parallel::decode_parallel(&mp3);
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_parallel(src: &[u8]) -> Vec<f32> {
+    let index = FrameIndex::new(src);
+    let frames = index.len();
+    let chunk_count = (frames / MIN_CHUNK_FRAMES).clamp(1, rayon::current_num_threads() * 4);
+    let chunk_frames = frames.div_ceil(chunk_count).max(1);
+    let chunks: Vec<Vec<f32>> = (0..chunk_count)
+        .into_par_iter()
+        .map(|i| {
+            let start = (i * chunk_frames).min(frames);
+            let end = (start + chunk_frames).min(frames);
+            let mut pcm = Vec::new();
+            decode_frames(src, &index, start, end, |_, audio| {
+                pcm.extend(audio.samples().iter().copied().map(to_f32));
+            });
+            pcm
+        })
+        .collect();
+    chunks.concat()
+}
+
+/// Decodes disjoint segments of a stream on rayon's thread pool, returning the interleaved
+/// samples of each, in the same order.
+///
+/// Segments are ranges of samples counted per channel, as with
+/// [`Decoder::seek_to_sample`], and are clamped to the end of the stream.
+/// Each one is decoded from a few frames early, so the output is the same as decoding
+/// the whole stream and cutting it up, but only the parts that are needed are decoded,
+/// such as the visible regions when rendering the waveform of a long file.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::parallel::{decode_segments, FrameIndex};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let index = FrameIndex::new(&mp3);
+/// let visible = [0..44100, 441000..485100];
+/// let segments = decode_segments(&mp3, &index, &visible);
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_segments(src: &[u8], index: &FrameIndex, segments: &[Range<u64>]) -> Vec<Vec<f32>> {
+    segments
+        .par_iter()
+        .map(|segment| {
+            let mut pcm = Vec::new();
+            let (start, end) = match (index.frame_at(segment.start), segment.start < segment.end) {
+                (Some(start), true) => (start, index.frame_at(segment.end - 1).unwrap_or(index.len() - 1) + 1),
+                _ => return pcm,
+            };
+            decode_frames(src, index, start, end, |frame, audio| {
+                // the segment is in this frame, so these don't over- or underflow
+                let frame_start = index.samples[frame];
+                let to = ((segment.end - frame_start) as usize).min(audio.sample_count());
+                // a stored index can say a frame has more samples than it does
+                let from = (segment.start.saturating_sub(frame_start) as usize).min(to);
+                let channels = usize::from(audio.channels());
+                pcm.extend(audio.samples()[from * channels..to * channels].iter().copied().map(to_f32));
+            });
+            pcm
+        })
+        .collect()
+}
+
+/// The positions of every audio frame in a stream, in bytes and in samples.
+///
+/// Building one takes a pass over the whole stream, but it doesn't decode anything,
+/// and it can be reused for every call to [`decode_segments`] on the same data.
+///
+/// With the `serde` feature, it can be stored to skip that pass. Deserializing checks that
+/// the index is consistent, but the format isn't stable between versions of this crate,
+/// so an index stored by another version should be rebuilt rather than loaded.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawFrameIndex"))]
+pub struct FrameIndex {
+    offsets: Vec<usize>,
+    samples: Vec<u64>,
+    sample_count: u64,
+
+    // how many bytes of main data come before each frame, which is what the bit reservoir holds
+    main_data: Vec<usize>,
+    main_data_bytes: usize,
+}
+
+impl FrameIndex {
+    /// Builds the index of every audio frame in `src`.
+    pub fn new(src: &[u8]) -> Self {
+        let mut index = Self::default();
+        let mut decoder = Decoder::new(src);
+        while let Some(frame) = decoder.peek() {
+            if let Frame::Audio(audio) = frame {
+                index.offsets.push(audio.offset_in_source());
+                index.samples.push(index.sample_count);
+                index.sample_count += audio.sample_count() as u64;
+                index.main_data.push(index.main_data_bytes);
+                index.main_data_bytes += main_data_bytes(&audio);
+            }
+            decoder.skip();
+        }
+        index
+    }
+
+    /// Gets the index of the frame containing the sample at `sample` (counted per channel),
+    /// or `None` if it's past the end.
+    pub fn frame_at(&self, sample: u64) -> Option<usize> {
+        if sample >= self.sample_count {
+            return None;
+        }
+        Some(self.samples.partition_point(|&x| x <= sample) - 1)
+    }
+
+    /// Gets the byte offset of a frame in the data.
+    pub fn offset(&self, frame: usize) -> Option<usize> {
+        self.offsets.get(frame).copied()
+    }
+
+    /// Gets the position of the first sample of a frame, counted per channel.
+    pub fn sample(&self, frame: usize) -> Option<u64> {
+        self.samples.get(frame).copied()
+    }
+
+    /// Gets the total sample count per channel.
+    pub fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Gets the amount of frames.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Checks whether there are no frames at all.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+}
+
+/// The fields of a [`FrameIndex`] as they're deserialized, before they're checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawFrameIndex {
+    offsets: Vec<usize>,
+    samples: Vec<u64>,
+    sample_count: u64,
+    main_data: Vec<usize>,
+    main_data_bytes: usize,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<RawFrameIndex> for FrameIndex {
+    type Error = &'static str;
+
+    fn try_from(raw: RawFrameIndex) -> Result<Self, Self::Error> {
+        let len = raw.offsets.len();
+        if raw.samples.len() != len || raw.main_data.len() != len {
+            return Err("frame index has fields of different lengths");
+        }
+        // every frame has samples and a header, so these only go up
+        let increasing = |a: &[usize]| a.windows(2).all(|x| x[0] < x[1]);
+        if !increasing(&raw.offsets) || !raw.samples.windows(2).all(|x| x[0] < x[1]) {
+            return Err("frame index positions aren't in order");
+        }
+        if raw.samples.first().map_or(raw.sample_count != 0, |&x| x != 0)
+            || matches!(raw.samples.last(), Some(&x) if x >= raw.sample_count)
+        {
+            return Err("frame index sample positions don't match its sample count");
+        }
+        if !raw.main_data.windows(2).all(|x| x[0] <= x[1])
+            || matches!(raw.main_data.last(), Some(&x) if x > raw.main_data_bytes)
+        {
+            return Err("frame index main data positions aren't in order");
+        }
+        Ok(Self {
+            offsets: raw.offsets,
+            samples: raw.samples,
+            sample_count: raw.sample_count,
+            main_data: raw.main_data,
+            main_data_bytes: raw.main_data_bytes,
+        })
+    }
+}
+
+/// Decodes the frames from `start` up to `end` in the index, passing each one with its index to `f`.
+fn decode_frames(src: &[u8], index: &FrameIndex, start: usize, end: usize, mut f: impl FnMut(usize, &Audio)) {
+    if start >= end {
+        return;
+    }
+    let offsets = &index.offsets;
+    let begin = offsets[start];
+    // the index can be for other data than this, if it was stored
+    if begin >= src.len() {
+        return;
+    }
+    let end = offsets.get(end).copied().unwrap_or(src.len());
+    let mut decoder = Decoder::new(src);
+    decoder.set_position(offsets[warm_up(&index.main_data, start)]);
+    while let Some(frame) = decoder.next() {
+        if let Frame::Audio(audio) = frame {
+            let offset = audio.offset_in_source();
+            if offset >= end {
+                break;
+            } else if offset >= begin {
+                if let Ok(frame) = offsets.binary_search(&offset) {
+                    f(frame, &audio);
+                }
+            }
+        }
+    }
+}
+
+/// Gets the size of the main data of a frame, which is everything after the header,
+/// the CRC, and the side information of layer III.
+fn main_data_bytes(audio: &Audio) -> usize {
+    let header = audio.header();
+    let crc = if header.has_crc() { 2 } else { 0 };
+    let side_info = if header.mpeg_layer() == 3 { header.side_info_bytes() } else { 0 };
+    audio.source().len().saturating_sub(4 + crc + side_info)
+}
+
+/// Gets the index of the frame to start decoding from, for frame `start` to be decoded correctly,
+/// given how many bytes of main data come before each frame.
+fn warm_up(main_data: &[usize], start: usize) -> usize {
+    let mut warm_up = start.saturating_sub(PREROLL_FRAMES);
+    // the frame right before `start` has to be decoded fully for its MDCT overlap to carry over,
+    // so its reservoir has to be filled as well
+    if let Some(&previous) = start.checked_sub(1).and_then(|x| main_data.get(x)) {
+        while warm_up > 0 && previous - main_data[warm_up] < MAX_RESERVOIR_BYTES {
+            warm_up -= 1;
+        }
+    }
+    warm_up
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_at_sample() {
+        let index = FrameIndex {
+            offsets: vec![0, 417, 834],
+            samples: vec![0, 1152, 2304],
+            sample_count: 3456,
+            main_data: vec![0, 381, 762],
+            main_data_bytes: 1143,
+        };
+        assert_eq!(index.frame_at(0), Some(0));
+        assert_eq!(index.frame_at(1151), Some(0));
+        assert_eq!(index.frame_at(1152), Some(1));
+        assert_eq!(index.frame_at(3455), Some(2));
+        assert_eq!(index.frame_at(3456), None);
+        assert_eq!(FrameIndex::default().frame_at(0), None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn checks_stored_index() {
+        use core::convert::TryFrom;
+        let raw = |offsets: Vec<usize>, samples: Vec<u64>, sample_count| RawFrameIndex {
+            main_data: vec![0; offsets.len()],
+            offsets,
+            samples,
+            sample_count,
+            main_data_bytes: 0,
+        };
+        assert!(FrameIndex::try_from(raw(vec![0, 417], vec![0, 1152], 2304)).is_ok());
+        assert!(FrameIndex::try_from(raw(vec![], vec![], 0)).is_ok());
+        assert!(FrameIndex::try_from(raw(vec![0, 417], vec![0], 2304)).is_err());
+        assert!(FrameIndex::try_from(raw(vec![417, 0], vec![0, 1152], 2304)).is_err());
+        assert!(FrameIndex::try_from(raw(vec![0, 417], vec![1, 1152], 2304)).is_err());
+        assert!(FrameIndex::try_from(raw(vec![0, 417], vec![0, 1152], 1152)).is_err());
+        assert!(FrameIndex::try_from(raw(vec![], vec![], 1152)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn index_of_other_data() {
+        let mp3 = crate::test_util::FrameBuilder::new().stream(20).unwrap();
+        let index = FrameIndex::new(&mp3);
+        let segments = decode_segments(&mp3[..417 * 5], &index, &[0..1152 * 20, 1152 * 10..1152 * 11]);
+        assert_eq!(segments[0].len(), 1152 * 5 * 2);
+        assert!(segments[1].is_empty());
+        // frames of fewer samples than the index says
+        let mpeg2 = crate::test_util::FrameBuilder::new().sample_rate(22050).stream(20).unwrap();
+        decode_segments(&mpeg2, &index, &[1000..1152 * 20, 0..1]);
+    }
+
+    #[test]
+    fn warm_up_covers_reservoir() {
+        // 100-byte MPEG-1 stereo frames have 64 bytes of main data,
+        // so the frame before the chunk needs 8 more frames before it
+        let main_data: Vec<usize> = (0..20).map(|i| i * 64).collect();
+        assert_eq!(warm_up(&main_data, 0), 0);
+        assert_eq!(warm_up(&main_data, 1), 0);
+        assert_eq!(warm_up(&main_data, 10), 1);
+        // frames with 1000 bytes of main data only need the pre-roll
+        let main_data: Vec<usize> = (0..20).map(|i| i * 1000).collect();
+        assert_eq!(warm_up(&main_data, 10), 10 - PREROLL_FRAMES);
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn same_as_sequential() {
+        // 32 kb/s frames, which are small enough for the reservoir to reach across many of them
+        let mp3 = crate::test_util::FrameBuilder::new().bitrate(32).stream(1000).unwrap();
+        let mut sequential = Vec::new();
+        let mut decoder = Decoder::new(&mp3);
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                sequential.extend(audio.samples().iter().copied().map(to_f32));
+            }
+        }
+        assert!(!sequential.is_empty());
+        assert_eq!(decode_parallel(&mp3), sequential);
+
+        let index = FrameIndex::new(&mp3);
+        let segments = decode_segments(&mp3, &index, &[1000..5000, 100_000..100_001]);
+        assert_eq!(segments[0], sequential[2000..10000]);
+        assert_eq!(segments[1], sequential[200_000..200_002]);
+    }
+}
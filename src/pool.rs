@@ -0,0 +1,144 @@
+//! Reusing decoders between streams.
+
+use crate::{Decoder, DecoderOptions, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// Where a pooled [`Decoder`] decodes into, which is boxed so it stays put.
+pub type PooledPcm = Box<[Sample; MAX_SAMPLES_PER_FRAME]>;
+
+/// A pool of decoders that are handed out to decode one stream each, and then reused.
+///
+/// Every decoder holds the decoder state and a frame of samples, about 11 KiB together,
+/// so a service decoding many short streams, such as one transcoding uploads, can keep
+/// a pool of them instead of allocating those for every request. The pool is shared
+/// between threads by reference, and [`get`](Self::get) hands out decoders from it,
+/// which go back into it when they're dropped, reset as if they were new.
+///
+/// If all of them are in use, `get` allocates a new one rather than waiting,
+/// and decoders past the size of the pool are freed when they're dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{DecoderPool, Frame};
+///
+/// # let requests: Vec<Vec<u8>> = Vec::new();
+/// let pool = DecoderPool::new(4);
+/// std::thread::scope(|scope| {
+///     for mp3 in &requests {
+///         let pool = &pool;
+///         scope.spawn(move || {
+///             let mut decoder = pool.get(mp3);
+///             while let Some(frame) = decoder.next() {
+///                 // transcode the frame here!
+///             }
+///         });
+///     }
+/// });
+/// ```
+pub struct DecoderPool {
+    idle: Mutex<Vec<Box<Decoder<'static, PooledPcm>>>>,
+    options: DecoderOptions,
+    size: usize,
+}
+
+impl DecoderPool {
+    /// Constructs a new `DecoderPool` of `size` decoders, which are all allocated up front.
+    pub fn new(size: usize) -> Self {
+        Self::with_options(size, DecoderOptions::new())
+    }
+
+    /// Constructs a new `DecoderPool` of `size` decoders with the given options,
+    /// which are all allocated up front.
+    pub fn with_options(size: usize, options: DecoderOptions) -> Self {
+        let idle = (0..size).map(|_| Self::allocate(options)).collect();
+        Self { idle: Mutex::new(idle), options, size }
+    }
+
+    /// Gets the options every decoder from this pool starts with.
+    #[inline]
+    pub fn options(&self) -> DecoderOptions {
+        self.options
+    }
+
+    /// Gets how many decoders the pool keeps.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets how many decoders are in the pool and not in use.
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Hands out a decoder for `source` from the pool, or a new one if none are idle.
+    pub fn get<'src>(&self, source: &'src [u8]) -> PooledDecoder<'_, 'src> {
+        let decoder = self.lock().pop();
+        let mut decoder: Box<Decoder<'src, PooledPcm>> = decoder.unwrap_or_else(|| Self::allocate(self.options));
+        decoder.source = source;
+        decoder.source_copy = source;
+        PooledDecoder { decoder: Some(decoder), pool: self }
+    }
+
+    fn allocate(options: DecoderOptions) -> Box<Decoder<'static, PooledPcm>> {
+        let mut decoder = Box::new(Decoder::with_storage(&[], Box::new([Sample::default(); MAX_SAMPLES_PER_FRAME])));
+        decoder.raw.options = options;
+        decoder
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Box<Decoder<'static, PooledPcm>>>> {
+        // the list is never left half-changed, so it's fine if a thread panicked with it locked
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A [`Decoder`] handed out by a [`DecoderPool`], which goes back into it when it's dropped.
+pub struct PooledDecoder<'pool, 'src> {
+    decoder: Option<Box<Decoder<'src, PooledPcm>>>,
+    pool: &'pool DecoderPool,
+}
+
+impl<'src> Deref for PooledDecoder<'_, 'src> {
+    type Target = Decoder<'src, PooledPcm>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.decoder.as_deref().unwrap()
+    }
+}
+
+impl<'src> DerefMut for PooledDecoder<'_, 'src> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.decoder.as_deref_mut().unwrap()
+    }
+}
+
+impl Drop for PooledDecoder<'_, '_> {
+    fn drop(&mut self) {
+        let decoder = match self.decoder.take() {
+            Some(decoder) => decoder,
+            None => return,
+        };
+        let mut idle = self.pool.lock();
+        if idle.len() >= self.pool.size {
+            return;
+        }
+
+        let decoder = Box::into_raw(decoder);
+        unsafe {
+            (*decoder).cached_peek_len = None;
+            (*decoder).source = &[];
+            (*decoder).source_copy = &[];
+            (*decoder).trim = 0;
+            RawDecoder::init(&mut (*decoder).raw, self.pool.options);
+
+            // SAFETY: The decoder doesn't borrow anything from `'src` anymore.
+            idle.push(Box::from_raw(decoder.cast()));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Decoding from storage which is read a chunk at a time.
+
+use crate::{DecoderOptions, Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME};
+use core::convert::{Infallible, TryFrom};
+
+/// Something which can be read from at any offset, such as external flash on a microcontroller.
+///
+/// This is all [`ReadAtDecoder`] needs, so it's simple to implement over a storage driver,
+/// such as one with `embedded-storage`'s `ReadStorage`, clamping reads to its capacity.
+pub trait ReadAt {
+    /// The error type of reads.
+    type Error;
+
+    /// Reads bytes from `offset` into `buf`, returning how many were read,
+    /// which is 0 only if `offset` is at or past the end.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+impl ReadAt for &[u8] {
+    type Error = Infallible;
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let data = usize::try_from(offset).ok().and_then(|x| self.get(x..)).unwrap_or(&[]);
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+}
+
+/// High-level streaming iterator which reads its data through [`ReadAt`],
+/// keeping only `N` bytes of it in memory at a time.
+///
+/// This is for when the whole file can't be staged in RAM when using a [`Decoder`](crate::Decoder).
+/// The buffer is refilled before every frame, so it's best to have it fit a few frames,
+/// for finding them as confidently as a [`Decoder`](crate::Decoder) would
+/// (see [`DecoderOptions::sync_matches`]). 4 KiB fits three frames at 320 kb/s.
+/// Frames that don't fit in the buffer at all can't be decoded, and end the stream.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Frame, ReadAt, ReadAtDecoder};
+///
+/// struct Flash;
+///
+/// impl ReadAt for Flash {
+///     type Error = ();
+///
+///     fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, ()> {
+///         // read from the flash chip here!
+/// #       Ok(0)
+///     }
+/// }
+///
+/// let mut decoder = ReadAtDecoder::<_, 4096>::new(Flash);
+/// while let Ok(Some(frame)) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         // play `audio.samples()` here!
+///     }
+/// }
+/// ```
+pub struct ReadAtDecoder<R, const N: usize> {
+    reader: R,
+
+    // the buffered data which is left, and where in the reader the buffer ends
+    buffer: [u8; N],
+    start: usize,
+    end: usize,
+    offset: u64,
+    ended: bool,
+
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    raw: RawDecoder,
+}
+
+impl<R: ReadAt, const N: usize> ReadAtDecoder<R, N> {
+    /// Constructs a new `ReadAtDecoder` reading from the start of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecoderOptions::new())
+    }
+
+    /// Constructs a new `ReadAtDecoder` reading from the start of `reader` with the given options.
+    pub fn with_options(reader: R, options: DecoderOptions) -> Self {
+        Self {
+            reader,
+            buffer: [0; N],
+            start: 0,
+            end: 0,
+            offset: 0,
+            ended: false,
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            raw: RawDecoder::with_options(options),
+        }
+    }
+
+    /// Reads the next frame, skipping over potential garbage data,
+    /// and reading more data first if needed.
+    ///
+    /// If reading fails, the error is returned, and calling this again retries where it left off.
+    #[allow(clippy::should_implement_trait)] // it lends out the frame, so it can't be an `Iterator`
+    pub fn next(&mut self) -> Result<Option<Frame<'_, '_>>, R::Error> {
+        self.refill()?;
+        let (frame, len) = match self.raw.next(&self.buffer[self.start..self.end], &mut self.pcm) {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        self.start += len;
+        Ok(Some(frame))
+    }
+
+    /// Gets a reference to the reader.
+    #[inline]
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets the offset in the reader of the data which is decoded next.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.offset - (self.end - self.start) as u64
+    }
+
+    /// Sets the offset in the reader to decode from next, dropping everything buffered.
+    pub fn set_position(&mut self, position: u64) {
+        self.start = 0;
+        self.end = 0;
+        self.offset = position;
+        self.ended = false;
+    }
+
+    /// Consumes the `ReadAtDecoder`, returning the reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Moves what's left of the buffer to the front, and reads until it's full or the data ends.
+    fn refill(&mut self) -> Result<(), R::Error> {
+        if self.start != 0 {
+            self.buffer.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        while !self.ended && self.end < N {
+            match self.reader.read_at(self.offset, &mut self.buffer[self.end..])? {
+                0 => self.ended = true,
+                len => {
+                    self.end += len;
+                    self.offset += len as u64;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadAt;
+
+    #[test]
+    fn read_at_slice() {
+        let mut data: &[u8] = &[1, 2, 3, 4, 5];
+        let mut buf = [0; 3];
+        assert_eq!(data.read_at(1, &mut buf), Ok(3));
+        assert_eq!(buf, [2, 3, 4]);
+        assert_eq!(data.read_at(4, &mut buf), Ok(1));
+        assert_eq!(data.read_at(5, &mut buf), Ok(0));
+        assert_eq!(data.read_at(u64::MAX, &mut buf), Ok(0));
+    }
+}
@@ -0,0 +1,280 @@
+//! A lock-free ring buffer for handing decoded audio to a real-time callback.
+
+use crate::{Audio, PcmSink, Sample};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// A fixed-capacity, single-producer single-consumer ring buffer of `N` samples.
+///
+/// This is the usual player architecture: a thread or main loop decodes into the
+/// [`RingProducer`], such as with [`Decoder::decode_into`](crate::Decoder::decode_into),
+/// while the audio callback drains the [`RingConsumer`] without ever locking or allocating.
+/// It doesn't need `std`, and can be put in a `static` on microcontrollers,
+/// in which case it's split with [`try_split`](Self::try_split).
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Decoder, RingBuffer, Sample};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut ring = RingBuffer::<8192>::new();
+/// let (mut producer, mut consumer) = ring.split();
+/// std::thread::scope(|scope| {
+///     scope.spawn(move || Decoder::new(&mp3).decode_into(&mut producer));
+///
+///     // in the audio callback:
+///     let mut output = [Sample::default(); 512];
+///     consumer.pop_or_silence(&mut output);
+/// });
+/// # Ok(())
+/// # }
+/// ```
+pub struct RingBuffer<const N: usize> {
+    samples: UnsafeCell<[Sample; N]>,
+
+    // positions in `0..2 * N`, so that a full buffer can be told apart from an empty one
+    read: AtomicUsize,
+    write: AtomicUsize,
+
+    // whether `try_split` was called, as its halves can be around for as long as the buffer
+    split: AtomicBool,
+}
+
+// Safety: The producer and consumer only ever access samples the other one isn't.
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Constructs a new, empty `RingBuffer`.
+    ///
+    /// A capacity of zero doesn't compile, since nothing could ever go through it.
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "ring buffers need a capacity of at least one sample") };
+        Self {
+            samples: UnsafeCell::new([0 as Sample; N]),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Gets how many samples the buffer holds at most.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Splits the buffer into its producer and consumer halves,
+    /// which can be sent to different threads.
+    pub fn split(&mut self) -> (RingProducer<'_, N>, RingConsumer<'_, N>) {
+        self.halves()
+    }
+
+    /// Splits the buffer into its producer and consumer halves through a shared reference,
+    /// such as to a `static`, returning `None` if it was split this way before.
+    ///
+    /// Unlike with [`split`](Self::split), the buffer can't know when the halves are gone,
+    /// so this only works once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rmp3::RingBuffer;
+    ///
+    /// static RING: RingBuffer<8192> = RingBuffer::new();
+    ///
+    /// let (producer, consumer) = RING.try_split().unwrap();
+    /// // hand `producer` to the decoding loop, and `consumer` to the audio interrupt here!
+    /// assert!(RING.try_split().is_none());
+    /// ```
+    pub fn try_split(&self) -> Option<(RingProducer<'_, N>, RingConsumer<'_, N>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        Some(self.halves())
+    }
+
+    fn halves(&self) -> (RingProducer<'_, N>, RingConsumer<'_, N>) {
+        (
+            RingProducer { ring: self, phantom: PhantomData },
+            RingConsumer { ring: self, underruns: 0, underrun_samples: 0, phantom: PhantomData },
+        )
+    }
+
+    /// Gets how many samples there are between two positions.
+    #[inline]
+    fn distance(from: usize, to: usize) -> usize {
+        if to >= from { to - from } else { to + 2 * N - from }
+    }
+
+    /// Copies `src` into the buffer at `position`, returning the position after it.
+    ///
+    /// The caller must own that part of the buffer.
+    unsafe fn write_at(&self, position: usize, src: &[Sample]) -> usize {
+        let samples = self.samples.get() as *mut Sample;
+        let start = position % N;
+        let (head, tail) = src.split_at(src.len().min(N - start));
+        ptr::copy_nonoverlapping(head.as_ptr(), samples.add(start), head.len());
+        ptr::copy_nonoverlapping(tail.as_ptr(), samples, tail.len());
+        (position + src.len()) % (2 * N)
+    }
+
+    /// Copies from the buffer at `position` into `dest`, returning the position after it.
+    ///
+    /// The caller must own that part of the buffer.
+    unsafe fn read_at(&self, position: usize, dest: &mut [Sample]) -> usize {
+        let samples = self.samples.get() as *const Sample;
+        let start = position % N;
+        let len = dest.len();
+        let (head, tail) = dest.split_at_mut(len.min(N - start));
+        ptr::copy_nonoverlapping(samples.add(start), head.as_mut_ptr(), head.len());
+        ptr::copy_nonoverlapping(samples, tail.as_mut_ptr(), tail.len());
+        (position + len) % (2 * N)
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The writing half of a [`RingBuffer`].
+///
+/// As a [`PcmSink`], it waits for space whenever the buffer is full,
+/// so it should be used on a thread or loop of its own, rather than from the audio callback.
+pub struct RingProducer<'ring, const N: usize> {
+    ring: &'ring RingBuffer<N>,
+    phantom: PhantomData<*mut ()>, // not `Clone`, but `Send`
+}
+
+// Safety: Only one producer exists per buffer, and it only writes where the consumer won't read.
+unsafe impl<const N: usize> Send for RingProducer<'_, N> {}
+
+impl<const N: usize> RingProducer<'_, N> {
+    /// Gets how many samples can be pushed right now.
+    #[inline]
+    pub fn free(&self) -> usize {
+        N - RingBuffer::<N>::distance(self.ring.read.load(Ordering::Acquire), self.ring.write.load(Ordering::Relaxed))
+    }
+
+    /// Pushes as many of `samples` as fit, returning how many did.
+    pub fn push(&mut self, samples: &[Sample]) -> usize {
+        let len = samples.len().min(self.free());
+        let write = self.ring.write.load(Ordering::Relaxed);
+        // SAFETY: The free space is only ever read from once the write position moves past it.
+        let write = unsafe { self.ring.write_at(write, &samples[..len]) };
+        self.ring.write.store(write, Ordering::Release);
+        len
+    }
+}
+
+impl<const N: usize> PcmSink for RingProducer<'_, N> {
+    fn write_samples(&mut self, _audio: &Audio<'_, '_>, mut samples: &[Sample]) {
+        loop {
+            samples = &samples[self.push(samples)..];
+            if samples.is_empty() {
+                break;
+            }
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// The reading half of a [`RingBuffer`], which also keeps track of underruns.
+pub struct RingConsumer<'ring, const N: usize> {
+    ring: &'ring RingBuffer<N>,
+    underruns: u64,
+    underrun_samples: u64,
+    phantom: PhantomData<*mut ()>, // not `Clone`, but `Send`
+}
+
+// Safety: Only one consumer exists per buffer, and it only reads where the producer won't write.
+unsafe impl<const N: usize> Send for RingConsumer<'_, N> {}
+
+impl<const N: usize> RingConsumer<'_, N> {
+    /// Gets how many samples can be popped right now.
+    #[inline]
+    pub fn len(&self) -> usize {
+        RingBuffer::<N>::distance(self.ring.read.load(Ordering::Relaxed), self.ring.write.load(Ordering::Acquire))
+    }
+
+    /// Checks whether there's no samples to pop right now.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pops as many samples as are available into `dest`, returning how many were.
+    pub fn pop(&mut self, dest: &mut [Sample]) -> usize {
+        let len = dest.len().min(self.len());
+        let read = self.ring.read.load(Ordering::Relaxed);
+        // SAFETY: The filled space is only ever written to once the read position moves past it.
+        let read = unsafe { self.ring.read_at(read, &mut dest[..len]) };
+        self.ring.read.store(read, Ordering::Release);
+        len
+    }
+
+    /// Fills all of `dest`, with silence for what isn't available, and returns how many samples were.
+    ///
+    /// Coming up short counts as an [underrun](Self::underruns).
+    pub fn pop_or_silence(&mut self, dest: &mut [Sample]) -> usize {
+        let len = self.pop(dest);
+        if len < dest.len() {
+            dest[len..].iter_mut().for_each(|x| *x = Sample::default());
+            self.underruns += 1;
+            self.underrun_samples += (dest.len() - len) as u64;
+        }
+        len
+    }
+
+    /// Gets how many times [`pop_or_silence`](Self::pop_or_silence) had to fill in silence.
+    #[inline]
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// Gets how many samples of silence [`pop_or_silence`](Self::pop_or_silence) filled in in total.
+    #[inline]
+    pub fn underrun_samples(&self) -> u64 {
+        self.underrun_samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+    use crate::Sample;
+
+    #[test]
+    fn ring() {
+        let mut ring = RingBuffer::<4>::new();
+        let (mut producer, mut consumer) = ring.split();
+        let samples = [1i16, 2, 3, 4, 5, 6].map(Sample::from);
+        let mut dest = [Sample::default(); 3];
+
+        assert_eq!(producer.push(&samples), 4);
+        assert_eq!(producer.free(), 0);
+        assert_eq!(consumer.pop(&mut dest), 3);
+        assert_eq!(dest, [1i16, 2, 3].map(Sample::from));
+
+        // wrapping around the end
+        assert_eq!(producer.push(&samples[4..]), 2);
+        assert_eq!(consumer.len(), 3);
+        assert_eq!(consumer.pop_or_silence(&mut dest), 3);
+        assert_eq!(dest, [4i16, 5, 6].map(Sample::from));
+        assert_eq!(consumer.underruns(), 0);
+
+        assert_eq!(consumer.pop_or_silence(&mut dest), 0);
+        assert_eq!(dest, [Sample::default(); 3]);
+        assert_eq!((consumer.underruns(), consumer.underrun_samples()), (1, 3));
+    }
+}
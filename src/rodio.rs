@@ -0,0 +1,157 @@
+//! Playing streams with [rodio](https://docs.rs/rodio).
+//!
+//! [`Mp3Source`] implements rodio's [`Source`] over a [`DecoderOwned`],
+//! so it can be given straight to a `Sink` in place of rodio's own decoder.
+
+use crate::{duration::to_duration, DecoderOwned, Frame, Sample, MAX_SAMPLES_PER_FRAME};
+use ::rodio::Source;
+use core::time::Duration;
+
+/// A rodio [`Source`] which plays a whole stream from a [`DecoderOwned`].
+///
+/// Samples are 16-bit integers, or 32-bit floats with the `float` feature, and the channel count
+/// and sample rate follow the stream if they change mid-stream. Frames are decoded one at a time
+/// as they're played, into a buffer of the source's own.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{rodio::Mp3Source, DecoderOwned};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let source = Mp3Source::new(DecoderOwned::new(mp3));
+/// // sink.append(source);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Mp3Source<T> {
+    decoder: DecoderOwned<T>,
+    total_duration: Option<Duration>,
+
+    // the frame being played, and how far into it
+    pcm: Box<[Sample; MAX_SAMPLES_PER_FRAME]>,
+    position: usize,
+    len: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl<T> Mp3Source<T> {
+    /// Constructs a new `Mp3Source` playing `decoder` from where it is.
+    ///
+    /// The [total duration](Source::total_duration) is what's left of the stream from there,
+    /// found from [`DecoderOwned::duration`] less the samples before the position.
+    /// This steps through every frame before the position,
+    /// and every frame after it too if the stream doesn't start with a [`XingHeader`](crate::XingHeader).
+    pub fn new(mut decoder: DecoderOwned<T>) -> Self {
+        let played = decoder.decoder.samples_played();
+        let total_duration = decoder
+            .duration()
+            .map(|x| to_duration(x.sample_count().saturating_sub(played), x.sample_rate()));
+        let mut source = Self {
+            decoder,
+            total_duration,
+            pcm: Box::new([Sample::default(); MAX_SAMPLES_PER_FRAME]),
+            position: 0,
+            len: 0,
+            // rodio needs a format even if there's no audio
+            channels: 2,
+            sample_rate: 44100,
+        };
+        source.decode();
+        source
+    }
+
+    /// Gets a reference to the decoder.
+    #[inline]
+    pub fn decoder(&self) -> &DecoderOwned<T> {
+        &self.decoder
+    }
+
+    /// Consumes the `Mp3Source`, returning the decoder, which is past the frame being played.
+    #[inline]
+    pub fn into_inner(self) -> DecoderOwned<T> {
+        self.decoder
+    }
+
+    /// Decodes the next audio frame into the buffer, skipping over anything else.
+    fn decode(&mut self) {
+        self.position = 0;
+        self.len = 0;
+        while let Some(frame) = self.decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                let samples = audio.samples();
+                // frames without samples would end the source early
+                if samples.is_empty() {
+                    continue;
+                }
+                self.pcm[..samples.len()].copy_from_slice(samples);
+                self.len = samples.len();
+                self.channels = audio.channels();
+                self.sample_rate = audio.sample_rate();
+                break;
+            }
+        }
+    }
+}
+
+impl<T> Iterator for Mp3Source<T> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        if self.position == self.len {
+            return None;
+        }
+        let sample = self.pcm[self.position];
+        self.position += 1;
+        // the next frame is decoded right away, so that its format is known at the boundary
+        if self.position == self.len {
+            self.decode();
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.position, None)
+    }
+}
+
+impl<T> Source for Mp3Source<T> {
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.len - self.position)
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::Mp3Source;
+    use crate::{duration::to_duration, test_util::FrameBuilder, DecoderOwned};
+    use ::rodio::Source;
+
+    #[test]
+    fn remaining_duration() {
+        let mp3 = FrameBuilder::new().stream(10).unwrap();
+        let mut decoder = DecoderOwned::new(mp3);
+        decoder.seek_to_sample(4000).unwrap();
+        let source = Mp3Source::new(decoder);
+        assert_eq!(source.total_duration(), Some(to_duration(10 * 1152 - 4000, 44100)));
+        assert_eq!(source.count(), (10 * 1152 - 4000) * 2);
+    }
+}
@@ -0,0 +1,481 @@
+//! Iterating over decoded samples regardless of where the frames are.
+
+use crate::{
+    dsp::{Interpolation, RateConverter},
+    Decoder,
+    Frame,
+    InlinePcm,
+    PcmStorage,
+    Sample,
+};
+use core::{ptr, slice};
+
+/// Iterator over the samples of a [`Decoder`], continuing across frame boundaries,
+/// returned by [`Decoder::samples`].
+///
+/// Samples are interleaved if there's more than one channel, and frames which aren't audio
+/// are skipped over. Since the channel count and sample rate may change mid-stream,
+/// the ones for the next sample can be looked up with [`channels`](Self::channels)
+/// and [`sample_rate`](Self::sample_rate), or kept fixed with
+/// [`FormatPolicy::Conform`](crate::FormatPolicy::Conform).
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::Decoder;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let first_second: Vec<_> = decoder.samples().take(44100 * 2).collect();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Samples<'dec, 'src, P: PcmStorage = InlinePcm> {
+    decoder: &'dec mut Decoder<'src, P>,
+
+    // samples of the current frame, which live in the decoder's storage
+    pcm: *const Sample,
+    len: usize,
+    position: usize,
+
+    channels: u16,
+    sample_rate: u32,
+}
+
+// Safety: The samples belong to the decoder, which is borrowed for as long.
+unsafe impl<P: PcmStorage + Send> Send for Samples<'_, '_, P> {}
+unsafe impl<P: PcmStorage + Sync> Sync for Samples<'_, '_, P> {}
+
+impl<'dec, 'src, P: PcmStorage> Samples<'dec, 'src, P> {
+    pub(crate) fn new(decoder: &'dec mut Decoder<'src, P>) -> Self {
+        Self {
+            decoder,
+            pcm: ptr::null(),
+            len: 0,
+            position: 0,
+            channels: 0,
+            sample_rate: 0,
+        }
+    }
+
+    /// Gets the channel count of the next sample, decoding the frame it's in if needed.
+    ///
+    /// Returns `None` if there are no samples left.
+    pub fn channels(&mut self) -> Option<u16> {
+        self.fill()?;
+        Some(self.channels)
+    }
+
+    /// Gets the sample rate of the next sample, decoding the frame it's in if needed.
+    ///
+    /// Returns `None` if there are no samples left.
+    pub fn sample_rate(&mut self) -> Option<u32> {
+        self.fill()?;
+        Some(self.sample_rate)
+    }
+
+    /// Turns this into an iterator over sample frames of `CH` channels,
+    /// such as `[left, right]` for stereo.
+    ///
+    /// Audio frames with any other channel count are skipped, so the stream should be
+    /// kept at one with [`FormatPolicy::Conform`](crate::FormatPolicy::Conform)
+    /// or [`DecoderOptions::mono`](crate::DecoderOptions::mono).
+    #[inline]
+    pub fn frames<const CH: usize>(self) -> SampleFrames<'dec, 'src, CH, P> {
+        SampleFrames { samples: self }
+    }
+
+    /// Turns this into an iterator over the samples of one channel, counting from zero.
+    ///
+    /// Mono frames yield their only channel for any `index`, as with
+    /// [`Audio::left`](crate::Audio::left) and [`Audio::right`](crate::Audio::right).
+    /// This should be called before any samples are taken, so it starts on the first channel.
+    #[inline]
+    pub fn channel(self, index: usize) -> ChannelSamples<'dec, 'src, P> {
+        ChannelSamples { samples: self, index }
+    }
+
+    /// Turns this into an iterator over the samples of the left channel.
+    ///
+    /// See [`channel`](Self::channel) for more info.
+    #[inline]
+    pub fn left(self) -> ChannelSamples<'dec, 'src, P> {
+        self.channel(0)
+    }
+
+    /// Turns this into an iterator over the samples of the right channel.
+    ///
+    /// See [`channel`](Self::channel) for more info.
+    #[inline]
+    pub fn right(self) -> ChannelSamples<'dec, 'src, P> {
+        self.channel(1)
+    }
+
+    /// Turns this into an iterator over the samples converted to `rate`, such as for a DAC which only runs at 48 kHz.
+    ///
+    /// See [`Resampled`] for more info. Returns `None` if `rate` is zero.
+    pub fn output_rate(self, rate: u32) -> Option<Resampled<'dec, 'src, P>> {
+        Some(Resampled {
+            samples: self,
+            converter: RateConverter::new(rate)?,
+            output: [Sample::default(); RESAMPLED_LEN],
+            len: 0,
+            position: 0,
+        })
+    }
+
+    /// Turns this into a reader of blocks of `N` samples each, which start every `hop` samples,
+    /// so `hop` being less than `N` makes them overlap, such as for spectral analysis.
+    ///
+    /// See [`Blocks`] for more info.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hop` is zero or greater than `N`.
+    pub fn blocks<const N: usize>(self, hop: usize) -> Blocks<'dec, 'src, N, P> {
+        assert!(hop != 0 && hop <= N, "hop of {} doesn't fit blocks of {} samples", hop, N);
+        Blocks {
+            samples: self,
+            block: [Sample::default(); N],
+            filled: 0,
+            hop,
+        }
+    }
+
+    /// Reads the next samples into `dest`, decoding as many frames as needed,
+    /// and returns how many were read, which is less than its length only at the end of the stream.
+    ///
+    /// This is the same as taking them from the iterator, but frames that fit in whole are decoded
+    /// straight into `dest`, so without the `float` feature, minimp3 writes the samples right where
+    /// they're going, such as into the halves of a buffer a DMA controller sends to an I2S peripheral.
+    /// Only frames which don't fit are decoded into the decoder's storage first,
+    /// and the rest of them is read by the next call.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rmp3::Decoder;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mp3 = [];
+    /// #[repr(align(4))]
+    /// struct Aligned([i16; 2048]);
+    ///
+    /// let mut buffer = Aligned([0; 2048]);
+    /// let mut decoder = Decoder::new(&mp3);
+    /// let mut samples = decoder.samples();
+    /// let (first, second) = buffer.0.split_at_mut(1024);
+    /// # #[cfg(not(feature = "float"))]
+    /// samples.read(first);
+    /// // start the DMA transfer here, and on each half-transfer interrupt:
+    /// # #[cfg(not(feature = "float"))]
+    /// samples.read(second);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read(&mut self, dest: &mut [Sample]) -> usize {
+        let mut read = 0;
+        while read < dest.len() {
+            let rest = &mut dest[read..];
+            if self.position == self.len {
+                let needed = match self.decoder.peek() {
+                    // big enough for decoding before converting channels, like the decoder checks
+                    Some(Frame::Audio(audio)) => {
+                        audio.sample_count() * usize::from(audio.channels().max(audio.header().channels()))
+                    },
+                    Some(_) => 0,
+                    None => break,
+                };
+                if needed <= rest.len() {
+                    // whatever is in `rest` isn't a previous frame, so there's nothing to repeat
+                    self.decoder.raw.last_frame = None;
+                    // SAFETY: `rest` is valid for that many samples, and only the frame borrows it.
+                    let frame = unsafe { self.decoder.next_into(rest.as_mut_ptr(), rest.len(), usize::MAX) };
+                    // nor is there after, as the frame isn't in the storage which `fill` decodes into
+                    self.decoder.raw.last_frame = None;
+                    match frame {
+                        Some(Frame::Audio(audio)) => {
+                            let samples = audio.samples();
+                            // moved back over what `seek_to_sample` trimmed off, if anything
+                            unsafe { ptr::copy(samples.as_ptr(), rest.as_mut_ptr(), samples.len()) };
+                            self.channels = audio.channels();
+                            self.sample_rate = audio.sample_rate();
+                            read += samples.len();
+                        },
+                        Some(_) => (),
+                        None => break,
+                    }
+                    continue;
+                }
+                if self.fill().is_none() {
+                    break;
+                }
+            }
+            let samples = self.rest();
+            let len = samples.len().min(rest.len());
+            rest[..len].copy_from_slice(&samples[..len]);
+            self.position += len;
+            read += len;
+        }
+        read
+    }
+
+    /// Decodes frames until one has samples left, if there are any.
+    fn fill(&mut self) -> Option<()> {
+        while self.position == self.len {
+            if let Frame::Audio(audio) = self.decoder.next()? {
+                let samples = audio.samples();
+                self.pcm = samples.as_ptr();
+                self.len = samples.len();
+                self.position = 0;
+                self.channels = audio.channels();
+                self.sample_rate = audio.sample_rate();
+            }
+        }
+        Some(())
+    }
+
+    /// Gets the rest of the samples in the current frame.
+    fn rest(&self) -> &[Sample] {
+        // SAFETY: The samples stay in the decoder's storage until it decodes again,
+        // which it only does through `fill`, as it's borrowed mutably.
+        unsafe { slice::from_raw_parts(self.pcm.add(self.position), self.len - self.position) }
+    }
+}
+
+impl<P: PcmStorage> Iterator for Samples<'_, '_, P> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        self.fill()?;
+        let sample = self.rest()[0];
+        self.position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.position, None)
+    }
+}
+
+/// Iterator over sample frames of `CH` channels from a [`Decoder`],
+/// returned by [`Samples::frames`].
+pub struct SampleFrames<'dec, 'src, const CH: usize, P: PcmStorage = InlinePcm> {
+    samples: Samples<'dec, 'src, P>,
+}
+
+impl<const CH: usize, P: PcmStorage> Iterator for SampleFrames<'_, '_, CH, P> {
+    type Item = [Sample; CH];
+
+    fn next(&mut self) -> Option<[Sample; CH]> {
+        let samples = &mut self.samples;
+        while samples.fill().is_some() {
+            if samples.channels as usize != CH {
+                samples.position = samples.len;
+                continue;
+            }
+            let mut frame = [Sample::default(); CH];
+            frame.copy_from_slice(&samples.rest()[..CH]);
+            samples.position += CH;
+            return Some(frame);
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.samples.channels as usize {
+            channels if channels == CH && CH != 0 => ((self.samples.len - self.samples.position) / CH, None),
+            _ => (0, None),
+        }
+    }
+}
+
+/// Iterator over the samples of one channel from a [`Decoder`],
+/// returned by [`Samples::channel`].
+pub struct ChannelSamples<'dec, 'src, P: PcmStorage = InlinePcm> {
+    samples: Samples<'dec, 'src, P>,
+    index: usize,
+}
+
+impl<P: PcmStorage> Iterator for ChannelSamples<'_, '_, P> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        let samples = &mut self.samples;
+        samples.fill()?;
+        let channels = samples.channels as usize;
+        let sample = samples.rest()[self.index.min(channels - 1)];
+        samples.position += channels;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.samples.channels as usize {
+            0 => (0, None),
+            channels => ((self.samples.len - self.samples.position) / channels, None),
+        }
+    }
+}
+
+/// How many samples a [`Resampled`] converts at once.
+const RESAMPLED_LEN: usize = 64;
+
+/// Iterator over the samples of a [`Decoder`] at one sample rate, returned by [`Samples::output_rate`].
+///
+/// The samples go through a [`RateConverter`], so sample rate changes mid-stream are followed,
+/// and audio which is already at the rate is passed through as-is. As with that, the channel count
+/// isn't converted, so for a fixed one too, see [`DecoderOptions::output_layout`](crate::DecoderOptions::output_layout).
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{dsp::Interpolation, Decoder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let samples = decoder.samples().output_rate(48000).unwrap().interpolation(Interpolation::Sinc);
+/// for sample in samples {
+///     // play `sample` at 48 kHz here!
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Resampled<'dec, 'src, P: PcmStorage = InlinePcm> {
+    samples: Samples<'dec, 'src, P>,
+    converter: RateConverter,
+
+    // samples converted so far, of which everything before `position` was yielded
+    output: [Sample; RESAMPLED_LEN],
+    len: usize,
+    position: usize,
+}
+
+impl<P: PcmStorage> Resampled<'_, '_, P> {
+    /// Sets how the samples are resampled, which is [linear](Interpolation::Linear) by default.
+    #[inline]
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.converter = self.converter.interpolation(interpolation);
+        self
+    }
+}
+
+impl<P: PcmStorage> Iterator for Resampled<'_, '_, P> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Sample> {
+        while self.position == self.len {
+            let samples = &mut self.samples;
+            self.position = 0;
+            if samples.fill().is_none() {
+                // which leaves what the converter held back
+                self.len = self.converter.flush(&mut self.output);
+                if self.len == 0 {
+                    return None;
+                }
+                break;
+            }
+            let (consumed, written) =
+                self.converter.process(samples.rest(), samples.sample_rate, samples.channels, &mut self.output);
+            samples.position += consumed;
+            self.len = written;
+        }
+        let sample = self.output[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+/// Reader of fixed-size blocks of samples from a [`Decoder`], regardless of frame sizes,
+/// returned by [`Samples::blocks`].
+///
+/// Blocks hold interleaved samples like [`Samples`] yields them, so for analysing a single channel,
+/// [`DecoderOptions::mono`](crate::DecoderOptions::mono) may be useful.
+/// The last block is padded with silence, and isn't returned if it would only be padding
+/// and samples from the previous block.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::Decoder;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut decoder = Decoder::new(&mp3);
+/// let mut blocks = decoder.samples().blocks::<1024>(512);
+/// while let Some(block) = blocks.next() {
+///     // run an FFT over the block here!
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Blocks<'dec, 'src, const N: usize, P: PcmStorage = InlinePcm> {
+    samples: Samples<'dec, 'src, P>,
+
+    // samples carried over from earlier frames, and how many of them are there
+    block: [Sample; N],
+    filled: usize,
+    hop: usize,
+}
+
+impl<const N: usize, P: PcmStorage> Blocks<'_, '_, N, P> {
+    /// Reads the next block, decoding as many frames as needed.
+    #[allow(clippy::should_implement_trait)] // it lends out the block, so it can't be an `Iterator`
+    pub fn next(&mut self) -> Option<&[Sample; N]> {
+        if self.filled == N {
+            // the last block was returned, so keep what overlaps with this one
+            self.block.copy_within(self.hop.., 0);
+            self.filled = N - self.hop;
+        }
+        let carried = self.filled;
+        while self.filled < N {
+            if self.samples.fill().is_none() {
+                if self.filled == carried {
+                    return None;
+                }
+                self.block[self.filled..].iter_mut().for_each(|x| *x = Sample::default());
+                self.filled = N;
+                break;
+            }
+            let rest = self.samples.rest();
+            let len = rest.len().min(N - self.filled);
+            self.block[self.filled..self.filled + len].copy_from_slice(&rest[..len]);
+            self.samples.position += len;
+            self.filled += len;
+        }
+        Some(&self.block)
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::{test_util::FrameBuilder, Decoder, Sample, MAX_SAMPLES_PER_FRAME};
+
+    #[test]
+    fn output_rate() {
+        let mut mp3 = FrameBuilder::new().stream(10).unwrap();
+        mp3.extend(FrameBuilder::new().sample_rate(48000).stream(10).unwrap());
+        // minimp3 doesn't sync to a frame followed by another sample rate otherwise
+        let mut decoder = Decoder::with_options(&mp3, crate::DecoderOptions::new().sync_matches(0));
+        // 44.1 kHz is resampled, and 48 kHz is passed through
+        let count = decoder.samples().output_rate(48000).unwrap().count();
+        assert_eq!(count, (10 * 1152 * 48000 / 44100 + 1 + 10 * 1152) * 2);
+    }
+
+    #[test]
+    fn read_doesnt_conceal_with_storage() {
+        let mp3 = FrameBuilder::new().stream(3).unwrap();
+        let mut decoder = Decoder::new(&mp3);
+        let mut samples = decoder.samples();
+        // a whole frame fits, so it's decoded straight into `pcm`
+        let mut pcm = [Sample::default(); MAX_SAMPLES_PER_FRAME];
+        assert_eq!(samples.read(&mut pcm), MAX_SAMPLES_PER_FRAME);
+        // the next frame would be concealed with the decoder's own storage, which was never written
+        assert_eq!(samples.decoder.raw.last_frame, None);
+
+        // a short read decodes into the decoder's storage instead
+        assert_eq!(samples.read(&mut pcm[..10]), 10);
+        assert!(samples.decoder.raw.last_frame.is_some());
+    }
+}
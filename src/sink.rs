@@ -0,0 +1,55 @@
+//! Receiving decoded audio from a whole stream.
+
+use crate::{Audio, Sample};
+
+/// Something which receives decoded audio one frame at a time,
+/// such as a file writer, a ring buffer for playback, or an analyzer.
+///
+/// [`Decoder::decode_into`](crate::Decoder::decode_into) drives a sink through a whole stream,
+/// so implementing this is all that's needed instead of writing the decoding loop by hand.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{Audio, Decoder, PcmSink, Sample};
+///
+/// struct Peak(Sample);
+///
+/// impl PcmSink for Peak {
+///     fn write_samples(&mut self, _audio: &Audio<'_, '_>, samples: &[Sample]) {
+///         for &sample in samples {
+///             if sample > self.0 {
+///                 self.0 = sample;
+///             }
+///         }
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mp3 = std::fs::read("test.mp3")?;
+/// let mut peak = Peak(Sample::default());
+/// Decoder::new(&mp3).decode_into(&mut peak);
+/// # Ok(())
+/// # }
+/// ```
+pub trait PcmSink {
+    /// Receives the interleaved samples of an audio frame, along with the frame for its metadata,
+    /// such as the channel count and sample rate, which may change mid-stream.
+    fn write_samples(&mut self, audio: &Audio<'_, '_>, samples: &[Sample]);
+}
+
+impl<S: PcmSink + ?Sized> PcmSink for &mut S {
+    #[inline]
+    fn write_samples(&mut self, audio: &Audio<'_, '_>, samples: &[Sample]) {
+        (**self).write_samples(audio, samples)
+    }
+}
+
+/// Appends the samples, regardless of format.
+#[cfg(feature = "alloc")]
+impl PcmSink for alloc::vec::Vec<Sample> {
+    #[inline]
+    fn write_samples(&mut self, _audio: &Audio<'_, '_>, samples: &[Sample]) {
+        self.extend_from_slice(samples);
+    }
+}
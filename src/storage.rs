@@ -0,0 +1,65 @@
+//! Where a [`Decoder`](crate::Decoder) keeps the samples of the frame it decoded last.
+
+use crate::{Sample, MAX_SAMPLES_PER_FRAME};
+use core::mem::MaybeUninit;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Storage for the samples a [`Decoder`](crate::Decoder) decodes into, one frame at a time.
+///
+/// By default, they're stored inline, which makes the decoder big to move around.
+/// This is also implemented for buffers borrowed from the caller, such as a static buffer
+/// in a specific RAM section on microcontrollers, or one from a pool in an audio engine,
+/// and with the `alloc` feature, for boxed ones.
+///
+/// This trait is sealed, and can't be implemented outside of this crate.
+pub trait PcmStorage: private::Sealed {
+    #[doc(hidden)]
+    fn as_mut_ptr(&mut self) -> *mut [Sample; MAX_SAMPLES_PER_FRAME];
+}
+
+/// Samples stored inline in the [`Decoder`](crate::Decoder), which is the default.
+pub struct InlinePcm(MaybeUninit<[Sample; MAX_SAMPLES_PER_FRAME]>);
+
+impl InlinePcm {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self(MaybeUninit::uninit())
+    }
+}
+
+impl private::Sealed for InlinePcm {}
+impl PcmStorage for InlinePcm {
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut [Sample; MAX_SAMPLES_PER_FRAME] {
+        self.0.as_mut_ptr()
+    }
+}
+
+impl private::Sealed for &mut [Sample; MAX_SAMPLES_PER_FRAME] {}
+impl PcmStorage for &mut [Sample; MAX_SAMPLES_PER_FRAME] {
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut [Sample; MAX_SAMPLES_PER_FRAME] {
+        *self
+    }
+}
+
+impl private::Sealed for &mut MaybeUninit<[Sample; MAX_SAMPLES_PER_FRAME]> {}
+impl PcmStorage for &mut MaybeUninit<[Sample; MAX_SAMPLES_PER_FRAME]> {
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut [Sample; MAX_SAMPLES_PER_FRAME] {
+        MaybeUninit::as_mut_ptr(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl private::Sealed for alloc::boxed::Box<[Sample; MAX_SAMPLES_PER_FRAME]> {}
+#[cfg(feature = "alloc")]
+impl PcmStorage for alloc::boxed::Box<[Sample; MAX_SAMPLES_PER_FRAME]> {
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut [Sample; MAX_SAMPLES_PER_FRAME] {
+        &mut **self
+    }
+}
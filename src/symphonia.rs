@@ -0,0 +1,271 @@
+//! Using this crate's decoder from [Symphonia](https://docs.rs/symphonia).
+//!
+//! [`Mp3Decoder`] implements Symphonia's [`Decoder`] trait over a [`RawDecoder`],
+//! and [`register`] adds it to a [`CodecRegistry`], so that it's used for MPEG Audio
+//! in place of Symphonia's own decoder, while the rest of a pipeline stays the same.
+//! [`Mp3Reader`] implements Symphonia's [`FormatReader`] trait over a [`DecoderOwned`],
+//! for splitting streams into packets with this crate too.
+
+use crate::{DecoderOwned, Frame, RawDecoder, Sample, MAX_SAMPLES_PER_FRAME, PREROLL_FRAMES};
+use std::io::Read;
+use symphonia_core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec},
+    codecs::{
+        CodecDescriptor, CodecParameters, CodecRegistry, Decoder, DecoderOptions, FinalizeResult, CODEC_TYPE_MP1,
+        CODEC_TYPE_MP2, CODEC_TYPE_MP3,
+    },
+    errors::{decode_error, end_of_stream_error, seek_error, unsupported_error, Result, SeekErrorKind},
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::MediaSourceStream,
+    meta::{Metadata, MetadataLog},
+    support_codec,
+    units::TimeBase,
+};
+
+/// Registers [`Mp3Decoder`] for every MPEG Audio layer this crate was built to decode.
+///
+/// The codecs it decodes are replaced if they were registered before, such as by
+/// `symphonia::default::register_enabled_codecs`, so this should be called after that.
+///
+/// # Example
+///
+/// ```no_run
+/// use symphonia_core::codecs::CodecRegistry;
+///
+/// let mut codecs = CodecRegistry::new();
+/// // symphonia::default::register_enabled_codecs(&mut codecs);
+/// rmp3::symphonia::register(&mut codecs);
+/// ```
+pub fn register(registry: &mut CodecRegistry) {
+    registry.register_all::<Mp3Decoder>();
+}
+
+/// A Symphonia [`Decoder`] for MPEG Audio, which decodes with minimp3.
+///
+/// Every packet has to be one whole frame, as Symphonia's MP3 format reader splits them.
+/// The output is 16-bit PCM, or 32-bit floats with the `float` feature.
+pub struct Mp3Decoder {
+    buffer: AudioBuffer<Sample>,
+    params: CodecParameters,
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    raw: RawDecoder,
+}
+
+impl Decoder for Mp3Decoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        if !Self::supported_codecs().iter().any(|x| x.codec == params.codec) {
+            return unsupported_error("rmp3: unsupported codec");
+        }
+        Ok(Self {
+            buffer: AudioBuffer::unused(),
+            params: params.clone(),
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            raw: RawDecoder::new(),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[
+            #[cfg(feature = "mp1-mp2")]
+            support_codec!(CODEC_TYPE_MP1, "mp1", "MPEG Audio Layer 1 (minimp3)"),
+            #[cfg(feature = "mp1-mp2")]
+            support_codec!(CODEC_TYPE_MP2, "mp2", "MPEG Audio Layer 2 (minimp3)"),
+            support_codec!(CODEC_TYPE_MP3, "mp3", "MPEG Audio Layer 3 (minimp3)"),
+        ]
+    }
+
+    fn reset(&mut self) {
+        self.raw = RawDecoder::with_options(self.raw.options());
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buffer.clear();
+        let audio = match self.raw.next(packet.buf(), &mut self.pcm) {
+            Some((Frame::Audio(audio), _)) => audio,
+            // this is where the first frame's audio would be, so there's none
+            Some((Frame::Xing { .. }, _)) => return Ok(self.buffer.as_audio_buffer_ref()),
+            _ => return decode_error("rmp3: invalid frame"),
+        };
+
+        let channels = match audio.channels() {
+            1 => Channels::FRONT_LEFT,
+            _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+        };
+        let spec = SignalSpec::new(audio.sample_rate(), channels);
+        if *self.buffer.spec() != spec {
+            self.buffer = AudioBuffer::new((MAX_SAMPLES_PER_FRAME / 2) as u64, spec);
+        }
+        self.buffer.render_reserved(Some(audio.sample_count()));
+        for channel in 0..usize::from(audio.channels()) {
+            for (dest, x) in self.buffer.chan_mut(channel).iter_mut().zip(audio.channel(channel)) {
+                *dest = x;
+            }
+        }
+        Ok(self.buffer.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buffer.as_audio_buffer_ref()
+    }
+}
+
+/// A Symphonia [`FormatReader`] for MPEG Audio streams, which finds frames with this crate.
+///
+/// Every packet is one whole frame, as [`Mp3Decoder`] and Symphonia's own decoder expect.
+/// The whole stream is read into memory when it's constructed, so this isn't for live streams.
+/// There's a single track, and tags aren't read, so the metadata is always empty.
+///
+/// Seeking steps through the frames by their headers, and lands a couple of frames before the
+/// one with the required timestamp, so that the bit reservoir is filled by the time it's decoded.
+/// Packets before [`SeekedTo::required_ts`] should be decoded and discarded, as with other readers.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::symphonia::Mp3Reader;
+/// use symphonia_core::{formats::{FormatOptions, FormatReader}, io::MediaSourceStream};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("test.mp3")?;
+/// let source = MediaSourceStream::new(Box::new(file), Default::default());
+/// let mut reader = Mp3Reader::try_new(source, &FormatOptions::default())?;
+/// while let Ok(packet) = reader.next_packet() {
+///     // decode `packet` here!
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Mp3Reader {
+    decoder: DecoderOwned<Vec<u8>>,
+    metadata: MetadataLog,
+    source: MediaSourceStream,
+    tracks: Vec<Track>,
+
+    // where the first audio frame is, and the timestamp of the next packet
+    start: usize,
+    ts: u64,
+}
+
+impl FormatReader for Mp3Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut data = Vec::new();
+        source.read_to_end(&mut data)?;
+        let mut decoder = DecoderOwned::new(data);
+        let (start, header) = loop {
+            match decoder.peek() {
+                Some(Frame::Audio(audio)) => break (audio.offset_in_source(), audio.header()),
+                Some(_) => {
+                    decoder.skip();
+                },
+                None => return unsupported_error("rmp3: no MPEG Audio frames"),
+            }
+        };
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(match header.mpeg_layer() {
+                1 => CODEC_TYPE_MP1,
+                2 => CODEC_TYPE_MP2,
+                _ => CODEC_TYPE_MP3,
+            })
+            .with_sample_rate(header.sample_rate())
+            .with_time_base(TimeBase::new(1, header.sample_rate()))
+            .with_channels(match header.channels() {
+                1 => Channels::FRONT_LEFT,
+                _ => Channels::FRONT_LEFT | Channels::FRONT_RIGHT,
+            })
+            .with_max_frames_per_packet(header.sample_count() as u64);
+        if let Some(duration) = decoder.duration() {
+            params.with_n_frames(duration.sample_count());
+        }
+
+        Ok(Self {
+            decoder,
+            metadata: MetadataLog::default(),
+            source,
+            tracks: vec![Track::new(0, params)],
+            start,
+            ts: 0,
+        })
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &[]
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let required_ts = match to {
+            SeekTo::TimeStamp { ts, track_id: 0 } => ts,
+            SeekTo::Time { time, track_id: None | Some(0) } => match self.tracks[0].codec_params.time_base {
+                Some(time_base) => time_base.calc_timestamp(time),
+                None => return seek_error(SeekErrorKind::Unseekable),
+            },
+            _ => return seek_error(SeekErrorKind::InvalidTrack),
+        };
+
+        // where and at which timestamp the last few frames up to the required one are
+        let mut frames = [(self.start, 0); PREROLL_FRAMES + 1];
+        let mut count = 0;
+        let mut ts = 0;
+        self.decoder.set_position(self.start);
+        loop {
+            match self.decoder.peek() {
+                Some(Frame::Audio(audio)) => {
+                    frames[count % frames.len()] = (audio.offset_in_source(), ts);
+                    count += 1;
+                    ts += audio.sample_count() as u64;
+                    if ts > required_ts {
+                        break;
+                    }
+                    self.decoder.skip();
+                },
+                Some(_) => {
+                    self.decoder.skip();
+                },
+                None => return seek_error(SeekErrorKind::OutOfRange),
+            }
+        }
+        let (position, actual_ts) = frames[(count - 1).saturating_sub(PREROLL_FRAMES) % frames.len()];
+        self.decoder.set_position(position);
+        self.ts = actual_ts;
+        Ok(SeekedTo { track_id: 0, required_ts, actual_ts })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        loop {
+            match self.decoder.peek() {
+                Some(Frame::Audio(audio)) => {
+                    let dur = audio.sample_count() as u64;
+                    let packet = Packet::new_from_slice(0, self.ts, dur, audio.source());
+                    self.ts += dur;
+                    self.decoder.skip();
+                    return Ok(packet);
+                },
+                Some(_) => {
+                    self.decoder.skip();
+                },
+                None => return end_of_stream_error(),
+            }
+        }
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.source
+    }
+}
@@ -0,0 +1,203 @@
+//! Helpers for testing code built on this crate, without committing binary fixtures.
+
+use crate::{frame_crc, ChannelMode, FrameHeader};
+use alloc::vec::Vec;
+
+/// Builds valid MPEG Audio frames of silence in any format, such as for unit testing decode paths.
+///
+/// The frames have all-zero side information and no audio data, which decoders output as silence
+/// of exactly one frame's worth of samples. Frames with a tone would need an MP3 encoder,
+/// which is out of scope for this crate.
+///
+/// # Example
+///
+/// ```no_run
+/// use rmp3::{test_util::FrameBuilder, ChannelMode, Decoder, Frame};
+///
+/// let mp3 = FrameBuilder::new()
+///     .sample_rate(22050)
+///     .bitrate(64)
+///     .channel_mode(ChannelMode::Mono)
+///     .stream(20)
+///     .unwrap();
+/// let mut decoder = Decoder::new(&mp3);
+/// while let Some(frame) = decoder.next() {
+///     if let Frame::Audio(audio) = frame {
+///         assert_eq!(audio.sample_rate(), 22050);
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FrameBuilder {
+    bitrate: u32,
+    channel_mode: ChannelMode,
+    crc: bool,
+    layer: u8,
+    padding: bool,
+    sample_rate: u32,
+}
+
+impl FrameBuilder {
+    /// Constructs a `FrameBuilder` for MPEG-1 layer III frames at 128 kb/s, 44.1 kHz, joint stereo,
+    /// without a checksum or padding.
+    pub const fn new() -> Self {
+        Self {
+            bitrate: 128,
+            channel_mode: ChannelMode::JointStereo,
+            crc: false,
+            layer: 3,
+            padding: false,
+            sample_rate: 44100,
+        }
+    }
+
+    /// Sets the bitrate in kb/s, which must be one the layer and MPEG version allow.
+    #[inline]
+    pub fn bitrate(mut self, bitrate: u32) -> Self {
+        self.bitrate = bitrate;
+        self
+    }
+
+    /// Sets the channel mode.
+    #[inline]
+    pub fn channel_mode(mut self, mode: ChannelMode) -> Self {
+        self.channel_mode = mode;
+        self
+    }
+
+    /// Sets whether frames are protected by a CRC-16 checksum, which is filled in correctly.
+    ///
+    /// Checksums aren't supported for layer II (see [`FrameHeader::crc_bytes`]).
+    #[inline]
+    pub fn crc(mut self, crc: bool) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// Sets the MPEG layer, which is 1, 2 or 3.
+    ///
+    /// Decoding layer I and II frames needs the `mp1-mp2` feature.
+    #[inline]
+    pub fn layer(mut self, layer: u8) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Sets whether frames are padded by a slot.
+    #[inline]
+    pub fn padding(mut self, padding: bool) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the sample rate in Hz, which also decides the MPEG version.
+    #[inline]
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Gets the header of the frames this builds.
+    ///
+    /// Returns `None` if there's no such header, such as for a bitrate that isn't allowed,
+    /// or MPEG-2.5 outside of layer III.
+    pub fn header(&self) -> Option<FrameHeader> {
+        if !(1..=3).contains(&self.layer) || self.bitrate == 0 || (self.crc && self.layer == 2) {
+            return None;
+        }
+        let mode = match self.channel_mode {
+            ChannelMode::Stereo => 0b00,
+            ChannelMode::JointStereo => 0b01,
+            ChannelMode::DualChannel => 0b10,
+            ChannelMode::Mono => 0b11,
+        };
+        // the version and indices are searched for, rather than duplicating the header tables
+        for version in [0b11, 0b10, 0b00] {
+            let byte1 = 0xE0 | version << 3 | (4 - self.layer) << 1 | !self.crc as u8;
+            for sample_rate in 0..3 {
+                for bitrate in 1..15 {
+                    let byte2 = bitrate << 4 | sample_rate << 2 | (self.padding as u8) << 1;
+                    let header = match FrameHeader::parse(&[0xFF, byte1, byte2, mode << 6]) {
+                        Some(header) => header,
+                        None => continue,
+                    };
+                    if header.sample_rate() == self.sample_rate && header.bitrate() == Some(self.bitrate) {
+                        return Some(header);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Builds one frame, or returns `None` if the [`header`](Self::header) doesn't exist.
+    pub fn build(&self) -> Option<Vec<u8>> {
+        let mut frame = Vec::new();
+        self.build_into(&mut frame)?;
+        Some(frame)
+    }
+
+    /// Builds `count` frames in a row, as a whole stream would be,
+    /// or returns `None` if the [`header`](Self::header) doesn't exist.
+    ///
+    /// Decoders need a few frames in a row to synchronize on (see
+    /// [`DecoderOptions::sync_matches`](crate::DecoderOptions::sync_matches)),
+    /// so this is more useful for testing them than a single frame.
+    pub fn stream(&self, count: usize) -> Option<Vec<u8>> {
+        let mut stream = Vec::new();
+        for _ in 0..count {
+            self.build_into(&mut stream)?;
+        }
+        Some(stream)
+    }
+
+    /// Appends one frame to `dest`.
+    fn build_into(&self, dest: &mut Vec<u8>) -> Option<()> {
+        let header = self.header()?;
+        let start = dest.len();
+        dest.extend_from_slice(&header.bytes());
+        dest.resize(start + header.frame_bytes()?, 0);
+        if self.crc {
+            let crc = frame_crc(&dest[start..])?;
+            dest[start + 4..start + 6].copy_from_slice(&crc.to_be_bytes());
+        }
+        Some(())
+    }
+}
+
+impl Default for FrameBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameBuilder;
+    use crate::{ChannelMode, Version};
+
+    #[test]
+    fn build() {
+        let frame = FrameBuilder::new().build().unwrap();
+        assert_eq!(frame.len(), 417);
+        assert_eq!(frame[..4], [0xFF, 0xFB, 0x90, 0x40]);
+
+        let header = FrameBuilder::new().sample_rate(8000).bitrate(8).channel_mode(ChannelMode::Mono).header().unwrap();
+        assert_eq!(header.version(), Version::Mpeg2_5);
+        assert_eq!((header.sample_rate(), header.bitrate(), header.channels()), (8000, Some(8), 1));
+
+        assert!(FrameBuilder::new().bitrate(129).build().is_none());
+        assert!(FrameBuilder::new().sample_rate(8000).layer(2).build().is_none());
+        assert!(FrameBuilder::new().layer(2).crc(true).build().is_none());
+
+        // the checksum is filled in
+        let frame = FrameBuilder::new().layer(1).bitrate(32).crc(true).padding(true).build().unwrap();
+        assert_eq!(frame.len(), 36);
+        assert_eq!(frame[4..6], crate::frame_crc(&frame).unwrap().to_be_bytes());
+
+        // and a stream is found as such
+        let stream = FrameBuilder::new().stream(12).unwrap();
+        assert_eq!(stream.len(), 417 * 12);
+        assert_eq!(crate::header::find_frame(&stream, &mut 0, 10, usize::MAX), (0, 417));
+    }
+}
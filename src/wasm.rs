@@ -0,0 +1,260 @@
+//! A small JavaScript interface over [wasm-bindgen](https://docs.rs/wasm-bindgen).
+//!
+//! Everything is exported as [`JsDecoder`], which outputs interleaved float samples
+//! as a `Float32Array`, ready for the Web Audio API, and [`JsWorkletDecoder`],
+//! which fills the planar output blocks of an `AudioWorkletProcessor`.
+
+use crate::{dsp::to_f32, Decoder, Frame, RawDecoder, Sample, LOOKAHEAD, MAX_SAMPLES_PER_FRAME};
+use js_sys::Float32Array;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+/// Decoder for use from JavaScript, decoding either a whole file or a stream of chunks.
+///
+/// The channel count and sample rate are those of the last frame which was decoded.
+///
+/// # Example
+///
+/// ```js
+/// import { JsDecoder } from "rmp3";
+///
+/// const decoder = new JsDecoder();
+/// for await (const chunk of response.body) {
+///     const pcm = decoder.push(chunk);
+///     // play pcm here, with decoder.channels and decoder.sampleRate!
+/// }
+/// const rest = decoder.flush();
+/// ```
+#[wasm_bindgen]
+pub struct JsDecoder {
+    buffer: Vec<u8>,
+    channels: u16,
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    raw: RawDecoder,
+    sample_rate: u32,
+}
+
+#[wasm_bindgen]
+impl JsDecoder {
+    /// Constructs a new `JsDecoder`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            channels: 0,
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            raw: RawDecoder::new(),
+            sample_rate: 0,
+        }
+    }
+
+    /// Gets the channel count of the last decoded frame, or 0 if there wasn't any.
+    #[wasm_bindgen(getter)]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Gets the sample rate of the last decoded frame, or 0 if there wasn't any.
+    #[wasm_bindgen(getter, js_name = sampleRate)]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Decodes an entire file, forgetting any stream that was being pushed.
+    #[wasm_bindgen(js_name = decodeAll)]
+    pub fn decode_all(&mut self, data: &[u8]) -> Float32Array {
+        self.reset();
+        let mut pcm = Vec::new();
+        let mut decoder = Decoder::new(data);
+        while let Some(frame) = decoder.next() {
+            if let Frame::Audio(audio) = frame {
+                self.channels = audio.channels();
+                self.sample_rate = audio.sample_rate();
+                pcm.extend(audio.samples().iter().copied().map(to_f32));
+            }
+        }
+        Float32Array::from(&pcm[..])
+    }
+
+    /// Appends a chunk of a stream, and decodes all frames which are certainly complete.
+    ///
+    /// The last few frames are held back until there's enough data after them,
+    /// or until [`flush`](Self::flush) is called at the end of the stream.
+    pub fn push(&mut self, chunk: &[u8]) -> Float32Array {
+        self.buffer.extend_from_slice(chunk);
+        self.drain(LOOKAHEAD)
+    }
+
+    /// Decodes everything that's still buffered, for when the stream has ended.
+    pub fn flush(&mut self) -> Float32Array {
+        let pcm = self.drain(0);
+        self.buffer.clear();
+        pcm
+    }
+
+    /// Forgets the stream, ready to decode a new one.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.channels = 0;
+        self.raw = RawDecoder::with_options(self.raw.options());
+        self.sample_rate = 0;
+    }
+}
+
+impl JsDecoder {
+    /// Decodes frames while more than `lookahead` bytes are buffered, and drops what was decoded.
+    fn drain(&mut self, lookahead: usize) -> Float32Array {
+        let mut pcm = Vec::new();
+        let mut position = 0;
+        while self.buffer.len() - position > lookahead {
+            let (frame, consumed) = match self.raw.next(&self.buffer[position..], &mut self.pcm) {
+                Some(next) => next,
+                None => break,
+            };
+            if let Frame::Audio(audio) = frame {
+                self.channels = audio.channels();
+                self.sample_rate = audio.sample_rate();
+                pcm.extend(audio.samples().iter().copied().map(to_f32));
+            }
+            position += consumed;
+        }
+        self.buffer.drain(..position);
+        Float32Array::from(&pcm[..])
+    }
+}
+
+impl Default for JsDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decoder for use from an `AudioWorkletProcessor`, which fills its output blocks
+/// with planar float samples, carrying what's left of each frame over to the next block.
+///
+/// Data is pushed as it arrives, and frames are only decoded once their samples are needed,
+/// so that no more than a frame is decoded per block, which keeps the audio thread's work even.
+/// Output is always stereo, with mono frames in both channels. The sample rate isn't converted,
+/// so it should be the `AudioContext`'s, or resampled before.
+///
+/// # Example
+///
+/// ```js
+/// import { JsWorkletDecoder } from "rmp3";
+///
+/// class Mp3Processor extends AudioWorkletProcessor {
+///     decoder = new JsWorkletDecoder();
+///
+///     constructor() {
+///         super();
+///         this.port.onmessage = ({ data }) => data ? this.decoder.push(data) : this.decoder.end();
+///     }
+///
+///     process(inputs, outputs) {
+///         const [left, right] = outputs[0];
+///         this.decoder.process(left, right);
+///         return true;
+///     }
+/// }
+/// ```
+#[wasm_bindgen]
+pub struct JsWorkletDecoder {
+    buffer: Vec<u8>,
+    ended: bool,
+    pcm: [Sample; MAX_SAMPLES_PER_FRAME],
+    position: usize,
+    queue: [VecDeque<f32>; 2],
+    raw: RawDecoder,
+    sample_rate: u32,
+}
+
+#[wasm_bindgen]
+impl JsWorkletDecoder {
+    /// Constructs a new `JsWorkletDecoder`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            ended: false,
+            pcm: [Sample::default(); MAX_SAMPLES_PER_FRAME],
+            position: 0,
+            queue: [VecDeque::new(), VecDeque::new()],
+            raw: RawDecoder::new(),
+            sample_rate: 0,
+        }
+    }
+
+    /// Gets the sample rate of the last decoded frame, or 0 if there wasn't any.
+    #[wasm_bindgen(getter, js_name = sampleRate)]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Appends a chunk of a stream, to be decoded as it's played.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.drain(..self.position);
+        self.position = 0;
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Marks the end of the stream, so that the last frames are decoded too.
+    pub fn end(&mut self) {
+        self.ended = true;
+    }
+
+    /// Fills one output block per channel, such as the ones an `AudioWorkletProcessor` is given
+    /// in `process`, and returns whether there were enough samples for all of it.
+    ///
+    /// If there weren't, such as when data arrives too slowly or at the end of the stream,
+    /// the rest is filled with silence.
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) -> bool {
+        let len = left.len().min(right.len());
+        while self.queue[0].len() < len && self.decode_next() {}
+        let [queue_left, queue_right] = &mut self.queue;
+        let filled = len.min(queue_left.len());
+        for (dest, queue) in [(left, queue_left), (right, queue_right)] {
+            for (x, y) in dest.iter_mut().zip(queue.drain(..filled)) {
+                *x = y;
+            }
+            dest[filled..].iter_mut().for_each(|x| *x = 0.0);
+        }
+        filled == len
+    }
+
+    /// Forgets the stream, ready to decode a new one.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.ended = false;
+        self.position = 0;
+        self.queue.iter_mut().for_each(VecDeque::clear);
+        self.raw = RawDecoder::with_options(self.raw.options());
+        self.sample_rate = 0;
+    }
+}
+
+impl JsWorkletDecoder {
+    /// Decodes the next frame into the queue, returning whether there was one.
+    fn decode_next(&mut self) -> bool {
+        let rest = &self.buffer[self.position..];
+        if !self.ended && rest.len() <= LOOKAHEAD {
+            return false;
+        }
+        let (frame, consumed) = match self.raw.next(rest, &mut self.pcm) {
+            Some(next) => next,
+            None => return false,
+        };
+        self.position += consumed;
+        if let Frame::Audio(audio) = frame {
+            self.sample_rate = audio.sample_rate();
+            self.queue[0].extend(audio.left().map(to_f32));
+            self.queue[1].extend(audio.right().map(to_f32));
+        }
+        true
+    }
+}
+
+impl Default for JsWorkletDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,373 @@
+//! Parsing of the Xing/Info header and LAME tag, which encoders put in place of the first frame's audio.
+
+use crate::header;
+use core::convert::TryInto;
+
+const FLAG_FRAMES: u32 = 0x1;
+const FLAG_BYTES: u32 = 0x2;
+const FLAG_TOC: u32 = 0x4;
+const FLAG_QUALITY: u32 = 0x8;
+
+/// A Xing or Info header, which describes the whole stream for seeking and duration purposes.
+///
+/// These are stored in an otherwise silent layer III frame at the start of the stream,
+/// as `Xing` in VBR streams, and `Info` in CBR streams (as LAME does).
+/// Every field is optional, so encoders may leave out any of them.
+///
+/// Decoders return these frames as [`Frame::Xing`](crate::Frame::Xing) instead of audio.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct XingHeader<'src> {
+    bytes: Option<u32>,
+    frames: Option<u32>,
+    quality: Option<u32>,
+    lame: Option<LameInfo>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_toc"))]
+    toc: Option<&'src [u8; 100]>,
+    vbr: bool,
+}
+
+/// The LAME tag, which LAME and compatible encoders (like FFmpeg's) store after the [`XingHeader`].
+///
+/// Most notably, this stores the encoder delay and padding needed for gapless playback,
+/// as in how many samples at the start and end of the stream are only there because of encoding.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct LameInfo {
+    album_gain: u16,
+    ath_type: u8,
+    bitrate: u8,
+    encoder: [u8; 9],
+    encoder_delay: u16,
+    encoding_flags: u8,
+    lowpass: u8,
+    misc: u8,
+    mp3_gain: i8,
+    music_crc: u16,
+    music_length: u32,
+    padding: u16,
+    peak: u32,
+    preset: u16,
+    revision: u8,
+    tag_crc: u16,
+    track_gain: u16,
+    vbr_method: u8,
+}
+
+// serde only implements arrays of up to 32 elements, but slices work the same
+#[cfg(feature = "serde")]
+fn serialize_toc<S: serde::Serializer>(toc: &Option<&[u8; 100]>, serializer: S) -> Result<S::Ok, S::Error> {
+    serde::Serialize::serialize(&toc.map(|x| &x[..]), serializer)
+}
+
+impl<'src> XingHeader<'src> {
+    /// Parses a Xing/Info header from a whole frame, starting with its frame header.
+    ///
+    /// Returns `None` if the frame isn't layer III, or doesn't contain one.
+    pub fn parse(frame: &'src [u8]) -> Option<Self> {
+        let header = header::read(frame)?;
+        if header.mpeg_layer() != 3 {
+            return None;
+        }
+        let mut data = frame.get(4 + if header.has_crc() { 2 } else { 0 } + header.side_info_bytes()..)?;
+        let vbr = match take(&mut data, 4)? {
+            b"Xing" => true,
+            b"Info" => false,
+            _ => return None,
+        };
+        let flags = take_u32(&mut data)?;
+
+        let frames = if flags & FLAG_FRAMES != 0 { Some(take_u32(&mut data)?) } else { None };
+        let bytes = if flags & FLAG_BYTES != 0 { Some(take_u32(&mut data)?) } else { None };
+        let toc = if flags & FLAG_TOC != 0 {
+            Some(take(&mut data, 100)?.try_into().ok()?)
+        } else {
+            None
+        };
+        let quality = if flags & FLAG_QUALITY != 0 { Some(take_u32(&mut data)?) } else { None };
+        let lame = LameInfo::parse(data);
+
+        Some(Self { bytes, frames, lame, quality, toc, vbr })
+    }
+
+    /// Gets the length of the stream in bytes, including this frame, if it's stored.
+    #[inline]
+    pub fn bytes(&self) -> Option<u32> {
+        self.bytes
+    }
+
+    /// Gets the amount of frames in the stream, excluding this frame, if it's stored.
+    #[inline]
+    pub fn frames(&self) -> Option<u32> {
+        self.frames
+    }
+
+    /// Gets the LAME tag which follows this header, if there's one.
+    #[inline]
+    pub fn lame(&self) -> Option<&LameInfo> {
+        self.lame.as_ref()
+    }
+
+    /// Gets whether this is a `Xing` header, which encoders write in VBR streams,
+    /// as opposed to an `Info` header, which LAME writes in CBR streams.
+    #[inline]
+    pub fn is_vbr(&self) -> bool {
+        self.vbr
+    }
+
+    /// Gets the encoder's quality indicator from 0 (best) to 100 (worst), if it's stored.
+    #[inline]
+    pub fn quality(&self) -> Option<u32> {
+        self.quality
+    }
+
+    /// Gets the table of contents, if it's stored.
+    ///
+    /// Entry `i` is where `i`% of the way through the stream's duration is,
+    /// as a fraction of [`bytes`](Self::bytes) out of 256.
+    #[inline]
+    pub fn toc(&self) -> Option<&'src [u8; 100]> {
+        self.toc
+    }
+
+    /// Gets the approximate byte offset from the start of this frame at which
+    /// `fraction` (from 0.0 to 1.0) of the way through the stream's duration is,
+    /// using the table of contents.
+    ///
+    /// Returns `None` if either the table of contents or the length in bytes isn't stored.
+    pub fn seek_offset(&self, fraction: f64) -> Option<u64> {
+        let (toc, bytes) = (self.toc?, self.bytes?);
+        let percent = (fraction * 100.0).clamp(0.0, 100.0);
+
+        // linear interpolation between entries, with an implicit 256 after the last one
+        let index = (percent as usize).min(99);
+        let start = f64::from(toc[index]);
+        let end = toc.get(index + 1).map_or(256.0, |&x| f64::from(x));
+        let position = start + (end - start) * (percent - index as f64);
+        Some((position / 256.0 * f64::from(bytes)) as u64)
+    }
+}
+
+impl LameInfo {
+    /// Parses the tag from right after the Xing/Info header fields.
+    fn parse(mut data: &[u8]) -> Option<Self> {
+        let mut encoder = [0; 9];
+        encoder.copy_from_slice(take(&mut data, 9)?);
+        if !matches!(&encoder[..4], b"LAME" | b"Lavf" | b"Lavc") {
+            return None;
+        }
+        let data = take(&mut data, 27)?;
+        let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        Some(Self {
+            album_gain: u16_at(8),
+            ath_type: data[10] & 0x0F,
+            bitrate: data[11],
+            encoder,
+            encoder_delay: u16_at(12) >> 4,
+            encoding_flags: data[10] >> 4,
+            lowpass: data[1],
+            misc: data[15],
+            mp3_gain: data[16] as i8,
+            music_crc: u16_at(23),
+            music_length: u32::from_be_bytes([data[19], data[20], data[21], data[22]]),
+            padding: u16_at(13) & 0x0FFF,
+            peak: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+            preset: u16_at(17) & 0x07FF,
+            revision: data[0] >> 4,
+            tag_crc: u16_at(25),
+            track_gain: u16_at(6),
+            vbr_method: data[0] & 0x0F,
+        })
+    }
+
+    /// Gets the album (audiophile) ReplayGain adjustment in dB, if it's stored.
+    #[inline]
+    pub fn album_gain(&self) -> Option<f32> {
+        replay_gain(self.album_gain)
+    }
+
+    /// Gets the ATH (absolute threshold of hearing) type the encoder used.
+    #[inline]
+    pub fn ath_type(&self) -> u8 {
+        self.ath_type
+    }
+
+    /// Gets the bitrate in kb/s, which is the target for ABR streams,
+    /// the minimum for VBR streams, and the bitrate for CBR streams.
+    /// A value of 255 means 255 kb/s or higher.
+    #[inline]
+    pub fn bitrate(&self) -> u8 {
+        self.bitrate
+    }
+
+    /// Gets the encoder version string, such as `LAME3.100`, which is padded with zeroes or spaces.
+    #[inline]
+    pub fn encoder(&self) -> &[u8; 9] {
+        &self.encoder
+    }
+
+    /// Gets the amount of samples (per channel) the encoder added at the start of the stream.
+    ///
+    /// This doesn't include the decoder delay of 529 samples.
+    #[inline]
+    pub fn encoder_delay(&self) -> u16 {
+        self.encoder_delay
+    }
+
+    /// Gets the raw 4-bit encoding flags (`0b0001` nspsytune, `0b0010` nssafejoint,
+    /// `0b0100` nogap continued, `0b1000` nogap continuing).
+    #[inline]
+    pub fn encoding_flags(&self) -> u8 {
+        self.encoding_flags
+    }
+
+    /// Gets the lowpass frequency in Hz, rounded to hundreds, if it's stored.
+    #[inline]
+    pub fn lowpass(&self) -> Option<u32> {
+        match self.lowpass {
+            0 => None,
+            x => Some(u32::from(x) * 100),
+        }
+    }
+
+    /// Gets the raw misc byte, which stores the noise shaping (bits 0-1), stereo mode (bits 2-4),
+    /// whether unwise settings were used (bit 5) and the source sample rate (bits 6-7).
+    #[inline]
+    pub fn misc(&self) -> u8 {
+        self.misc
+    }
+
+    /// Gets the global gain change applied by mp3gain, in steps of 1.5 dB.
+    #[inline]
+    pub fn mp3_gain(&self) -> i8 {
+        self.mp3_gain
+    }
+
+    /// Gets the CRC-16 of the audio data (the frames after this one), as stored.
+    #[inline]
+    pub fn music_crc(&self) -> u16 {
+        self.music_crc
+    }
+
+    /// Gets the length of the stream in bytes, including this frame, as stored.
+    #[inline]
+    pub fn music_length(&self) -> u32 {
+        self.music_length
+    }
+
+    /// Gets the amount of samples (per channel) the encoder added at the end of the stream.
+    #[inline]
+    pub fn padding(&self) -> u16 {
+        self.padding
+    }
+
+    /// Gets the peak amplitude of the stream, where 1.0 is full scale, if it's stored.
+    #[inline]
+    pub fn peak(&self) -> Option<f32> {
+        match self.peak {
+            0 => None,
+            x => Some(x as f32 / (1 << 23) as f32),
+        }
+    }
+
+    /// Gets the preset the encoder used, such as 1001 for `--preset standard`, if it's stored.
+    #[inline]
+    pub fn preset(&self) -> Option<u16> {
+        match self.preset {
+            0 => None,
+            x => Some(x),
+        }
+    }
+
+    /// Gets the revision of the tag format.
+    #[inline]
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Gets the CRC-16 of the frame up until this field, as stored.
+    #[inline]
+    pub fn tag_crc(&self) -> u16 {
+        self.tag_crc
+    }
+
+    /// Gets the track (radio) ReplayGain adjustment in dB, if it's stored.
+    #[inline]
+    pub fn track_gain(&self) -> Option<f32> {
+        replay_gain(self.track_gain)
+    }
+
+    /// Gets the raw 4-bit VBR method (1 CBR, 2 ABR, 3-5 VBR, 8 two-pass CBR, 9 two-pass ABR).
+    #[inline]
+    pub fn vbr_method(&self) -> u8 {
+        self.vbr_method
+    }
+}
+
+/// Decodes a ReplayGain field, which stores a 3-bit name (0 being unset), a 3-bit originator,
+/// a sign bit, and the adjustment in tenths of a dB.
+fn replay_gain(field: u16) -> Option<f32> {
+    if field >> 13 == 0 {
+        return None;
+    }
+    let gain = f32::from(field & 0x1FF) / 10.0;
+    Some(if field & 0x200 != 0 { -gain } else { gain })
+}
+
+fn take<'src>(data: &mut &'src [u8], len: usize) -> Option<&'src [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Some(head)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    take(data, 4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XingHeader;
+
+    #[test]
+    fn parse() {
+        // MPEG-1 layer III, 128 kb/s, 44.1 kHz, mono, so the tag is 17 bytes in
+        let mut frame = [0; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+        frame[21..25].copy_from_slice(b"Xing");
+        frame[25..29].copy_from_slice(&0x7u32.to_be_bytes());
+        frame[29..33].copy_from_slice(&1000u32.to_be_bytes());
+        frame[33..37].copy_from_slice(&256_000u32.to_be_bytes());
+        for (i, x) in frame[37..137].iter_mut().enumerate() {
+            *x = (i * 256 / 100) as u8;
+        }
+
+        frame[137..146].copy_from_slice(b"LAME3.100");
+        frame[152..154].copy_from_slice(&[0x2E, 0x41]);
+        frame[158..161].copy_from_slice(&[0x24, 0x03, 0xE8]);
+
+        let xing = XingHeader::parse(&frame).unwrap();
+        assert!(xing.is_vbr());
+        assert_eq!(xing.frames(), Some(1000));
+        assert_eq!(xing.bytes(), Some(256_000));
+        assert_eq!(xing.quality(), None);
+        assert_eq!(xing.seek_offset(0.0), Some(0));
+        assert_eq!(xing.seek_offset(0.5), Some(128_000));
+        assert_eq!(xing.seek_offset(2.0), Some(256_000));
+
+        let lame = xing.lame().unwrap();
+        assert_eq!(lame.encoder(), b"LAME3.100");
+        assert_eq!(lame.encoder_delay(), 576);
+        assert_eq!(lame.padding(), 1000);
+        assert_eq!(lame.track_gain(), Some(-6.5));
+        assert_eq!(lame.album_gain(), None);
+        assert_eq!(lame.peak(), None);
+
+        frame[21..25].copy_from_slice(b"Nope");
+        assert_eq!(XingHeader::parse(&frame), None);
+    }
+}